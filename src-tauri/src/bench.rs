@@ -0,0 +1,565 @@
+use crate::error::PandaError;
+use crate::http_runner::{self, PandaTomlRequest, PreparedRequest, RunOptions};
+use crate::utils::parse_toml;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch::Receiver;
+
+/// Upper bounds of the latency histogram buckets in milliseconds, the last bucket is open.
+const HISTOGRAM_BOUNDS: [f64; 12] = [
+    1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0,
+];
+
+/// How much load a benchmark generates.
+///
+/// The run stops after `iterations` or once `duration` has elapsed, whichever comes
+/// first.
+#[derive(Clone, Debug)]
+pub struct BenchOptions {
+    /// Number of requests in flight at the same time.
+    pub concurrency: usize,
+    /// Times the sequence of requests is sent.
+    pub iterations: Option<usize>,
+    pub duration: Option<Duration>,
+    /// Target requests per second across all workers, `None` sends as fast as possible.
+    pub rate: Option<f64>,
+}
+
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct LatencyStats {
+    pub min: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+/// Responses that took at most `le` milliseconds and more than the previous bucket,
+/// `le` is `None` for the last one.
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct HistogramBucket {
+    pub le: Option<f64>,
+    pub count: usize,
+}
+
+/// Results of one file of the sequence.
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct FileReport {
+    pub file: String,
+    pub requests: usize,
+    pub failed: usize,
+    pub latency: Option<LatencyStats>,
+}
+
+/// Times are in milliseconds.
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct BenchReport {
+    pub requests: usize,
+    pub succeeded: usize,
+    /// Requests that could not be sent and responses with a 4xx or 5xx status.
+    pub failed: usize,
+    pub elapsed_time: f64,
+    /// Requests per second.
+    pub throughput: f64,
+    pub concurrency: usize,
+    /// Failures by category, the error kind eg. `timeout` or `status_5xx`.
+    pub errors: BTreeMap<String, usize>,
+    pub statuses: BTreeMap<u16, usize>,
+    /// Time until the whole response body was read, `None` when no response was
+    /// received.
+    pub latency: Option<LatencyStats>,
+    pub histogram: Vec<HistogramBucket>,
+    pub files: Vec<FileReport>,
+    pub cancelled: bool,
+}
+
+/// A response or the category of the error.
+struct Sample {
+    file: usize,
+    latency: Option<f64>,
+    outcome: Result<u16, &'static str>,
+}
+
+/// Hands out evenly spaced send times to hold the target rate.
+struct Pacer {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl Pacer {
+    fn reserve(&self) -> Instant {
+        let mut next = self.next.lock().unwrap();
+
+        // Slots missed while the workers were busy are not caught up with a burst.
+        let slot = (*next).max(Instant::now());
+        *next = slot + self.interval;
+
+        slot
+    }
+}
+
+struct Shared {
+    requests: Vec<PreparedRequest>,
+    next_iteration: AtomicUsize,
+    iterations: Option<usize>,
+    deadline: Option<Instant>,
+    pacer: Option<Pacer>,
+    cancelled_rx: Receiver<bool>,
+}
+
+impl Shared {
+    fn should_stop(&self) -> bool {
+        *self.cancelled_rx.borrow() || self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+}
+
+/// Sends the requests of `files` in order, again and again from `options.concurrency`
+/// workers.
+///
+/// Each file is read and its variables substituted once, its client and connection
+/// pool are shared by every worker so the numbers reflect the server rather than new
+/// connections.
+pub async fn run_bench(
+    project_root: &Path,
+    files: &[PathBuf],
+    run_options: &RunOptions,
+    options: &BenchOptions,
+    cancelled_rx: Receiver<bool>,
+) -> Result<BenchReport, PandaError> {
+    if files.is_empty() {
+        return Err(PandaError::Config("No request to benchmark.".into()));
+    }
+
+    let names: Vec<String> = files
+        .iter()
+        .map(|path| {
+            path.strip_prefix(project_root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect();
+
+    let mut requests = vec![];
+
+    for (path, name) in files.iter().zip(&names) {
+        requests.push(prepare(path, name, run_options)?);
+    }
+
+    let concurrency = options.concurrency.max(1);
+    let started = Instant::now();
+
+    let pacer = match options.rate.filter(|rate| *rate > 0.0) {
+        Some(rate) => Some(Pacer {
+            interval: Duration::try_from_secs_f64(1.0 / rate).map_err(|_| {
+                PandaError::Config(format!("Invalid rate of {} requests per second.", rate).into())
+            })?,
+            next: Mutex::new(started),
+        }),
+        None => None,
+    };
+
+    let shared = Arc::new(Shared {
+        requests,
+        next_iteration: AtomicUsize::new(0),
+        iterations: options.iterations,
+        deadline: options.duration.map(|d| started + d),
+        pacer,
+        cancelled_rx,
+    });
+
+    let workers: Vec<_> = (0..concurrency)
+        .map(|_| tokio::spawn(worker(Arc::clone(&shared))))
+        .collect();
+
+    let mut samples = vec![];
+
+    for worker in workers {
+        samples.extend(
+            worker
+                .await
+                .map_err(|err| PandaError::Network(err.to_string().into()))?,
+        );
+    }
+
+    let elapsed = started.elapsed();
+    let cancelled = *shared.cancelled_rx.borrow();
+
+    Ok(report(&names, samples, elapsed, concurrency, cancelled))
+}
+
+fn prepare(path: &Path, name: &str, options: &RunOptions) -> Result<PreparedRequest, PandaError> {
+    let source = fs::read_to_string(path)
+        .map_err(|err| PandaError::Io(format!("Failed to read {:?}: {}", path, err).into()))?;
+
+    let request: PandaTomlRequest =
+        parse_toml(&source, &options.variables, Some(name.to_string()))?;
+
+    let prepared = http_runner::prepare_request(request, options)?;
+
+    if prepared.try_clone().is_none() {
+        return Err(PandaError::Request(
+            format!("{} can't be sent repeatedly, its body is streamed.", name).into(),
+        ));
+    }
+
+    Ok(prepared)
+}
+
+async fn worker(shared: Arc<Shared>) -> Vec<Sample> {
+    let mut samples = vec![];
+
+    loop {
+        if shared.should_stop() {
+            break;
+        }
+
+        let iteration = shared.next_iteration.fetch_add(1, Ordering::Relaxed);
+
+        if shared.iterations.is_some_and(|total| iteration >= total) {
+            break;
+        }
+
+        for (file, request) in shared.requests.iter().enumerate() {
+            if let Some(pacer) = &shared.pacer {
+                tokio::time::sleep_until(pacer.reserve().into()).await;
+            }
+
+            if shared.should_stop() {
+                return samples;
+            }
+
+            // Checked when preparing the request.
+            let request = request.try_clone().unwrap();
+
+            samples.push(send(file, request).await);
+        }
+    }
+
+    samples
+}
+
+async fn send(file: usize, request: PreparedRequest) -> Sample {
+    let started = Instant::now();
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(err) => {
+            return Sample {
+                file,
+                latency: None,
+                outcome: Err(err.kind()),
+            }
+        }
+    };
+
+    let status = response.status().as_u16();
+
+    // The latency includes reading the body, dropping it unread would close the connection.
+    if let Err(err) = response.bytes().await {
+        return Sample {
+            file,
+            latency: None,
+            outcome: Err(PandaError::from(err).kind()),
+        };
+    }
+
+    Sample {
+        file,
+        latency: Some(started.elapsed().as_secs_f64() * 1000.0),
+        outcome: Ok(status),
+    }
+}
+
+fn report(
+    names: &[String],
+    samples: Vec<Sample>,
+    elapsed: Duration,
+    concurrency: usize,
+    cancelled: bool,
+) -> BenchReport {
+    let mut errors = BTreeMap::new();
+    let mut statuses = BTreeMap::new();
+    let mut latencies = vec![];
+    let mut file_latencies = vec![vec![]; names.len()];
+    let mut file_failures = vec![0; names.len()];
+    let mut file_requests = vec![0; names.len()];
+
+    for sample in &samples {
+        file_requests[sample.file] += 1;
+
+        if let Some(latency) = sample.latency {
+            latencies.push(latency);
+            file_latencies[sample.file].push(latency);
+        }
+
+        let category = match sample.outcome {
+            Ok(status) => {
+                *statuses.entry(status).or_insert(0) += 1;
+
+                match status {
+                    400..=499 => Some("status_4xx"),
+                    500.. => Some("status_5xx"),
+                    _ => None,
+                }
+            }
+            Err(kind) => Some(kind),
+        };
+
+        if let Some(category) = category {
+            *errors.entry(category.to_string()).or_insert(0) += 1;
+            file_failures[sample.file] += 1;
+        }
+    }
+
+    let failed: usize = errors.values().sum();
+    let seconds = elapsed.as_secs_f64();
+
+    BenchReport {
+        requests: samples.len(),
+        succeeded: samples.len() - failed,
+        failed,
+        elapsed_time: seconds * 1000.0,
+        throughput: match seconds > 0.0 {
+            true => samples.len() as f64 / seconds,
+            false => 0.0,
+        },
+        concurrency,
+        errors,
+        statuses,
+        histogram: histogram(&latencies),
+        latency: latency_stats(&mut latencies),
+        files: names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| FileReport {
+                file: name.clone(),
+                requests: file_requests[i],
+                failed: file_failures[i],
+                latency: latency_stats(&mut file_latencies[i]),
+            })
+            .collect(),
+        cancelled,
+    }
+}
+
+fn latency_stats(latencies: &mut [f64]) -> Option<LatencyStats> {
+    if latencies.is_empty() {
+        return None;
+    }
+
+    latencies.sort_by(f64::total_cmp);
+
+    Some(LatencyStats {
+        min: latencies[0],
+        mean: latencies.iter().sum::<f64>() / latencies.len() as f64,
+        p50: percentile(latencies, 50.0),
+        p90: percentile(latencies, 90.0),
+        p95: percentile(latencies, 95.0),
+        p99: percentile(latencies, 99.0),
+        max: latencies[latencies.len() - 1],
+    })
+}
+
+/// Nearest rank percentile of sorted values.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn histogram(latencies: &[f64]) -> Vec<HistogramBucket> {
+    let mut buckets: Vec<HistogramBucket> = HISTOGRAM_BOUNDS
+        .iter()
+        .map(|le| HistogramBucket {
+            le: Some(*le),
+            count: 0,
+        })
+        .chain([HistogramBucket { le: None, count: 0 }])
+        .collect();
+
+    for latency in latencies {
+        let index = HISTOGRAM_BOUNDS
+            .iter()
+            .position(|le| latency <= le)
+            .unwrap_or(HISTOGRAM_BOUNDS.len());
+
+        buckets[index].count += 1;
+    }
+
+    buckets
+}
+
+impl fmt::Display for LatencyStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "min {:.2}ms  mean {:.2}ms  p50 {:.2}ms  p90 {:.2}ms  p95 {:.2}ms  p99 {:.2}ms  max {:.2}ms",
+            self.min, self.mean, self.p50, self.p90, self.p95, self.p99, self.max
+        )
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} requests in {:.2}s with {} workers, {:.1} req/s{}",
+            self.requests,
+            self.elapsed_time / 1000.0,
+            self.concurrency,
+            self.throughput,
+            if self.cancelled { " (cancelled)" } else { "" }
+        )?;
+        writeln!(f, "{} succeeded, {} failed", self.succeeded, self.failed)?;
+
+        if !self.errors.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Errors:")?;
+
+            for (category, count) in &self.errors {
+                writeln!(f, "  {:<12} {}", category, count)?;
+            }
+        }
+
+        if !self.statuses.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Status codes:")?;
+
+            for (status, count) in &self.statuses {
+                writeln!(f, "  {:<12} {}", status, count)?;
+            }
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Latency:")?;
+        match &self.latency {
+            Some(latency) => writeln!(f, "  {}", latency)?,
+            None => writeln!(f, "  no responses")?,
+        }
+
+        if self.files.len() > 1 {
+            for file in &self.files {
+                writeln!(
+                    f,
+                    "  {}: {} requests, {} failed",
+                    file.file, file.requests, file.failed
+                )?;
+                if let Some(latency) = &file.latency {
+                    writeln!(f, "    {}", latency)?;
+                }
+            }
+        }
+
+        let largest = self.histogram.iter().map(|b| b.count).max().unwrap_or(0);
+
+        // Empty buckets below the fastest and above the slowest response are left out.
+        let first = self.histogram.iter().position(|b| b.count > 0);
+        let last = self.histogram.iter().rposition(|b| b.count > 0);
+
+        if let (Some(first), Some(last)) = (first, last) {
+            writeln!(f)?;
+            writeln!(f, "Histogram:")?;
+
+            for bucket in &self.histogram[first..=last] {
+                let label = match bucket.le {
+                    Some(le) => format!("<= {}ms", le),
+                    None => format!("> {}ms", HISTOGRAM_BOUNDS[HISTOGRAM_BOUNDS.len() - 1]),
+                };
+
+                let bar = "#".repeat((bucket.count * 40).div_ceil(largest));
+
+                writeln!(f, "  {:>10} {:>8} {}", label, bucket.count, bar)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uses_nearest_rank_percentiles() {
+        let mut latencies: Vec<f64> = (1..=10).rev().map(f64::from).collect();
+        let stats = latency_stats(&mut latencies).unwrap();
+
+        assert_eq!((stats.min, stats.max, stats.mean), (1.0, 10.0, 5.5));
+        assert_eq!(
+            (stats.p50, stats.p90, stats.p95, stats.p99),
+            (5.0, 9.0, 10.0, 10.0)
+        );
+
+        assert_eq!(percentile(&[4.0], 1.0), 4.0);
+        assert_eq!(percentile(&[1.0, 2.0, 3.0], 0.0), 1.0);
+        assert!(latency_stats(&mut []).is_none());
+    }
+
+    #[test]
+    fn counts_latencies_in_buckets() {
+        let buckets = histogram(&[0.5, 1.0, 1.5, 5.0, 5.01, 5000.0, 12000.0]);
+
+        assert_eq!(buckets.len(), HISTOGRAM_BOUNDS.len() + 1);
+        let counts: Vec<(Option<f64>, usize)> = buckets
+            .iter()
+            .filter(|b| b.count > 0)
+            .map(|b| (b.le, b.count))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                (Some(1.0), 2),
+                (Some(2.0), 1),
+                (Some(5.0), 1),
+                (Some(10.0), 1),
+                (Some(5000.0), 1),
+                (None, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_failures_by_category() {
+        let names = vec!["a.toml".to_string(), "b.toml".to_string()];
+        let sample = |file, latency, outcome| Sample {
+            file,
+            latency,
+            outcome,
+        };
+
+        let report = report(
+            &names,
+            vec![
+                sample(0, Some(10.0), Ok(200)),
+                sample(0, Some(20.0), Ok(503)),
+                sample(1, Some(30.0), Ok(404)),
+                sample(1, None, Err("timeout")),
+            ],
+            Duration::from_secs(2),
+            4,
+            false,
+        );
+
+        assert_eq!(
+            (report.requests, report.succeeded, report.failed),
+            (4, 1, 3)
+        );
+        assert_eq!(report.throughput, 2.0);
+        assert_eq!(report.errors["status_5xx"], 1);
+        assert_eq!(report.errors["status_4xx"], 1);
+        assert_eq!(report.errors["timeout"], 1);
+        assert_eq!(report.statuses[&200], 1);
+        assert_eq!((report.files[1].requests, report.files[1].failed), (2, 2));
+        assert_eq!(report.files[1].latency.as_ref().unwrap().max, 30.0);
+    }
+}
@@ -0,0 +1,301 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use fancy_regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, Mutex};
+
+/// Binary bodies up to this size are sent inline as base64, larger ones are read in chunks.
+pub const INLINE_BINARY_LIMIT: usize = 1024 * 1024;
+
+/// Number of response bodies kept in memory for chunked reads and saving.
+const MAX_STORED_BODIES: usize = 20;
+
+/// How far into an HTML or XML document charset declarations are looked for.
+const CHARSET_SNIFF_LIMIT: usize = 1024;
+
+/// Matches a `<meta>` charset or an `<?xml?>` encoding, the charset is the first capture group.
+static CHARSET_DECLARATION_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?i)(?:<meta[^>]+charset\s*=\s*["']?|<\?xml[^>]+encoding\s*=\s*["'])([A-Za-z0-9_:.\-]+)"#,
+    )
+    .unwrap()
+});
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyKind {
+    #[default]
+    Text,
+    Binary,
+}
+
+/// Where the encoding used to decode a text body came from.
+#[derive(Deserialize, Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EncodingSource {
+    /// Byte order mark at the start of the body, it overrides everything else.
+    Bom,
+    /// `charset` parameter of the `Content-Type` header.
+    Header,
+    /// `<meta charset>` in HTML or the `<?xml encoding?>` declaration.
+    Document,
+    /// Nothing was declared so UTF-8 was assumed.
+    Default,
+}
+
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct TextEncoding {
+    /// Name of the encoding eg. `windows-1252`, `Shift_JIS` or `UTF-16LE`.
+    pub name: String,
+    pub source: EncodingSource,
+    /// `true` when malformed bytes were replaced with U+FFFD.
+    pub replaced: bool,
+}
+
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct BodyChunk {
+    pub offset: usize,
+    /// Total size of the body in bytes.
+    pub size: usize,
+    /// Base64 encoded bytes of this chunk.
+    pub data: String,
+    pub done: bool,
+}
+
+/// Bodies of the most recent responses keyed by response id.
+#[derive(Default)]
+pub struct ResponseStore {
+    bodies: Mutex<VecDeque<(String, Arc<Vec<u8>>)>>,
+}
+
+impl ResponseStore {
+    pub fn insert(&self, id: String, body: Vec<u8>) {
+        let mut bodies = self.bodies.lock().unwrap();
+
+        if bodies.len() >= MAX_STORED_BODIES {
+            bodies.pop_front();
+        }
+
+        bodies.push_back((id, Arc::new(body)));
+    }
+
+    pub fn get(&self, id: &str) -> Result<Arc<Vec<u8>>, String> {
+        self.bodies
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(body_id, _)| body_id == id)
+            .map(|(_, body)| Arc::clone(body))
+            .ok_or_else(|| format!("Response {:?} is no longer available.", id))
+    }
+
+    pub fn read_chunk(&self, id: &str, offset: usize, length: usize) -> Result<BodyChunk, String> {
+        let body = self.get(id)?;
+
+        let start = offset.min(body.len());
+        let end = start.saturating_add(length).min(body.len());
+
+        Ok(BodyChunk {
+            offset: start,
+            size: body.len(),
+            data: STANDARD.encode(&body[start..end]),
+            done: end == body.len(),
+        })
+    }
+
+    /// Writes the raw body to `path` and returns the number of bytes written.
+    pub fn save(&self, id: &str, path: &Path) -> Result<usize, String> {
+        let body = self.get(id)?;
+
+        fs::write(path, body.as_slice())
+            .map_err(|err| format!("Failed to save response to {:?}: {}", path, err))?;
+
+        Ok(body.len())
+    }
+}
+
+/// Detects the mime type from the body's magic bytes, falling back to the `Content-Type` header.
+pub fn detect_mime_type(body: &[u8], content_type: &str) -> String {
+    if let Some(kind) = infer::get(body) {
+        return kind.mime_type().to_string();
+    }
+
+    let is_text = std::str::from_utf8(body).is_ok() || Encoding::for_bom(body).is_some();
+
+    match content_type.parse::<mime::Mime>() {
+        Ok(m) => m.essence_str().to_string(),
+        Err(_) if is_text => "text/plain".to_string(),
+        Err(_) => "application/octet-stream".to_string(),
+    }
+}
+
+pub fn get_body_kind(body: &[u8], mime_type: &str) -> BodyKind {
+    let m = match mime_type.parse::<mime::Mime>() {
+        Ok(m) => m,
+        Err(_) => return BodyKind::Binary,
+    };
+
+    let is_text = m.type_() == mime::TEXT
+        || matches!(
+            m.subtype().as_str(),
+            "json" | "xml" | "javascript" | "ecmascript" | "x-www-form-urlencoded" | "graphql"
+        )
+        || matches!(m.suffix().map(|s| s.as_str()), Some("json" | "xml"));
+
+    if is_text {
+        return BodyKind::Text;
+    }
+
+    // Servers often mislabel text as `application/octet-stream`.
+    if m == mime::APPLICATION_OCTET_STREAM
+        && infer::get(body).is_none()
+        && std::str::from_utf8(body).is_ok()
+    {
+        return BodyKind::Text;
+    }
+
+    BodyKind::Binary
+}
+
+/// Decodes a text body, the encoding is picked from the BOM, the `Content-Type` charset,
+/// an HTML/XML charset declaration and finally defaults to UTF-8.
+pub fn decode_text(body: &[u8], content_type: &str) -> (String, TextEncoding) {
+    let mime_type = content_type.parse::<mime::Mime>().ok();
+
+    let header_encoding = mime_type
+        .as_ref()
+        .and_then(|m| m.get_param(mime::CHARSET))
+        .and_then(|charset| Encoding::for_label(charset.as_str().as_bytes()));
+
+    let (encoding, source) = match (Encoding::for_bom(body), header_encoding) {
+        (Some((bom_encoding, _)), _) => (bom_encoding, EncodingSource::Bom),
+        (None, Some(encoding)) => (encoding, EncodingSource::Header),
+        (None, None) => match sniff_document_charset(body) {
+            Some(encoding) => (encoding, EncodingSource::Document),
+            None => (UTF_8, EncodingSource::Default),
+        },
+    };
+
+    // `decode` strips the BOM and may switch encodings because of it.
+    let (text, used_encoding, replaced) = encoding.decode(body);
+
+    let text_encoding = TextEncoding {
+        name: used_encoding.name().to_string(),
+        source,
+        replaced,
+    };
+
+    (text.into_owned(), text_encoding)
+}
+
+/// Decodes a header value, header bytes outside ASCII are usually UTF-8 but RFC 9110
+/// treats them as ISO-8859-1 so that is the fallback.
+pub fn decode_header_value(value: &[u8]) -> String {
+    match std::str::from_utf8(value) {
+        Ok(v) => v.to_string(),
+        Err(_) => WINDOWS_1252
+            .decode_without_bom_handling(value)
+            .0
+            .into_owned(),
+    }
+}
+
+/// Looks for `<meta charset>`, `<meta http-equiv="Content-Type">` or `<?xml encoding?>`
+/// near the start of the document.
+fn sniff_document_charset(body: &[u8]) -> Option<&'static Encoding> {
+    let head = &body[..body.len().min(CHARSET_SNIFF_LIMIT)];
+
+    // Declarations are ASCII so a single byte decoding is enough to find them.
+    let (head, _) = WINDOWS_1252.decode_without_bom_handling(head);
+
+    let charset = CHARSET_DECLARATION_REGEX
+        .captures(&head)
+        .ok()??
+        .get(1)?
+        .as_str()
+        .to_string();
+
+    // A document can't declare UTF-16 about itself in an ASCII compatible way, treat it as UTF-8.
+    Encoding::for_label(charset.as_bytes()).map(|e| e.output_encoding())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bom_takes_precedence_over_the_header() {
+        let body = [0xFF, 0xFE, b'h', 0, b'i', 0];
+        let (text, encoding) = decode_text(&body, "text/plain; charset=iso-8859-1");
+
+        assert_eq!(text, "hi");
+        assert_eq!(encoding.name, "UTF-16LE");
+        assert_eq!(encoding.source, EncodingSource::Bom);
+    }
+
+    #[test]
+    fn header_charset_takes_precedence_over_the_document() {
+        let body = b"<meta charset=\"utf-8\"><p>caf\xe9</p>";
+        let (text, encoding) = decode_text(body, "text/html; charset=iso-8859-1");
+
+        assert_eq!(text, "<meta charset=\"utf-8\"><p>caf\u{e9}</p>");
+        assert_eq!(encoding.name, "windows-1252");
+        assert_eq!(encoding.source, EncodingSource::Header);
+        assert!(!encoding.replaced);
+    }
+
+    #[test]
+    fn sniffs_html_and_xml_declarations() {
+        let html = b"<html><head><meta http-equiv=\"Content-Type\" content=\"text/html; charset=Shift_JIS\"></head>\x82\xa0</html>";
+        let (text, encoding) = decode_text(html, "text/html");
+        assert!(text.contains('\u{3042}'));
+        assert_eq!(encoding.name, "Shift_JIS");
+        assert_eq!(encoding.source, EncodingSource::Document);
+
+        let xml = b"<?xml version=\"1.0\" encoding='ISO-8859-1'?><a>\xe9</a>";
+        let (text, encoding) = decode_text(xml, "application/xml");
+        assert_eq!(
+            text,
+            "<?xml version=\"1.0\" encoding='ISO-8859-1'?><a>\u{e9}</a>"
+        );
+        assert_eq!(encoding.source, EncodingSource::Document);
+
+        let (_, encoding) = decode_text(b"plain \xe9", "text/plain");
+        assert_eq!(encoding.name, "UTF-8");
+        assert_eq!(encoding.source, EncodingSource::Default);
+        assert!(encoding.replaced);
+    }
+
+    #[test]
+    fn decodes_non_utf8_header_values_as_windows_1252() {
+        assert_eq!(decode_header_value("caf\u{e9}".as_bytes()), "caf\u{e9}");
+        assert_eq!(decode_header_value(b"caf\xe9"), "caf\u{e9}");
+        assert_eq!(decode_header_value(b"\x80"), "\u{20ac}");
+    }
+
+    #[test]
+    fn tells_text_and_binary_bodies_apart() {
+        assert_eq!(get_body_kind(b"{}", "application/json"), BodyKind::Text);
+        assert_eq!(
+            get_body_kind(b"{}", "application/problem+json"),
+            BodyKind::Text
+        );
+        assert_eq!(get_body_kind(b"<a/>", "image/svg+xml"), BodyKind::Text);
+        assert_eq!(
+            get_body_kind(b"hello", "application/octet-stream"),
+            BodyKind::Text
+        );
+        assert_eq!(
+            get_body_kind(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", "application/octet-stream"),
+            BodyKind::Binary
+        );
+        assert_eq!(
+            get_body_kind(&[0xFF, 0xFE, 0x00], "application/octet-stream"),
+            BodyKind::Binary
+        );
+        assert_eq!(get_body_kind(b"", "not a mime type"), BodyKind::Binary);
+    }
+}
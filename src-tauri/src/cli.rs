@@ -0,0 +1,971 @@
+use crate::bench::{self, BenchOptions};
+use crate::body::BodyKind;
+use crate::codegen::{self, Language};
+use crate::config::read_config;
+use crate::cookies::{CookieEntry, PandaCookieJar};
+use crate::curl;
+use crate::error::PandaError;
+use crate::har;
+use crate::history::{
+    format_timestamp, HistoryEntry, HistoryFilter, HistoryStore, PruneOptions, Redactor,
+};
+use crate::http_runner::{self, RunOptions};
+use crate::import;
+use crate::insomnia;
+use crate::openapi;
+use crate::postman;
+use crate::report::{parse_reporter, Reporter, RunReport};
+use crate::runner::{self, Iteration, RequestResult, RunEvent, RunSettings, SnapshotMode};
+use crate::snapshot::{SnapshotChange, SnapshotOutcome};
+use crate::utils::{get_collection_from_path, normalise_path, PandaCollection};
+use clap_derive::{Args, Subcommand};
+use normalize_path::NormalizePath;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CookieCommands {
+    /// Lists the cookies of the environment.
+    List,
+    /// Adds or replaces a cookie.
+    Set {
+        domain: String,
+        name: String,
+        value: String,
+        #[arg(long, default_value = "/")]
+        path: String,
+        /// Also send the cookie to subdomains of `domain`.
+        #[arg(long)]
+        include_subdomains: bool,
+        /// Unix timestamp in seconds, omit for a session cookie.
+        #[arg(long)]
+        expires: Option<i64>,
+        #[arg(long)]
+        secure: bool,
+        #[arg(long)]
+        http_only: bool,
+    },
+    /// Removes a single cookie.
+    Delete {
+        domain: String,
+        name: String,
+        #[arg(long, default_value = "/")]
+        path: String,
+    },
+    /// Removes every cookie of the environment.
+    Clear,
+}
+
+pub fn run_cookies(
+    project_root: &Path,
+    environment: Option<&str>,
+    action: CookieCommands,
+) -> Result<(), PandaError> {
+    let jar = PandaCookieJar::load(project_root, environment)
+        .map_err(|err| PandaError::Io(err.into()))?;
+
+    match action {
+        CookieCommands::List => {
+            for cookie in jar.list() {
+                let expires = cookie
+                    .expires
+                    .map(|e| e.to_string())
+                    .unwrap_or_else(|| "session".to_string());
+
+                println!(
+                    "{}\t{}\t{}={}\t{}",
+                    cookie.domain, cookie.path, cookie.name, cookie.value, expires
+                );
+            }
+
+            return Ok(());
+        }
+        CookieCommands::Set {
+            domain,
+            name,
+            value,
+            path,
+            include_subdomains,
+            expires,
+            secure,
+            http_only,
+        } => jar
+            .set(&CookieEntry {
+                name,
+                value,
+                domain,
+                path,
+                host_only: !include_subdomains,
+                expires,
+                secure,
+                http_only,
+            })
+            .map_err(|err| PandaError::Request(err.into()))?,
+        CookieCommands::Delete { domain, name, path } => {
+            if !jar.remove(&domain, &path, &name) {
+                return Err(PandaError::Request(
+                    format!("Cookie {:?} was not found.", name).into(),
+                ));
+            }
+        }
+        CookieCommands::Clear => jar.clear(),
+    };
+
+    jar.save().map_err(|err| PandaError::Io(err.into()))
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum HistoryCommands {
+    /// Lists recorded requests, newest first.
+    List {
+        #[command(flatten)]
+        filter: HistoryFilter,
+    },
+    /// Prints a recorded request and its response as JSON.
+    Show {
+        /// Id of the entry, a unique prefix is enough.
+        id: String,
+    },
+    /// Sends a recorded request again and records the new result.
+    Replay {
+        id: String,
+        /// Environment to use instead of the one the request was sent with.
+        #[arg(long)]
+        env: Option<String>,
+        /// Variable given as `NAME=VALUE`, can be repeated.
+        #[arg(long = "var", value_parser = parse_variable)]
+        variables: Vec<(String, String)>,
+    },
+    /// Removes old entries.
+    Prune {
+        #[command(flatten)]
+        options: PruneOptions,
+    },
+    /// Writes the matching entries as a HAR file, oldest first.
+    Export {
+        #[command(flatten)]
+        filter: HistoryFilter,
+        /// File to write, the HAR is printed when not given.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+fn parse_variable(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!(
+            "Invalid variable {:?}, expected NAME=VALUE.",
+            value
+        )),
+    }
+}
+
+pub async fn run_history(project_root: &Path, action: HistoryCommands) -> Result<(), PandaError> {
+    let history = HistoryStore::open(project_root)?;
+
+    match action {
+        HistoryCommands::List { filter } => {
+            for entry in history.list(&filter)? {
+                let status = entry
+                    .status
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| "error".to_string());
+
+                let elapsed = entry
+                    .elapsed_time
+                    .map(|e| format!("{:.1}ms", e))
+                    .unwrap_or_else(|| "-".to_string());
+
+                println!(
+                    "{}\t{}\t{}\t{} {}\t{}\t{}",
+                    &entry.id[..8.min(entry.id.len())],
+                    format_timestamp(entry.timestamp),
+                    status,
+                    entry.method.unwrap_or_else(|| "-".to_string()),
+                    entry.url.unwrap_or_else(|| "-".to_string()),
+                    elapsed,
+                    entry.file.unwrap_or_default()
+                );
+            }
+        }
+        HistoryCommands::Show { id } => {
+            let entry = history.get(&id)?;
+
+            let json = serde_json::to_string_pretty(&entry).map_err(|err| {
+                PandaError::Io(format!("Failed to serialize entry: {}", err).into())
+            })?;
+
+            println!("{}", json);
+        }
+        HistoryCommands::Replay { id, env, variables } => {
+            let entry = history.get(&id)?;
+            let environment = env.or_else(|| entry.environment.clone());
+            let variables: HashMap<String, String> = variables.into_iter().collect();
+
+            let options = RunOptions::for_project(project_root, environment.as_deref(), variables)?;
+
+            // Nothing cancels a request from the command line.
+            let (_cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+
+            let source = entry.replay_source(project_root)?;
+            let res = http_runner::run_toml_request(&source, &options, cancel_rx).await;
+
+            let replayed = HistoryEntry::new(
+                &source,
+                entry.file.as_deref(),
+                environment.as_deref(),
+                &options,
+                &res,
+            );
+            history.append(&replayed)?;
+
+            let res = res?;
+
+            println!(
+                "{} {} {} ({:.1}ms)",
+                res.status, res.request.method, res.final_url, res.elapsed_time
+            );
+
+            match (res.body_kind, res.text_response) {
+                (BodyKind::Text, Some(text)) => println!("{}", text),
+                _ => println!("<{} bytes of {}>", res.size, res.mime_type),
+            }
+        }
+        HistoryCommands::Prune { options } => {
+            let removed = history.prune(&options)?;
+
+            println!("Removed {} history entries.", removed);
+        }
+        HistoryCommands::Export { filter, output } => {
+            let mut entries = history.find(&filter)?;
+            entries.reverse();
+
+            let json =
+                serde_json::to_string_pretty(&har::from_history(&entries)).map_err(|err| {
+                    PandaError::Io(format!("Failed to serialize HAR: {}", err).into())
+                })?;
+
+            match &output {
+                Some(output) => {
+                    fs::write(output, json + "\n").map_err(|err| {
+                        PandaError::Io(format!("Failed to write {:?}: {}", output, err).into())
+                    })?;
+
+                    eprintln!("Exported {} entries to {:?}", entries.len(), output);
+                }
+                None => println!("{}", json),
+            }
+        }
+    };
+
+    Ok(())
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ImportCommands {
+    /// Converts a Postman v2.1 collection export to request files.
+    Postman {
+        /// The exported collection JSON.
+        file: PathBuf,
+        /// Folder the collection is written to instead of the project collection.
+        #[arg(long)]
+        into: Option<PathBuf>,
+    },
+    /// Converts an Insomnia v4 export, JSON or YAML, to request files and environments.
+    Insomnia {
+        /// The exported data file.
+        file: PathBuf,
+        /// Folder the workspace is written to instead of the project collection.
+        #[arg(long)]
+        into: Option<PathBuf>,
+    },
+    /// Converts the requests of a HAR file, eg. saved from the browser devtools.
+    Har {
+        /// The HAR file.
+        file: PathBuf,
+        /// Folder the requests are written to instead of the project collection.
+        #[arg(long)]
+        into: Option<PathBuf>,
+        /// Imports only requests to this host or its subdomains, can be repeated.
+        #[arg(long = "domain")]
+        domains: Vec<String>,
+        /// Imports a request sent several times with the same url and body once.
+        #[arg(long)]
+        dedupe: bool,
+    },
+    /// Converts a curl command to a request file.
+    Curl {
+        /// The command, read from the standard input when not given or `-`.
+        command: Option<String>,
+        /// Name of the request, defaults to the method and the path of the url.
+        #[arg(long)]
+        name: Option<String>,
+        /// Folder the request file is written to instead of the project collection.
+        #[arg(long)]
+        into: Option<PathBuf>,
+    },
+    /// Generates a request per operation of an OpenAPI 3 specification, JSON or YAML.
+    Openapi {
+        /// The specification, `$ref`s to other local files are followed.
+        file: PathBuf,
+        /// Folder the requests are written to instead of the project collection.
+        #[arg(long)]
+        into: Option<PathBuf>,
+    },
+}
+
+pub fn run_import(project_root: &Path, action: ImportCommands) -> Result<(), PandaError> {
+    let (imported, into) = match action {
+        ImportCommands::Postman { file, into } => {
+            (postman::convert(postman::read_collection(&file)?), into)
+        }
+        ImportCommands::Insomnia { file, into } => {
+            let name = file_stem(&file);
+
+            (
+                insomnia::convert(insomnia::read_export(&file)?, &name),
+                into,
+            )
+        }
+        ImportCommands::Har {
+            file,
+            into,
+            domains,
+            dedupe,
+        } => {
+            let options = har::HarOptions { domains, dedupe };
+
+            (
+                har::convert(har::read_har(&file)?, &file_stem(&file), &options),
+                into,
+            )
+        }
+        ImportCommands::Openapi { file, into } => {
+            (openapi::convert(openapi::read_spec(&file)?), into)
+        }
+        ImportCommands::Curl {
+            command,
+            name,
+            into,
+        } => return import_curl(project_root, command, name, into),
+    };
+
+    let summary = import::write_import(project_root, imported, into.as_deref())?;
+
+    println!(
+        "Imported {} requests in {} folders to {}",
+        summary.requests, summary.folders, summary.path
+    );
+
+    if summary.variables > 0 {
+        println!("Added {} variables to the project.", summary.variables);
+    }
+
+    for environment in &summary.environments {
+        println!("Added environment {}.", environment);
+    }
+
+    print_warnings(&summary.warnings);
+
+    Ok(())
+}
+
+fn import_curl(
+    project_root: &Path,
+    command: Option<String>,
+    name: Option<String>,
+    into: Option<PathBuf>,
+) -> Result<(), PandaError> {
+    let command = match command.filter(|c| c != "-") {
+        Some(command) => command,
+        None => std::io::read_to_string(std::io::stdin()).map_err(|err| {
+            PandaError::Io(format!("Failed to read the standard input: {}", err).into())
+        })?,
+    };
+
+    let mut curl = curl::parse(&command)?;
+
+    if let Some(name) = name {
+        curl.request.name = name;
+    }
+
+    let summary =
+        import::write_request(project_root, &curl.request, curl.warnings, into.as_deref())?;
+
+    println!("Created {}", summary.path);
+    print_warnings(&summary.warnings);
+
+    Ok(())
+}
+
+fn print_warnings(warnings: &[String]) {
+    if !warnings.is_empty() {
+        println!("\nWarnings:");
+
+        for warning in warnings {
+            println!("  - {}", warning);
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ExportCommands {
+    /// Writes the requests as a Postman v2.1 collection.
+    Postman {
+        /// Request file or folder, defaults to the project collection.
+        target: Option<PathBuf>,
+        /// File to write, the collection is printed when not given.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Environment whose variables are exported along with the project ones.
+        #[arg(long)]
+        env: Option<String>,
+    },
+}
+
+pub fn run_export(project_root: &Path, action: ExportCommands) -> Result<(), PandaError> {
+    let config = read_config(&project_root.to_string_lossy())?;
+
+    let ExportCommands::Postman {
+        target,
+        output,
+        env,
+    } = action;
+
+    let target = target.unwrap_or_else(|| project_root.join(&config.collection));
+    let target = target.normalize();
+
+    let (name, items) = match target.is_dir() {
+        true => {
+            let name = match target == project_root.join(&config.collection).normalize() {
+                true => config.name.clone(),
+                false => file_name(&target),
+            };
+
+            let items = get_collection_from_path(
+                normalise_path(target.to_string_lossy().to_string()),
+                vec![],
+            )?;
+
+            (name, items)
+        }
+        false => {
+            let content = fs::read_to_string(&target).map_err(|err| {
+                PandaError::Io(format!("Failed to read {:?}: {}", target, err).into())
+            })?;
+
+            let item = PandaCollection {
+                id: String::new(),
+                name: file_name(&target),
+                path: target.to_string_lossy().to_string(),
+                content: Some(content),
+                children: None,
+                is_selectable: true,
+                item_type: "file".to_string(),
+            };
+
+            (config.name.clone(), vec![item])
+        }
+    };
+
+    let variables = config.get_variables(env.as_deref()).into_iter().collect();
+    let (collection, warnings) = postman::export(&name, &items, &variables);
+
+    let json = serde_json::to_string_pretty(&collection)
+        .map_err(|err| PandaError::Io(format!("Failed to serialize collection: {}", err).into()))?;
+
+    match &output {
+        Some(output) => fs::write(output, json + "\n").map_err(|err| {
+            PandaError::Io(format!("Failed to write {:?}: {}", output, err).into())
+        })?,
+        None => println!("{}", json),
+    }
+
+    // Warnings go to stderr so the printed collection stays valid JSON.
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    if let Some(output) = &output {
+        eprintln!("Exported {:?} to {:?}", name, output);
+    }
+
+    Ok(())
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct RunArgs {
+    /// Request file or folder, defaults to the project collection.
+    pub target: Option<PathBuf>,
+    #[arg(long)]
+    pub env: Option<String>,
+    /// Variable given as `NAME=VALUE`, can be repeated.
+    #[arg(long = "var", value_parser = parse_variable)]
+    pub variables: Vec<(String, String)>,
+    /// Stops at the first failed request whatever the folders configure.
+    #[arg(long, conflicts_with = "continue_on_failure")]
+    pub bail: bool,
+    /// Runs every request even in folders with `stop_on_failure`.
+    #[arg(long)]
+    pub continue_on_failure: bool,
+    /// CSV or JSON data file, the run is repeated once per row with its columns as variables.
+    #[arg(long)]
+    pub data: Option<PathBuf>,
+    /// `junit`, `json`, `tap`, `html` or `har`, followed by `=PATH` to write to a file eg.
+    /// `junit=reports/junit.xml`. Can be repeated, one reporter may use the standard output.
+    #[arg(long = "reporter", value_parser = parse_reporter)]
+    pub reporters: Vec<Reporter>,
+    /// Neither sends nor stores the cookies of the environment.
+    #[arg(long)]
+    pub no_cookies: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct TestArgs {
+    #[command(flatten)]
+    pub run: RunArgs,
+    /// Compares responses with the snapshots stored next to each request, missing ones
+    /// are written.
+    #[arg(long)]
+    pub snapshot: bool,
+    /// Overwrites the snapshots that changed, implies `--snapshot`.
+    #[arg(long)]
+    pub update_snapshots: bool,
+}
+
+pub async fn run_requests(project_root: &Path, args: RunArgs) -> Result<(), PandaError> {
+    execute_run(project_root, args, None).await
+}
+
+/// Same as `panda run`, failing as well when a snapshot changed.
+pub async fn run_test(project_root: &Path, args: TestArgs) -> Result<(), PandaError> {
+    let snapshot = match (args.snapshot, args.update_snapshots) {
+        (_, true) => Some(SnapshotMode::Update),
+        (true, false) => Some(SnapshotMode::Check),
+        (false, false) => None,
+    };
+
+    execute_run(project_root, args.run, snapshot).await
+}
+
+async fn execute_run(
+    project_root: &Path,
+    args: RunArgs,
+    snapshot: Option<SnapshotMode>,
+) -> Result<(), PandaError> {
+    let config = read_config(&project_root.to_string_lossy())?;
+
+    let target = args
+        .target
+        .unwrap_or_else(|| project_root.join(&config.collection));
+
+    // Progress lines would corrupt a report written to the standard output.
+    let quiet = match args.reporters.iter().filter(|r| r.path.is_none()).count() {
+        0 => false,
+        1 => true,
+        _ => {
+            return Err(PandaError::Config(
+                "Only one reporter can write to the standard output, give the others a path eg. junit=junit.xml."
+                    .into(),
+            ))
+        }
+    };
+
+    let started_at = OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .unwrap_or_default();
+
+    let variables: HashMap<String, String> = args.variables.into_iter().collect();
+    let mut options = RunOptions::for_project(project_root, args.env.as_deref(), variables)?;
+    if args.no_cookies {
+        options.cookie_jar = None;
+    }
+
+    let settings = RunSettings {
+        stop_on_failure: match (args.bail, args.continue_on_failure) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        },
+        snapshot,
+        data: args.data,
+    };
+
+    // Nothing cancels a run from the command line.
+    let (_cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+
+    let mut iteration = None;
+    let mut results = vec![];
+
+    // Reports end up in CI logs and artifacts.
+    let redactor = Redactor::new(&options.variables);
+
+    let summary = runner::run_collection(
+        project_root,
+        &target,
+        options,
+        &settings,
+        cancel_rx,
+        |event| {
+            if let RunEvent::RequestFinished { result, .. } = &event {
+                let error = result.error.clone().map(|mut err| {
+                    let details = err.details_mut();
+                    details.message = redactor.text(&details.message);
+
+                    err
+                });
+
+                results.push(RequestResult {
+                    captured: redactor.variables(&result.captured),
+                    error,
+                    ..result.as_ref().clone()
+                });
+            }
+
+            if !quiet {
+                print_event(event, &mut iteration);
+            }
+        },
+    )
+    .await?;
+
+    let report = RunReport {
+        target: target
+            .strip_prefix(project_root)
+            .unwrap_or(&target)
+            .to_string_lossy()
+            .to_string(),
+        environment: args.env,
+        started_at,
+        summary: summary.clone(),
+        results,
+    };
+
+    for reporter in &args.reporters {
+        reporter.write(&report)?;
+    }
+
+    if summary.failed > 0 {
+        let hint = match snapshot {
+            Some(SnapshotMode::Check) => ", run with --update-snapshots to accept the changes",
+            _ => "",
+        };
+
+        return Err(PandaError::Assertion(
+            format!(
+                "{} of {} requests failed{}.",
+                summary.failed, summary.total, hint
+            )
+            .into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Prints results as they come, `iteration` is the data row of the previous request.
+fn print_event(event: RunEvent, iteration: &mut Option<Iteration>) {
+    let result = match event {
+        RunEvent::RequestStarted {
+            iteration: Some(current),
+            ..
+        } => {
+            let changed = iteration
+                .as_ref()
+                .is_none_or(|i| i.index != current.index || i.data_file != current.data_file);
+
+            if changed {
+                println!(
+                    "Iteration {}/{} of {}",
+                    current.index, current.total, current.data_file
+                );
+            }
+
+            *iteration = Some(current);
+            return;
+        }
+        RunEvent::RequestFinished { result, .. } => result,
+        RunEvent::Finished { summary } => {
+            for i in summary.iterations.iter().filter(|i| i.failed > 0) {
+                println!(
+                    "Iteration {} of {}: {} failed",
+                    i.index, i.data_file, i.failed
+                );
+            }
+
+            println!(
+                "{} passed, {} failed, {} skipped in {:.1}ms",
+                summary.passed, summary.failed, summary.skipped, summary.elapsed_time
+            );
+            return;
+        }
+        _ => return,
+    };
+
+    if let Some(err) = &result.error {
+        println!("FAIL  {}\n      {}", result.file, err);
+        return;
+    }
+
+    let summary = format!(
+        "{} ({:.1}ms)",
+        result.status.unwrap_or_default(),
+        result.elapsed_time.unwrap_or_default()
+    );
+
+    match &result.snapshot {
+        None | Some(SnapshotOutcome::Matched) => println!("PASS  {}  {}", result.file, summary),
+        Some(SnapshotOutcome::Created) => {
+            println!("NEW   {}  {}, snapshot written", result.file, summary)
+        }
+        Some(SnapshotOutcome::Updated(changes)) => {
+            println!("UPD   {}  {}, snapshot updated", result.file, summary);
+            print_changes(changes);
+        }
+        Some(SnapshotOutcome::Changed(changes)) => {
+            println!("FAIL  {}  {}, snapshot changed", result.file, summary);
+            print_changes(changes);
+        }
+    }
+
+    if !result.captured.is_empty() {
+        let mut names: Vec<&String> = result.captured.keys().collect();
+        names.sort();
+
+        // Values are left out, captures are often tokens.
+        println!(
+            "      captured {}",
+            names
+                .iter()
+                .map(|n| n.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    for warning in &result.warnings {
+        println!("      warning: {}", warning);
+    }
+}
+
+fn print_changes(changes: &[SnapshotChange]) {
+    for change in changes {
+        println!("      {}", change);
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BenchArgs {
+    /// Request files, sent in this order by each worker.
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+    #[arg(long)]
+    pub env: Option<String>,
+    /// Variable given as `NAME=VALUE`, can be repeated.
+    #[arg(long = "var", value_parser = parse_variable)]
+    pub variables: Vec<(String, String)>,
+    /// Number of requests in flight at the same time.
+    #[arg(short, long, default_value_t = 10)]
+    pub concurrency: usize,
+    /// Times the files are sent, defaults to 100 unless a duration is given.
+    #[arg(short = 'n', long)]
+    pub requests: Option<usize>,
+    /// How long to send requests for eg. `500ms`, `30s` or `2m`.
+    #[arg(short, long, value_parser = parse_bench_duration)]
+    pub duration: Option<Duration>,
+    /// Target requests per second across all workers.
+    #[arg(long, value_parser = parse_rps)]
+    pub rps: Option<f64>,
+    /// Prints the report as JSON.
+    #[arg(long)]
+    pub json: bool,
+    /// Neither sends nor stores the cookies of the environment.
+    #[arg(long)]
+    pub no_cookies: bool,
+}
+
+fn parse_bench_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+
+    let amount: f64 = value[..split]
+        .parse()
+        .map_err(|_| format!("Invalid duration {:?}, expected eg. 30s.", value))?;
+
+    let seconds = match &value[split..] {
+        "ms" => amount / 1000.0,
+        "s" | "" => amount,
+        "m" => amount * 60.0,
+        "h" => amount * 3600.0,
+        unit => {
+            return Err(format!(
+                "Unknown duration unit {:?}, use ms, s, m or h.",
+                unit
+            ))
+        }
+    };
+
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|_| format!("Invalid duration {:?}, expected eg. 30s.", value))
+}
+
+fn parse_rps(value: &str) -> Result<f64, String> {
+    let invalid = || {
+        format!(
+            "Invalid rate {:?}, expected a positive number of requests per second.",
+            value
+        )
+    };
+
+    let rate: f64 = value.trim().parse().map_err(|_| invalid())?;
+
+    // The pacer spaces requests `1 / rate` seconds apart.
+    match rate > 0.0 && Duration::try_from_secs_f64(1.0 / rate).is_ok() {
+        true => Ok(rate),
+        false => Err(invalid()),
+    }
+}
+
+/// Sends the files repeatedly and prints the throughput, errors and latencies. Ctrl-C
+/// stops early and still prints the report.
+pub async fn run_bench(project_root: &Path, args: BenchArgs) -> Result<(), PandaError> {
+    let variables: HashMap<String, String> = args.variables.into_iter().collect();
+    let mut run_options = RunOptions::for_project(project_root, args.env.as_deref(), variables)?;
+    if args.no_cookies {
+        run_options.cookie_jar = None;
+    }
+
+    let options = BenchOptions {
+        concurrency: args.concurrency,
+        iterations: match (args.requests, args.duration) {
+            (None, None) => Some(100),
+            (requests, _) => requests,
+        },
+        duration: args.duration,
+        rate: args.rps,
+    };
+
+    let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            let _ = cancel_tx.send(true);
+        }
+    });
+
+    let report =
+        bench::run_bench(project_root, &args.files, &run_options, &options, cancel_rx).await?;
+
+    match args.json {
+        true => println!(
+            "{}",
+            serde_json::to_string_pretty(&report)
+                .map_err(|err| PandaError::Io(err.to_string().into()))?
+        ),
+        false => print!("{}", report),
+    }
+
+    Ok(())
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CodegenArgs {
+    /// Request file to convert.
+    pub file: PathBuf,
+    #[arg(short, long, value_enum, default_value = "curl")]
+    pub lang: Language,
+    /// Substitutes the variables, otherwise the code reads them from environment variables.
+    #[arg(long)]
+    pub resolve: bool,
+    #[arg(long, requires = "resolve")]
+    pub env: Option<String>,
+    /// Variable given as `NAME=VALUE`, can be repeated.
+    #[arg(long = "var", value_parser = parse_variable, requires = "resolve")]
+    pub variables: Vec<(String, String)>,
+}
+
+pub fn run_codegen(project_root: &Path, args: CodegenArgs) -> Result<(), PandaError> {
+    let source = fs::read_to_string(&args.file)
+        .map_err(|err| PandaError::Io(format!("Failed to read {:?}: {}", args.file, err).into()))?;
+
+    let variables = match args.resolve {
+        true => {
+            let config = read_config(&project_root.to_string_lossy())?;
+            let mut variables = config.get_variables(args.env.as_deref());
+            variables.extend(args.variables);
+
+            Some(variables)
+        }
+        false => None,
+    };
+
+    print!(
+        "{}",
+        codegen::generate(&source, args.lang, variables.as_ref())?
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bench_durations() {
+        assert_eq!(
+            parse_bench_duration("500ms"),
+            Ok(Duration::from_millis(500))
+        );
+        assert_eq!(parse_bench_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(
+            parse_bench_duration(" 1.5s "),
+            Ok(Duration::from_millis(1500))
+        );
+        assert_eq!(parse_bench_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_bench_duration("1h"), Ok(Duration::from_secs(3600)));
+
+        assert!(parse_bench_duration("5d")
+            .unwrap_err()
+            .contains("Unknown duration unit"));
+        assert!(parse_bench_duration("s")
+            .unwrap_err()
+            .contains("Invalid duration"));
+        assert!(parse_bench_duration(&format!("{}h", "9".repeat(400)))
+            .unwrap_err()
+            .contains("Invalid duration"));
+    }
+
+    #[test]
+    fn parses_request_rates() {
+        assert_eq!(parse_rps("20"), Ok(20.0));
+        assert_eq!(parse_rps("0.5"), Ok(0.5));
+
+        for rate in ["0", "-1", "NaN", "1e-300", "fast"] {
+            assert!(
+                parse_rps(rate).unwrap_err().contains("Invalid rate"),
+                "{}",
+                rate
+            );
+        }
+    }
+}
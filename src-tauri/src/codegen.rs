@@ -0,0 +1,1275 @@
+use crate::error::PandaError;
+use crate::http_runner::PandaTomlRequest;
+use crate::import::{FormValue, ImportedBody};
+use crate::utils::{self, VARIABLE_REGEX};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    Curl,
+    Httpie,
+    /// Python with `requests`.
+    Python,
+    /// JavaScript `fetch`, the files of multipart forms are read with Node.js.
+    Fetch,
+    /// Node.js with `axios`.
+    Axios,
+    /// Go with `net/http`.
+    Go,
+    /// Rust with `reqwest` and `tokio`.
+    Rust,
+}
+
+/// Generates code sending the request of a request file.
+///
+/// With `variables` they are substituted like when the request is run, otherwise every
+/// `_.name` is read from the environment variable `name` by the generated code.
+pub fn generate(
+    source: &str,
+    language: Language,
+    variables: Option<&HashMap<String, String>>,
+) -> Result<String, PandaError> {
+    let request = Request::parse(source, variables)?;
+
+    Ok(match language {
+        Language::Curl => curl(&request),
+        Language::Httpie => httpie(&request),
+        Language::Python => python(&request),
+        Language::Fetch => fetch(&request),
+        Language::Axios => axios(&request),
+        Language::Go => go(&request),
+        Language::Rust => rust(&request),
+    })
+}
+
+/// A string of the request, with the variables that were not substituted.
+#[derive(Clone, Debug)]
+struct Text(Vec<Part>);
+
+#[derive(Clone, Debug)]
+enum Part {
+    Literal(String),
+    Variable(String),
+}
+
+impl Text {
+    fn parse(text: &str) -> Text {
+        let mut parts = vec![];
+        let mut last = 0;
+
+        for caps in VARIABLE_REGEX
+            .captures_iter(text)
+            .filter_map(|caps| caps.ok())
+        {
+            let (whole, name) = (caps.get(0).unwrap(), caps.get(1).unwrap());
+
+            if whole.start() > last {
+                parts.push(Part::Literal(text[last..whole.start()].to_string()));
+            }
+
+            parts.push(Part::Variable(name.as_str().to_string()));
+            last = whole.end();
+        }
+
+        if last < text.len() {
+            parts.push(Part::Literal(text[last..].to_string()));
+        }
+
+        Text(parts)
+    }
+
+    fn literal(text: impl Into<String>) -> Text {
+        Text(vec![Part::Literal(text.into())])
+    }
+
+    /// The text when it has no variables.
+    fn as_literal(&self) -> Option<String> {
+        let mut literal = String::new();
+
+        for part in &self.0 {
+            match part {
+                Part::Literal(text) => literal.push_str(text),
+                Part::Variable(_) => return None,
+            }
+        }
+
+        Some(literal)
+    }
+
+    fn is_literal(&self) -> bool {
+        self.as_literal().is_some()
+    }
+
+    fn join(mut self, other: &Text) -> Text {
+        self.0.extend(other.0.iter().cloned());
+        self
+    }
+
+    /// The text after a literal prefix.
+    fn strip_prefix(&self, prefix: &str) -> Option<Text> {
+        let mut parts = self.0.clone();
+
+        match parts.first_mut() {
+            Some(Part::Literal(text)) if text.starts_with(prefix) => {
+                *text = text[prefix.len()..].to_string();
+            }
+            _ => return None,
+        }
+
+        if matches!(parts.first(), Some(Part::Literal(text)) if text.is_empty()) {
+            parts.remove(0);
+        }
+
+        Some(Text(parts))
+    }
+
+    /// Literal parts percent encoded as a query component, variables are kept.
+    fn encoded(&self) -> Text {
+        Text(
+            self.0
+                .iter()
+                .map(|part| match part {
+                    Part::Literal(text) => Part::Literal(utils::encode_component(text)),
+                    variable => variable.clone(),
+                })
+                .collect(),
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Body {
+    Raw(Text),
+    UrlEncoded(Vec<(Text, Text)>),
+    Multipart(Vec<(Text, Field)>),
+}
+
+#[derive(Clone, Debug)]
+enum Field {
+    Value(Text),
+    /// Path of the file, relative to the project root.
+    File(String),
+}
+
+#[derive(Clone, Debug)]
+enum Auth {
+    Basic { username: String, password: String },
+    Bearer(Text),
+}
+
+#[derive(Clone, Debug)]
+struct Request {
+    /// Uppercase eg. `POST`.
+    method: String,
+    url: Text,
+    query: Vec<(Text, Text)>,
+    headers: Vec<(String, Text)>,
+    body: Option<Body>,
+}
+
+impl Request {
+    fn parse(
+        source: &str,
+        variables: Option<&HashMap<String, String>>,
+    ) -> Result<Self, PandaError> {
+        let toml: PandaTomlRequest = match variables {
+            Some(variables) => utils::parse_toml(source, variables, None)?,
+            None => {
+                toml::from_str(source).map_err(|err| PandaError::from_toml(err, source, None))?
+            }
+        };
+
+        let (method, params) = toml
+            .params()
+            .ok_or_else(|| PandaError::Request("Invalid or unsupported request method".into()))?;
+
+        // Path variables fall back to `[method.params]` like when the request is run, they
+        // are values of the request rather than of the environment.
+        let url =
+            utils::replace_variables(params.url(), &params.path_params().into_iter().collect());
+
+        let pairs = |pairs: Vec<(String, String)>| {
+            pairs
+                .into_iter()
+                .map(|(key, value)| (Text::parse(&key), Text::parse(&value)))
+                .collect::<Vec<_>>()
+        };
+
+        let mut headers: Vec<(String, Text)> = params
+            .headers()
+            .iter()
+            .map(|h| (h.name.clone(), Text::parse(&h.value)))
+            .collect();
+
+        let has_header = |headers: &[(String, Text)], name: &str| {
+            headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name))
+        };
+
+        let body = match params.body() {
+            Some(ImportedBody::FormMultipart(fields)) => {
+                // The boundary is set by the client.
+                headers.retain(|(name, _)| !name.eq_ignore_ascii_case("content-type"));
+
+                let fields = fields.into_iter().map(|(name, value)| {
+                    let field = match value {
+                        FormValue::File(file) => Field::File(file),
+                        FormValue::Text(value) => Field::Value(Text::parse(&value)),
+                    };
+
+                    (Text::parse(&name), field)
+                });
+
+                Some(Body::Multipart(fields.collect()))
+            }
+            Some(ImportedBody::FormUrlEncoded(fields)) => Some(Body::UrlEncoded(pairs(fields))),
+            Some(ImportedBody::Json(content)) => {
+                if !has_header(&headers, "content-type") {
+                    headers.push(("Content-Type".into(), Text::literal("application/json")));
+                }
+
+                Some(Body::Raw(Text::parse(&content)))
+            }
+            Some(ImportedBody::Xml(content)) => {
+                if !has_header(&headers, "content-type") {
+                    headers.push(("Content-Type".into(), Text::literal("application/xml")));
+                }
+
+                Some(Body::Raw(Text::parse(&content)))
+            }
+            Some(ImportedBody::Text(content)) => Some(Body::Raw(Text::parse(&content))),
+            None => None,
+        };
+
+        Ok(Request {
+            method: method.to_string(),
+            url: Text::parse(&url),
+            query: pairs(params.query()),
+            headers,
+            body,
+        })
+    }
+
+    /// Credentials of the Authorization header, basic ones only when they have no
+    /// variables as they are encoded.
+    fn auth(&self) -> Option<Auth> {
+        let (_, value) = self
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("authorization"))?;
+
+        if let Some(token) = value.strip_prefix("Bearer ") {
+            return Some(Auth::Bearer(token));
+        }
+
+        let encoded = value.strip_prefix("Basic ")?.as_literal()?;
+        let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+        let (username, password) = decoded.split_once(':')?;
+
+        Some(Auth::Basic {
+            username: username.to_string(),
+            password: password.to_string(),
+        })
+    }
+
+    /// Headers without the Authorization one when `auth` is written with the client.
+    fn headers_without(&self, auth: &Option<Auth>) -> Vec<(String, Text)> {
+        self.headers
+            .iter()
+            .filter(|(name, _)| auth.is_none() || !name.eq_ignore_ascii_case("authorization"))
+            .cloned()
+            .collect()
+    }
+
+    /// The url with the query table appended, percent encoded.
+    fn full_url(&self) -> Text {
+        let mut url = self.url.clone();
+
+        for (i, (key, value)) in self.query.iter().enumerate() {
+            let has_query = i > 0
+                || url
+                    .0
+                    .iter()
+                    .any(|part| matches!(part, Part::Literal(t) if t.contains('?')));
+            let separator = if has_query { "&" } else { "?" };
+
+            url = url
+                .join(&Text::literal(separator))
+                .join(&key.encoded())
+                .join(&Text::literal("="))
+                .join(&value.encoded());
+        }
+
+        url
+    }
+
+    /// Names of the variables read from the environment.
+    fn variables(&self) -> BTreeSet<String> {
+        let mut texts: Vec<&Text> = vec![&self.url];
+
+        for (key, value) in &self.query {
+            texts.extend([key, value]);
+        }
+
+        texts.extend(self.headers.iter().map(|(_, value)| value));
+
+        match &self.body {
+            Some(Body::Raw(text)) => texts.push(text),
+            Some(Body::UrlEncoded(fields)) => {
+                for (key, value) in fields {
+                    texts.extend([key, value]);
+                }
+            }
+            Some(Body::Multipart(fields)) => {
+                for (key, field) in fields {
+                    texts.push(key);
+
+                    if let Field::Value(value) = field {
+                        texts.push(value);
+                    }
+                }
+            }
+            None => {}
+        }
+
+        texts
+            .into_iter()
+            .flat_map(|text| &text.0)
+            .filter_map(|part| match part {
+                Part::Variable(name) => Some(name.clone()),
+                Part::Literal(_) => None,
+            })
+            .collect()
+    }
+
+    fn has_files(&self) -> bool {
+        matches!(&self.body, Some(Body::Multipart(fields)) if fields.iter().any(|(_, f)| matches!(f, Field::File(_))))
+    }
+}
+
+/// `mars.png` for `./images/mars.png`.
+fn file_name(path: &str) -> String {
+    path.rsplit(['/', '\\']).next().unwrap_or(path).to_string()
+}
+
+/// Whether form fields can be written as an object, repeated names can't.
+fn has_unique_keys(pairs: &[(Text, Text)]) -> bool {
+    let keys: Vec<Option<String>> = pairs.iter().map(|(key, _)| key.as_literal()).collect();
+
+    keys.iter().all(Option::is_some) && keys.iter().collect::<BTreeSet<_>>().len() == keys.len()
+}
+
+/// A shell word, single quoted or double quoted when it reads variables.
+fn sh(text: &Text) -> String {
+    match text.as_literal() {
+        Some(text)
+            if !text.is_empty()
+                && text
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c)) =>
+        {
+            text
+        }
+        Some(text) => format!("'{}'", text.replace('\'', r"'\''")),
+        None => {
+            let mut word = String::from("\"");
+
+            for part in &text.0 {
+                match part {
+                    Part::Literal(text) => {
+                        for c in text.chars() {
+                            if matches!(c, '\\' | '"' | '$' | '`') {
+                                word.push('\\');
+                            }
+
+                            word.push(c);
+                        }
+                    }
+                    Part::Variable(name) => {
+                        let _ = write!(word, "${{{}}}", name);
+                    }
+                }
+            }
+
+            word.push('"');
+            word
+        }
+    }
+}
+
+/// `Name: value`, or `Name;` for an empty value as `Name:` removes the header in curl.
+fn header_line(name: &str, value: &Text) -> Text {
+    match value.as_literal().as_deref() {
+        Some("") => Text::literal(format!("{};", name)),
+        _ => Text::literal(format!("{}: ", name)).join(value),
+    }
+}
+
+fn curl(request: &Request) -> String {
+    let mut args = vec![];
+
+    match (request.method.as_str(), &request.body) {
+        ("GET", None) | ("POST", Some(_)) => {}
+        ("HEAD", _) => args.push("--head".to_string()),
+        (method, _) => args.push(format!("-X {}", method)),
+    }
+
+    args.push(sh(&request.full_url()));
+
+    let auth = request
+        .auth()
+        .filter(|auth| matches!(auth, Auth::Basic { .. }));
+
+    for (name, value) in request.headers_without(&auth) {
+        args.push(format!("-H {}", sh(&header_line(&name, &value))));
+    }
+
+    if let Some(Auth::Basic { username, password }) = &auth {
+        args.push(format!(
+            "-u {}",
+            sh(&Text::literal(format!("{}:{}", username, password)))
+        ));
+    }
+
+    match &request.body {
+        Some(Body::Raw(text)) => args.push(format!("--data-raw {}", sh(text))),
+        Some(Body::UrlEncoded(fields)) => {
+            for (key, value) in fields {
+                let field = key.clone().join(&Text::literal("=")).join(value);
+                args.push(format!("--data-urlencode {}", sh(&field)));
+            }
+        }
+        Some(Body::Multipart(fields)) => {
+            for (key, field) in fields {
+                let arg = match field {
+                    Field::Value(value) => {
+                        let field = key.clone().join(&Text::literal("=")).join(value);
+                        format!("--form-string {}", sh(&field))
+                    }
+                    Field::File(path) => {
+                        let field = key.clone().join(&Text::literal(format!("=@{}", path)));
+                        format!("-F {}", sh(&field))
+                    }
+                };
+
+                args.push(arg);
+            }
+        }
+        None => {}
+    }
+
+    format!("curl {}\n", args.join(" \\\n  "))
+}
+
+fn httpie(request: &Request) -> String {
+    let mut args = vec![];
+
+    match &request.body {
+        Some(Body::UrlEncoded(_)) => args.push("--form".to_string()),
+        Some(Body::Multipart(_)) => args.push("--multipart".to_string()),
+        _ => {}
+    }
+
+    let auth = request.auth();
+
+    match &auth {
+        Some(Auth::Basic { username, password }) => args.push(format!(
+            "-a {}",
+            sh(&Text::literal(format!("{}:{}", username, password)))
+        )),
+        Some(Auth::Bearer(token)) => args.push(format!("-A bearer -a {}", sh(token))),
+        None => {}
+    }
+
+    args.push(format!("{} {}", request.method, sh(&request.url)));
+
+    for (name, value) in request.headers_without(&auth) {
+        let item = match value.as_literal().as_deref() {
+            Some("") => Text::literal(format!("{};", name)),
+            _ => Text::literal(format!("{}:", name)).join(&value),
+        };
+
+        args.push(sh(&item));
+    }
+
+    for (key, value) in &request.query {
+        args.push(sh(&key.clone().join(&Text::literal("==")).join(value)));
+    }
+
+    match &request.body {
+        Some(Body::Raw(text)) => args.push(format!("--raw {}", sh(text))),
+        Some(Body::UrlEncoded(fields)) => {
+            for (key, value) in fields {
+                args.push(sh(&key.clone().join(&Text::literal("=")).join(value)));
+            }
+        }
+        Some(Body::Multipart(fields)) => {
+            for (key, field) in fields {
+                let item = match field {
+                    Field::Value(value) => key.clone().join(&Text::literal("=")).join(value),
+                    Field::File(path) => key.clone().join(&Text::literal(format!("@{}", path))),
+                };
+
+                args.push(sh(&item));
+            }
+        }
+        None => {}
+    }
+
+    format!("http {}\n", args.join(" \\\n  "))
+}
+
+/// A Python string, an f-string reading the environment when it has variables.
+fn py(text: &Text) -> String {
+    if let [Part::Variable(name)] = text.0.as_slice() {
+        return format!("os.environ[{:?}]", name);
+    }
+
+    let is_f = !text.is_literal();
+    let literal: String = text
+        .0
+        .iter()
+        .filter_map(|part| match part {
+            Part::Literal(text) => Some(text.as_str()),
+            Part::Variable(_) => None,
+        })
+        .collect();
+
+    let triple = literal.contains('\n')
+        && !literal.contains("\"\"\"")
+        && !literal.contains('\\')
+        && !matches!(text.0.last(), Some(Part::Literal(t)) if t.ends_with('"'));
+
+    let mut string = String::new();
+
+    for part in &text.0 {
+        match part {
+            Part::Literal(text) => {
+                let text = match triple {
+                    true => text.clone(),
+                    false => escape(text, false),
+                };
+
+                match is_f {
+                    true => string.push_str(&text.replace('{', "{{").replace('}', "}}")),
+                    false => string.push_str(&text),
+                }
+            }
+            Part::Variable(name) => {
+                let _ = write!(string, "{{os.environ['{}']}}", name);
+            }
+        }
+    }
+
+    let prefix = if is_f { "f" } else { "" };
+    let quote = if triple { "\"\"\"" } else { "\"" };
+
+    format!("{}{}{}{}", prefix, quote, string, quote)
+}
+
+/// Escapes a string for double quotes, `\u{..}` for Rust and `\x..` otherwise.
+fn escape(text: &str, rust: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() && rust => {
+                let _ = write!(escaped, "\\u{{{:x}}}", c as u32);
+            }
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\x{:02x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// A Python dict, or a list of tuples when names are repeated.
+fn py_pairs(pairs: &[(Text, Text)]) -> String {
+    let mut dict = String::new();
+
+    match has_unique_keys(pairs) {
+        true => {
+            dict.push_str("{\n");
+
+            for (key, value) in pairs {
+                let _ = writeln!(dict, "    {}: {},", py(key), py(value));
+            }
+
+            dict.push('}');
+        }
+        false => {
+            dict.push_str("[\n");
+
+            for (key, value) in pairs {
+                let _ = writeln!(dict, "    ({}, {}),", py(key), py(value));
+            }
+
+            dict.push(']');
+        }
+    }
+
+    dict
+}
+
+fn python(request: &Request) -> String {
+    let mut code = String::new();
+    let mut arguments = vec![format!("{:?}", request.method), "url".to_string()];
+
+    if !request.variables().is_empty() {
+        code.push_str("import os\n\n");
+    }
+
+    code.push_str("import requests\n\n");
+    let _ = writeln!(code, "url = {}\n", py(&request.url));
+
+    if !request.query.is_empty() {
+        let _ = writeln!(code, "params = {}\n", py_pairs(&request.query));
+        arguments.push("params=params".into());
+    }
+
+    let auth = request
+        .auth()
+        .filter(|auth| matches!(auth, Auth::Basic { .. }));
+    let headers = request.headers_without(&auth);
+
+    if !headers.is_empty() {
+        let headers: Vec<(Text, Text)> = headers
+            .into_iter()
+            .map(|(name, value)| (Text::literal(name), value))
+            .collect();
+
+        let _ = writeln!(code, "headers = {}\n", py_pairs(&headers));
+        arguments.push("headers=headers".into());
+    }
+
+    match &request.body {
+        Some(Body::Raw(text)) => {
+            let _ = writeln!(code, "data = {}\n", py(text));
+            arguments.push("data=data".into());
+        }
+        Some(Body::UrlEncoded(fields)) => {
+            let _ = writeln!(code, "data = {}\n", py_pairs(fields));
+            arguments.push("data=data".into());
+        }
+        Some(Body::Multipart(fields)) => {
+            // `(None, value)` sends a field without a file name.
+            code.push_str("files = [\n");
+
+            for (key, field) in fields {
+                let value = match field {
+                    Field::Value(value) => format!("(None, {})", py(value)),
+                    Field::File(path) => format!("open({}, \"rb\")", py(&Text::literal(path))),
+                };
+
+                let _ = writeln!(code, "    ({}, {}),", py(key), value);
+            }
+
+            code.push_str("]\n\n");
+            arguments.push("files=files".into());
+        }
+        None => {}
+    }
+
+    if let Some(Auth::Basic { username, password }) = &auth {
+        arguments.push(format!(
+            "auth=({}, {})",
+            py(&Text::literal(username)),
+            py(&Text::literal(password))
+        ));
+    }
+
+    let _ = writeln!(
+        code,
+        "response = requests.request(\n    {},\n)\n",
+        arguments.join(",\n    ")
+    );
+    code.push_str("print(response.status_code)\nprint(response.text)\n");
+
+    code
+}
+
+/// A JavaScript string, a template literal for variables and multiple lines.
+fn js(text: &Text) -> String {
+    if let Some(literal) = text.as_literal().filter(|t| !t.contains('\n')) {
+        return serde_json::to_string(&literal).unwrap_or_default();
+    }
+
+    if let [Part::Variable(name)] = text.0.as_slice() {
+        return format!("process.env.{}", name);
+    }
+
+    let mut string = String::from("`");
+
+    for part in &text.0 {
+        match part {
+            Part::Literal(text) => string.push_str(
+                &text
+                    .replace('\\', "\\\\")
+                    .replace('`', "\\`")
+                    .replace("${", "\\${"),
+            ),
+            Part::Variable(name) => {
+                let _ = write!(string, "${{process.env.{}}}", name);
+            }
+        }
+    }
+
+    string.push('`');
+    string
+}
+
+/// A JavaScript object, indented by `indent` spaces.
+fn js_object(pairs: &[(Text, Text)], indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    let mut object = String::from("{\n");
+
+    for (key, value) in pairs {
+        let _ = writeln!(object, "{}  {}: {},", pad, js(key), js(value));
+    }
+
+    let _ = write!(object, "{}}}", pad);
+    object
+}
+
+/// `new URLSearchParams(...)` of an urlencoded body.
+fn js_search_params(fields: &[(Text, Text)]) -> String {
+    match has_unique_keys(fields) {
+        true => format!("new URLSearchParams({})", js_object(fields, 2)),
+        false => {
+            let entries: Vec<String> = fields
+                .iter()
+                .map(|(key, value)| format!("    [{}, {}],", js(key), js(value)))
+                .collect();
+
+            format!("new URLSearchParams([\n{}\n  ])", entries.join("\n"))
+        }
+    }
+}
+
+/// Builds `form` before the request for multipart bodies.
+fn js_form(request: &Request) -> String {
+    let fields = match &request.body {
+        Some(Body::Multipart(fields)) => fields,
+        _ => return String::new(),
+    };
+
+    let mut code = String::from("const form = new FormData();\n");
+
+    for (key, field) in fields {
+        let _ = match field {
+            Field::Value(value) => writeln!(code, "form.append({}, {});", js(key), js(value)),
+            Field::File(path) => writeln!(
+                code,
+                "form.append({}, await openAsBlob({}), {});",
+                js(key),
+                js(&Text::literal(path)),
+                js(&Text::literal(file_name(path)))
+            ),
+        };
+    }
+
+    code.push('\n');
+    code
+}
+
+fn fetch(request: &Request) -> String {
+    let mut code = String::new();
+
+    if request.has_files() {
+        code.push_str("import { openAsBlob } from \"node:fs\";\n\n");
+    }
+
+    code.push_str(&js_form(request));
+
+    let _ = writeln!(
+        code,
+        "const response = await fetch({}, {{",
+        js(&request.full_url())
+    );
+    let _ = writeln!(code, "  method: {:?},", request.method);
+
+    let headers: Vec<(Text, Text)> = request
+        .headers
+        .iter()
+        .map(|(name, value)| (Text::literal(name), value.clone()))
+        .collect();
+
+    if !headers.is_empty() {
+        let _ = writeln!(code, "  headers: {},", js_object(&headers, 2));
+    }
+
+    match &request.body {
+        Some(Body::Raw(text)) => {
+            let _ = writeln!(code, "  body: {},", js(text));
+        }
+        Some(Body::UrlEncoded(fields)) => {
+            let _ = writeln!(code, "  body: {},", js_search_params(fields));
+        }
+        Some(Body::Multipart(_)) => code.push_str("  body: form,\n"),
+        None => {}
+    }
+
+    code.push_str("});\n\nconsole.log(response.status);\nconsole.log(await response.text());\n");
+    code
+}
+
+fn axios(request: &Request) -> String {
+    let mut code = String::new();
+
+    if request.has_files() {
+        code.push_str("import { openAsBlob } from \"node:fs\";\n");
+    }
+
+    code.push_str("import axios from \"axios\";\n\n");
+    code.push_str(&js_form(request));
+
+    code.push_str("const response = await axios.request({\n");
+    let _ = writeln!(code, "  method: {:?},", request.method.to_lowercase());
+    let _ = writeln!(code, "  url: {},", js(&request.url));
+
+    if !request.query.is_empty() {
+        let _ = writeln!(code, "  params: {},", js_object(&request.query, 2));
+    }
+
+    let auth = request
+        .auth()
+        .filter(|auth| matches!(auth, Auth::Basic { .. }));
+
+    let headers: Vec<(Text, Text)> = request
+        .headers_without(&auth)
+        .into_iter()
+        .map(|(name, value)| (Text::literal(name), value))
+        .collect();
+
+    if !headers.is_empty() {
+        let _ = writeln!(code, "  headers: {},", js_object(&headers, 2));
+    }
+
+    if let Some(Auth::Basic { username, password }) = &auth {
+        let _ = writeln!(
+            code,
+            "  auth: {{ username: {}, password: {} }},",
+            js(&Text::literal(username)),
+            js(&Text::literal(password))
+        );
+    }
+
+    match &request.body {
+        Some(Body::Raw(text)) => {
+            let _ = writeln!(code, "  data: {},", js(text));
+        }
+        Some(Body::UrlEncoded(fields)) => {
+            let _ = writeln!(code, "  data: {},", js_search_params(fields));
+        }
+        Some(Body::Multipart(_)) => code.push_str("  data: form,\n"),
+        None => {}
+    }
+
+    code.push_str("});\n\nconsole.log(response.status);\nconsole.log(response.data);\n");
+    code
+}
+
+/// A Go string expression, variables are concatenated with `os.Getenv`.
+fn go_string(text: &Text) -> String {
+    let parts: Vec<String> = text
+        .0
+        .iter()
+        .map(|part| match part {
+            Part::Literal(text) if text.contains('\n') && !text.contains(['`', '\r']) => {
+                format!("`{}`", text)
+            }
+            Part::Literal(text) => format!("\"{}\"", escape(text, false)),
+            Part::Variable(name) => format!("os.Getenv({:?})", name),
+        })
+        .collect();
+
+    match parts.is_empty() {
+        true => "\"\"".to_string(),
+        false => parts.join(" + "),
+    }
+}
+
+fn go(request: &Request) -> String {
+    let mut imports = BTreeSet::from(["fmt", "io", "net/http"]);
+    let mut code = String::new();
+
+    if !request.variables().is_empty() {
+        imports.insert("os");
+    }
+
+    let check = "\tif err != nil {\n\t\tpanic(err)\n\t}\n";
+    let mut headers = request.headers.clone();
+    let has_content_type = headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+
+    let body = match &request.body {
+        None => "nil",
+        Some(Body::Raw(text)) => {
+            imports.insert("strings");
+            let _ = writeln!(code, "\tbody := strings.NewReader({})\n", go_string(text));
+            "body"
+        }
+        Some(Body::UrlEncoded(fields)) => {
+            imports.extend(["strings", "net/url"]);
+            code.push_str("\tform := url.Values{}\n");
+
+            for (key, value) in fields {
+                let _ = writeln!(code, "\tform.Add({}, {})", go_string(key), go_string(value));
+            }
+
+            code.push_str("\tbody := strings.NewReader(form.Encode())\n\n");
+
+            if !has_content_type {
+                headers.push((
+                    "Content-Type".into(),
+                    Text::literal("application/x-www-form-urlencoded"),
+                ));
+            }
+
+            "body"
+        }
+        Some(Body::Multipart(fields)) => {
+            imports.extend(["bytes", "mime/multipart"]);
+            code.push_str("\tbody := &bytes.Buffer{}\n\twriter := multipart.NewWriter(body)\n\n");
+
+            for (i, (key, field)) in fields.iter().enumerate() {
+                match field {
+                    Field::Value(value) => {
+                        let _ = writeln!(
+                            code,
+                            "\tif err := writer.WriteField({}, {}); err != nil {{\n\t\tpanic(err)\n\t}}",
+                            go_string(key),
+                            go_string(value)
+                        );
+                    }
+                    Field::File(path) => {
+                        imports.extend(["os", "path/filepath"]);
+
+                        let path = go_string(&Text::literal(path));
+                        let _ = write!(
+                            code,
+                            "\n\tfile{i}, err := os.Open({path})\n{check}\tdefer file{i}.Close()\n\n\
+                             \tpart{i}, err := writer.CreateFormFile({key}, filepath.Base({path}))\n{check}\
+                             \tif _, err := io.Copy(part{i}, file{i}); err != nil {{\n\t\tpanic(err)\n\t}}\n\n",
+                            i = i,
+                            path = path,
+                            check = check,
+                            key = go_string(key),
+                        );
+                    }
+                }
+            }
+
+            code.push_str("\tif err := writer.Close(); err != nil {\n\t\tpanic(err)\n\t}\n\n");
+            "body"
+        }
+    };
+
+    let _ = write!(
+        code,
+        "\treq, err := http.NewRequest({:?}, {}, {})\n{}",
+        request.method,
+        go_string(&request.url),
+        body,
+        check
+    );
+
+    if !request.query.is_empty() {
+        code.push_str("\n\tquery := req.URL.Query()\n");
+
+        for (key, value) in &request.query {
+            let _ = writeln!(
+                code,
+                "\tquery.Add({}, {})",
+                go_string(key),
+                go_string(value)
+            );
+        }
+
+        code.push_str("\treq.URL.RawQuery = query.Encode()\n");
+    }
+
+    let auth = request
+        .auth()
+        .filter(|auth| matches!(auth, Auth::Basic { .. }));
+
+    if !headers.is_empty() || auth.is_some() || matches!(request.body, Some(Body::Multipart(_))) {
+        code.push('\n');
+    }
+
+    for (name, value) in &headers {
+        if auth.is_some() && name.eq_ignore_ascii_case("authorization") {
+            continue;
+        }
+
+        let _ = writeln!(code, "\treq.Header.Add({:?}, {})", name, go_string(value));
+    }
+
+    if matches!(request.body, Some(Body::Multipart(_))) {
+        code.push_str("\treq.Header.Set(\"Content-Type\", writer.FormDataContentType())\n");
+    }
+
+    if let Some(Auth::Basic { username, password }) = &auth {
+        let _ = writeln!(
+            code,
+            "\treq.SetBasicAuth({}, {})",
+            go_string(&Text::literal(username)),
+            go_string(&Text::literal(password))
+        );
+    }
+
+    let _ = write!(
+        code,
+        "\n\tres, err := http.DefaultClient.Do(req)\n{check}\tdefer res.Body.Close()\n\n\
+         \tdata, err := io.ReadAll(res.Body)\n{check}\n\
+         \tfmt.Println(res.Status)\n\tfmt.Println(string(data))\n",
+        check = check
+    );
+
+    let imports: Vec<String> = imports.iter().map(|i| format!("\t{:?}", i)).collect();
+
+    format!(
+        "package main\n\nimport (\n{}\n)\n\nfunc main() {{\n{}}}\n",
+        imports.join("\n"),
+        code
+    )
+}
+
+/// A Rust string literal, raw when it has multiple lines.
+fn rs_literal(text: &str) -> String {
+    if !text.contains('\n') {
+        return format!("\"{}\"", escape(text, true));
+    }
+
+    let mut hashes = String::from("#");
+
+    while text.contains(&format!("\"{}", hashes)) {
+        hashes.push('#');
+    }
+
+    format!("r{}\"{}\"{}", hashes, text, hashes)
+}
+
+/// A Rust string expression, `format!` reading the environment when it has variables.
+fn rs(text: &Text) -> String {
+    if let Some(literal) = text.as_literal() {
+        return rs_literal(&literal);
+    }
+
+    if let [Part::Variable(name)] = text.0.as_slice() {
+        return format!("std::env::var({:?})?", name);
+    }
+
+    let mut format = String::new();
+    let mut arguments = vec![];
+
+    for part in &text.0 {
+        match part {
+            Part::Literal(text) => format.push_str(&text.replace('{', "{{").replace('}', "}}")),
+            Part::Variable(name) => {
+                format.push_str("{}");
+                arguments.push(format!("std::env::var({:?})?", name));
+            }
+        }
+    }
+
+    format!("format!({}, {})", rs_literal(&format), arguments.join(", "))
+}
+
+/// An owned string when `owned`, so that literals and `format!` can be in the same array.
+fn rs_value(text: &Text, owned: bool) -> String {
+    match owned && text.is_literal() {
+        true => format!("{}.to_string()", rs(text)),
+        false => rs(text),
+    }
+}
+
+fn rust(request: &Request) -> String {
+    let mut code = String::new();
+    let mut calls = vec![];
+
+    if let Some(Body::Multipart(fields)) = &request.body {
+        code.push_str("    let form = multipart::Form::new()");
+
+        for (key, field) in fields {
+            let _ = match field {
+                Field::Value(value) => write!(code, "\n        .text({}, {})", rs(key), rs(value)),
+                Field::File(path) => write!(
+                    code,
+                    "\n        .part(\n            {},\n            multipart::Part::bytes(std::fs::read({})?).file_name({}),\n        )",
+                    rs(key),
+                    rs_literal(path),
+                    rs_literal(&file_name(path))
+                ),
+            };
+        }
+
+        code.push_str(";\n\n");
+    }
+
+    let url = rs(&request.url);
+
+    let method = match request.method.as_str() {
+        "OPTIONS" => format!(".request(reqwest::Method::OPTIONS, {})", url),
+        method => format!(".{}({})", method.to_lowercase(), url),
+    };
+    calls.push(method);
+
+    for (key, value) in &request.query {
+        calls.push(format!(".query(&[({}, {})])", rs(key), rs(value)));
+    }
+
+    let auth = request.auth();
+
+    for (name, value) in request.headers_without(&auth) {
+        calls.push(format!(".header({:?}, {})", name, rs(&value)));
+    }
+
+    match &auth {
+        Some(Auth::Basic { username, password }) => calls.push(format!(
+            ".basic_auth({}, Some({}))",
+            rs_literal(username),
+            rs_literal(password)
+        )),
+        Some(Auth::Bearer(token)) => calls.push(format!(".bearer_auth({})", rs(token))),
+        None => {}
+    }
+
+    match &request.body {
+        Some(Body::Raw(text)) => calls.push(format!(".body({})", rs(text))),
+        Some(Body::UrlEncoded(fields)) => {
+            let owned = fields
+                .iter()
+                .any(|(k, v)| !k.is_literal() || !v.is_literal());
+            let pairs: Vec<String> = fields
+                .iter()
+                .map(|(key, value)| {
+                    format!(
+                        "            ({}, {}),",
+                        rs_value(key, owned),
+                        rs_value(value, owned)
+                    )
+                })
+                .collect();
+
+            calls.push(format!(".form(&[\n{}\n        ])", pairs.join("\n")));
+        }
+        Some(Body::Multipart(_)) => calls.push(".multipart(form)".to_string()),
+        None => {}
+    }
+
+    calls.extend([".send()".to_string(), ".await?;".to_string()]);
+
+    let _ = write!(
+        code,
+        "    let response = reqwest::Client::new()\n        {}\n\n\
+         \x20   println!(\"{{}}\", response.status());\n\
+         \x20   println!(\"{{}}\", response.text().await?);\n\n    Ok(())\n",
+        calls.join("\n        ")
+    );
+
+    let imports = match request.body {
+        Some(Body::Multipart(_)) => "use reqwest::multipart;\n\n",
+        _ => "",
+    };
+
+    format!(
+        "{}#[tokio::main]\nasync fn main() -> Result<(), Box<dyn std::error::Error>> {{\n{}}}\n",
+        imports, code
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_shell_words() {
+        assert_eq!(sh(&Text::parse("plain-word")), "plain-word");
+        assert_eq!(sh(&Text::parse(r#"it's "$HOME""#)), r#"'it'\''s "$HOME"'"#);
+        assert_eq!(
+            sh(&Text::parse("a\"$b`\\_.TOKEN\nc")),
+            "\"a\\\"\\$b\\`\\\\${TOKEN}\nc\""
+        );
+    }
+
+    #[test]
+    fn escapes_python_strings() {
+        assert_eq!(py(&Text::parse(r#"a "b" \ c"#)), r#""a \"b\" \\ c""#);
+        assert_eq!(py(&Text::parse("l1\nl2")), "\"\"\"l1\nl2\"\"\"");
+        assert_eq!(py(&Text::parse("l1\n\\l2")), r#""l1\n\\l2""#);
+        assert_eq!(
+            py(&Text::parse(r#"{"id": "_.ID"}"#)),
+            r#"f"{{\"id\": \"{os.environ['ID']}\"}}""#
+        );
+        assert_eq!(py(&Text::parse("_.TOKEN")), r#"os.environ["TOKEN"]"#);
+    }
+
+    #[test]
+    fn escapes_javascript_strings() {
+        assert_eq!(js(&Text::parse(r#"a "b" $c"#)), r#""a \"b\" $c""#);
+        assert_eq!(
+            js(&Text::parse("x`${y}\\\n_.ID")),
+            "`x\\`\\${y}\\\\\n${process.env.ID}`"
+        );
+        assert_eq!(js(&Text::parse("_.TOKEN")), "process.env.TOKEN");
+    }
+
+    #[test]
+    fn escapes_go_strings() {
+        assert_eq!(go_string(&Text::parse("a \"b\"\tc")), r#""a \"b\"\tc""#);
+        assert_eq!(
+            go_string(&Text::parse("l1\nl2 _.ID")),
+            "`l1\nl2 ` + os.Getenv(\"ID\")"
+        );
+        assert_eq!(go_string(&Text::parse("a`\nb")), r#""a`\nb""#);
+        assert_eq!(go_string(&Text::parse("")), r#""""#);
+    }
+
+    #[test]
+    fn escapes_rust_strings() {
+        assert_eq!(rs(&Text::parse("a \"b\"\u{1}")), r#""a \"b\"\u{1}""#);
+        assert_eq!(rs(&Text::parse("l1\n\"#l2")), "r##\"l1\n\"#l2\"##");
+        assert_eq!(
+            rs(&Text::parse(r#"{"id": _.ID}"#)),
+            r#"format!("{{\"id\": {}}}", std::env::var("ID")?)"#
+        );
+    }
+
+    #[test]
+    fn generates_code_with_file_fields() {
+        let source = r#"
+[post]
+url = "https://example.com/upload"
+
+[post.form_multipart]
+content = [
+    { field = "name", value = "O'Brien" },
+    { field = "avatar", file = "files/a.png" },
+]
+"#;
+
+        let curl = generate(source, Language::Curl, None).unwrap();
+
+        // `--form-string` keeps values starting with `@` or `<` as text.
+        assert!(
+            curl.contains(r#"--form-string 'name=O'\''Brien'"#),
+            "{}",
+            curl
+        );
+        assert!(curl.contains("-F avatar=@files/a.png"), "{}", curl);
+    }
+}
@@ -1,25 +1,123 @@
-use crate::http_runner::{self, PandaHttpResponse, PandaTomlRequest};
-use crate::utils::{replace_variables, variables_to_hashmap};
+use crate::body::{BodyChunk, ResponseStore};
+use crate::codegen::{self, Language};
+use crate::cookies::{CookieEntry, PandaCookieJar};
+use crate::curl;
+use crate::error::PandaError;
+use crate::har::{self, HarOptions};
+use crate::history::{HistoryEntry, HistoryFilter, HistoryStore, HistorySummary, PruneOptions};
+use crate::http_runner::{self, PandaHttpResponse, RunOptions};
+use crate::import::{self, ImportFormat, ImportSummary};
+use crate::insomnia;
+use crate::openapi;
+use crate::postman;
+use crate::runner::{self, RunSettings, RunSummary};
+use crate::utils::variables_to_hashmap;
+use crate::AppData;
 
+use std::collections::HashMap;
 use std::env;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use tauri::{self, AppHandle, Listener, Manager, Runtime, WebviewWindow};
+use tauri::{self, AppHandle, Emitter, Listener, Manager, Runtime, State, WebviewWindow};
+
+/// Builds the run options from the project currently opened in the app.
+fn get_run_options(
+    state: &State<'_, Mutex<AppData>>,
+    environment: Option<&str>,
+    variables: HashMap<String, String>,
+) -> Result<RunOptions, PandaError> {
+    let cwd = state.lock().unwrap().cwd.clone();
+
+    // No project has been opened yet eg. the demo file.
+    if cwd.is_empty() {
+        return Ok(RunOptions {
+            variables,
+            ..Default::default()
+        });
+    }
+
+    RunOptions::for_project(Path::new(&cwd), environment, variables)
+}
+
+/// Opens the history of the opened project.
+fn get_history(state: &State<'_, Mutex<AppData>>) -> Result<HistoryStore, PandaError> {
+    let cwd = state.lock().unwrap().cwd.clone();
+
+    if cwd.is_empty() {
+        return Err(PandaError::Config(
+            "Open a project to see its history.".into(),
+        ));
+    }
+
+    HistoryStore::open(Path::new(&cwd))
+}
+
+/// Loads the cookie jar of the opened project.
+fn get_cookie_jar(
+    state: &State<'_, Mutex<AppData>>,
+    environment: Option<&str>,
+) -> Result<PandaCookieJar, PandaError> {
+    let cwd = state.lock().unwrap().cwd.clone();
+
+    if cwd.is_empty() {
+        return Err(PandaError::Config(
+            "Open a project to manage its cookies.".into(),
+        ));
+    }
+
+    PandaCookieJar::load(Path::new(&cwd), environment).map_err(|err| PandaError::Io(err.into()))
+}
 
 #[tauri::command(rename_all = "snake_case")]
 pub async fn cmd_http_request<R: Runtime>(
     app_handle: AppHandle<R>,
+    state: State<'_, Mutex<AppData>>,
+    response_store: State<'_, ResponseStore>,
     toml_schema: &str,
     default_variables: &str,
-) -> Result<PandaHttpResponse, String> {
+    environment: Option<String>,
+    file_path: Option<String>,
+) -> Result<PandaHttpResponse, PandaError> {
+    let parsed_variables = variables_to_hashmap(default_variables)?;
+
+    send_request(
+        &app_handle,
+        &state,
+        &response_store,
+        toml_schema,
+        file_path.as_deref(),
+        environment.as_deref(),
+        parsed_variables,
+    )
+    .await
+}
+
+/// Runs a request until it completes or the UI cancels it, recording it in the history
+/// when a project is opened.
+async fn send_request<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    state: &State<'_, Mutex<AppData>>,
+    response_store: &State<'_, ResponseStore>,
+    toml_schema: &str,
+    file_path: Option<&str>,
+    environment: Option<&str>,
+    variables: HashMap<String, String>,
+) -> Result<PandaHttpResponse, PandaError> {
     let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
-    let webview: WebviewWindow<R> = app_handle.get_webview_window("main").unwrap();
+
+    let webview: WebviewWindow<R> = match app_handle.get_webview_window("main") {
+        Some(w) => w,
+        None => {
+            return Err(PandaError::Request(
+                "The main window is not available.".into(),
+            ))
+        }
+    };
 
     let temp_event_id = Arc::new(Mutex::new(0));
     let temp_event_id_clone = Arc::clone(&temp_event_id);
 
-    let parsed_variables = variables_to_hashmap(default_variables);
-    let interpolated_toml = replace_variables(toml_schema, &parsed_variables);
+    let options = get_run_options(state, environment, variables)?;
 
     webview.listen_any("cancel_request", move |ev| {
         if let Err(e) = cancel_tx.send(true) {
@@ -32,26 +130,350 @@ pub async fn cmd_http_request<R: Runtime>(
         println!("Request is cancelled")
     });
 
-    let schema: PandaTomlRequest = match toml::from_str(interpolated_toml.as_str()) {
-        Ok(d) => d,
-        Err(e) => return Err(e.to_string()),
-    };
-
-    let res = http_runner::run_single_request(schema, cancel_rx).await;
+    let res = http_runner::run_toml_request(toml_schema, &options, cancel_rx).await;
 
     // Try to unlisten after each individual request
     webview.unlisten(*temp_event_id_clone.lock().unwrap());
 
-    res
+    if !options.project_root.as_os_str().is_empty() {
+        let entry = HistoryEntry::new(toml_schema, file_path, environment, &options, &res);
+
+        // A request that went through is still returned when it can't be recorded.
+        if let Err(err) = HistoryStore::open(&options.project_root).and_then(|h| h.append(&entry)) {
+            println!("Failed to record history {err:?}");
+        }
+    }
+
+    let mut res = res?;
+    response_store.insert(res.id.clone(), std::mem::take(&mut res.body));
+
+    Ok(res)
 }
 
 #[tauri::command(rename_all = "snake_case")]
-pub async fn cmd_update_cwd(curr_dir: &str) -> Result<String, String> {
+pub fn cmd_list_history(
+    state: State<'_, Mutex<AppData>>,
+    filter: Option<HistoryFilter>,
+) -> Result<Vec<HistorySummary>, PandaError> {
+    get_history(&state)?.list(&filter.unwrap_or_default())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn cmd_get_history_entry(
+    state: State<'_, Mutex<AppData>>,
+    id: &str,
+) -> Result<HistoryEntry, PandaError> {
+    get_history(&state)?.get(id)
+}
+
+/// Sends the request of a history entry again, `environment` defaults to the one it was
+/// sent with.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn cmd_replay_history<R: Runtime>(
+    app_handle: AppHandle<R>,
+    state: State<'_, Mutex<AppData>>,
+    response_store: State<'_, ResponseStore>,
+    id: &str,
+    default_variables: &str,
+    environment: Option<String>,
+) -> Result<PandaHttpResponse, PandaError> {
+    let entry = get_history(&state)?.get(id)?;
+    let cwd = state.lock().unwrap().cwd.clone();
+    let source = entry.replay_source(Path::new(&cwd))?;
+    let parsed_variables = variables_to_hashmap(default_variables)?;
+    let environment = environment.or(entry.environment);
+
+    send_request(
+        &app_handle,
+        &state,
+        &response_store,
+        &source,
+        entry.file.as_deref(),
+        environment.as_deref(),
+        parsed_variables,
+    )
+    .await
+}
+
+/// Runs the requests of a file or folder of the opened project, progress is emitted as
+/// `collection_run` events and a `cancel_run` event stops the run.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn cmd_run_collection<R: Runtime>(
+    app_handle: AppHandle<R>,
+    state: State<'_, Mutex<AppData>>,
+    path: &str,
+    default_variables: &str,
+    environment: Option<String>,
+    stop_on_failure: Option<bool>,
+    data_file: Option<String>,
+) -> Result<RunSummary, PandaError> {
+    let cwd = state.lock().unwrap().cwd.clone();
+
+    if cwd.is_empty() {
+        return Err(PandaError::Config(
+            "Open a project to run its collection.".into(),
+        ));
+    }
+
+    let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+
+    let webview: WebviewWindow<R> = match app_handle.get_webview_window("main") {
+        Some(w) => w,
+        None => {
+            return Err(PandaError::Request(
+                "The main window is not available.".into(),
+            ))
+        }
+    };
+
+    let parsed_variables = variables_to_hashmap(default_variables)?;
+    let options = get_run_options(&state, environment.as_deref(), parsed_variables)?;
+
+    let settings = RunSettings {
+        stop_on_failure,
+        snapshot: None,
+        data: data_file.map(PathBuf::from),
+    };
+
+    let event_id = webview.listen_any("cancel_run", move |_| {
+        if let Err(e) = cancel_tx.send(true) {
+            println!("Failed to send cancel event for collection run {e:?}");
+        }
+    });
+
+    let summary = runner::run_collection(
+        Path::new(&cwd),
+        Path::new(path),
+        options,
+        &settings,
+        cancel_rx,
+        |event| {
+            if let Err(e) = app_handle.emit("collection_run", &event) {
+                println!("Failed to emit collection run progress {e:?}");
+            }
+        },
+    )
+    .await;
+
+    webview.unlisten(event_id);
+
+    summary
+}
+
+/// Removes old history entries, returning how many were removed.
+#[tauri::command(rename_all = "snake_case")]
+pub fn cmd_prune_history(
+    state: State<'_, Mutex<AppData>>,
+    options: PruneOptions,
+) -> Result<usize, PandaError> {
+    get_history(&state)?.prune(&options)
+}
+
+/// History entries matching `filter` as a HAR log, oldest first.
+#[tauri::command(rename_all = "snake_case")]
+pub fn cmd_export_history_har(
+    state: State<'_, Mutex<AppData>>,
+    filter: HistoryFilter,
+) -> Result<serde_json::Value, PandaError> {
+    let mut entries = get_history(&state)?.find(&filter)?;
+    entries.reverse();
+
+    Ok(har::from_history(&entries))
+}
+
+/// Reads `length` bytes of a response body starting at `offset`, base64 encoded.
+#[tauri::command(rename_all = "snake_case")]
+pub fn cmd_read_response_body(
+    response_store: State<'_, ResponseStore>,
+    response_id: &str,
+    offset: usize,
+    length: usize,
+) -> Result<BodyChunk, PandaError> {
+    response_store
+        .read_chunk(response_id, offset, length)
+        .map_err(|err| PandaError::Body(err.into()))
+}
+
+/// Saves the raw response body to `path`, returning the number of bytes written.
+#[tauri::command(rename_all = "snake_case")]
+pub fn cmd_save_response_body(
+    response_store: State<'_, ResponseStore>,
+    response_id: &str,
+    path: &str,
+) -> Result<usize, PandaError> {
+    response_store
+        .save(response_id, Path::new(path))
+        .map_err(|err| PandaError::Io(err.into()))
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn cmd_get_cookies(
+    state: State<'_, Mutex<AppData>>,
+    environment: Option<String>,
+) -> Result<Vec<CookieEntry>, PandaError> {
+    let jar = get_cookie_jar(&state, environment.as_deref())?;
+
+    Ok(jar.list())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn cmd_set_cookie(
+    state: State<'_, Mutex<AppData>>,
+    environment: Option<String>,
+    cookie: CookieEntry,
+) -> Result<Vec<CookieEntry>, PandaError> {
+    let jar = get_cookie_jar(&state, environment.as_deref())?;
+
+    jar.set(&cookie)
+        .map_err(|err| PandaError::Request(err.into()))?;
+    jar.save().map_err(|err| PandaError::Io(err.into()))?;
+
+    Ok(jar.list())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn cmd_delete_cookie(
+    state: State<'_, Mutex<AppData>>,
+    environment: Option<String>,
+    domain: String,
+    path: String,
+    name: String,
+) -> Result<Vec<CookieEntry>, PandaError> {
+    let jar = get_cookie_jar(&state, environment.as_deref())?;
+
+    if !jar.remove(&domain, &path, &name) {
+        return Err(PandaError::Request(
+            format!("Cookie {:?} was not found.", name).into(),
+        ));
+    }
+
+    jar.save().map_err(|err| PandaError::Io(err.into()))?;
+
+    Ok(jar.list())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub fn cmd_clear_cookies(
+    state: State<'_, Mutex<AppData>>,
+    environment: Option<String>,
+) -> Result<(), PandaError> {
+    let jar = get_cookie_jar(&state, environment.as_deref())?;
+
+    jar.clear();
+    jar.save().map_err(|err| PandaError::Io(err.into()))
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn cmd_update_cwd(curr_dir: &str) -> Result<String, PandaError> {
     let dir = Path::new(curr_dir);
 
     if let Err(v) = env::set_current_dir(&dir) {
-        return Err(v.to_string());
+        return Err(PandaError::Io(v.to_string().into()));
+    }
+
+    match env::current_dir() {
+        Ok(cwd) => Ok(cwd.to_string_lossy().to_string()),
+        Err(err) => Err(PandaError::Io(err.to_string().into())),
+    }
+}
+
+/// Converts a file exported by another tool into a new folder of the opened project.
+#[tauri::command(rename_all = "snake_case")]
+pub fn cmd_import_collection(
+    state: State<'_, Mutex<AppData>>,
+    format: ImportFormat,
+    path: &str,
+    into: Option<String>,
+) -> Result<ImportSummary, PandaError> {
+    let cwd = state.lock().unwrap().cwd.clone();
+
+    if cwd.is_empty() {
+        return Err(PandaError::Config(
+            "Open a project to import a collection into it.".into(),
+        ));
+    }
+
+    let imported = match format {
+        ImportFormat::Postman => postman::convert(postman::read_collection(Path::new(path))?),
+        ImportFormat::Insomnia => {
+            let name = Path::new(path)
+                .file_stem()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            insomnia::convert(insomnia::read_export(Path::new(path))?, &name)
+        }
+        ImportFormat::Openapi => openapi::convert(openapi::read_spec(Path::new(path))?),
+    };
+
+    import::write_import(Path::new(&cwd), imported, into.as_deref().map(Path::new))
+}
+
+/// Converts the requests of a HAR file into a new folder of the opened project.
+#[tauri::command(rename_all = "snake_case")]
+pub fn cmd_import_har(
+    state: State<'_, Mutex<AppData>>,
+    path: &str,
+    into: Option<String>,
+    options: HarOptions,
+) -> Result<ImportSummary, PandaError> {
+    let cwd = state.lock().unwrap().cwd.clone();
+
+    if cwd.is_empty() {
+        return Err(PandaError::Config(
+            "Open a project to import a collection into it.".into(),
+        ));
     }
 
-    Ok(env::current_dir().unwrap().to_str().unwrap().to_string())
+    let path = Path::new(path);
+    let name = path
+        .file_stem()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let imported = har::convert(har::read_har(path)?, &name, &options);
+
+    import::write_import(Path::new(&cwd), imported, into.as_deref().map(Path::new))
+}
+
+/// Converts a pasted curl command to a request file, in `into` or the project collection.
+#[tauri::command(rename_all = "snake_case")]
+pub fn cmd_import_curl(
+    state: State<'_, Mutex<AppData>>,
+    command: &str,
+    name: Option<String>,
+    into: Option<String>,
+) -> Result<ImportSummary, PandaError> {
+    let cwd = state.lock().unwrap().cwd.clone();
+
+    if cwd.is_empty() {
+        return Err(PandaError::Config(
+            "Open a project to import a request into it.".into(),
+        ));
+    }
+
+    let mut curl = curl::parse(command)?;
+
+    if let Some(name) = name.filter(|name| !name.trim().is_empty()) {
+        curl.request.name = name;
+    }
+
+    import::write_request(
+        Path::new(&cwd),
+        &curl.request,
+        curl.warnings,
+        into.as_deref().map(Path::new),
+    )
+}
+
+/// Generates code sending the request, `default_variables` are substituted when given.
+#[tauri::command(rename_all = "snake_case")]
+pub fn cmd_generate_code(
+    toml_schema: &str,
+    language: Language,
+    default_variables: Option<&str>,
+) -> Result<String, PandaError> {
+    let variables = default_variables.map(variables_to_hashmap).transpose()?;
+
+    codegen::generate(toml_schema, language, variables.as_ref())
 }
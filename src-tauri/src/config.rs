@@ -0,0 +1,246 @@
+use crate::error::{ErrorDetails, PandaError, SourceLocation};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+pub const PANDA_CONFIG: &str = "panda.config.json";
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct PandaConfig {
+    pub name: String,
+    pub version: String,
+    /// The user defined directory relative to the `panda.config.json`
+    pub collection: String,
+    /// Client certificates used for mutual TLS, the first entry whose `host` matches wins.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub client_certificates: Vec<ClientCertificate>,
+    /// Project wide proxy, can be overridden per environment and per request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfig>,
+    /// Settings keyed by environment name eg. `staging`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub environments: HashMap<String, EnvironmentConfig>,
+    /// Snapshot settings applied to every request, `[method.snapshot]` adds to them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<SnapshotConfig>,
+    /// Default values of `_.NAME` variables, the ones given to a run take precedence.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, String>,
+}
+
+impl PandaConfig {
+    /// Resolves the proxy for the active environment, environment fields take precedence.
+    pub fn get_proxy(&self, environment: Option<&str>) -> Option<ProxyConfig> {
+        let env_proxy = environment
+            .and_then(|name| self.environments.get(name))
+            .and_then(|env| env.proxy.clone());
+
+        merge_proxy(env_proxy, self.proxy.clone())
+    }
+
+    /// Project variables overridden by the ones of the active environment.
+    pub fn get_variables(&self, environment: Option<&str>) -> HashMap<String, String> {
+        let mut variables = self.variables.clone();
+
+        if let Some(env) = environment.and_then(|name| self.environments.get(name)) {
+            variables.extend(env.variables.clone());
+        }
+
+        variables
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct EnvironmentConfig {
+    pub proxy: Option<ProxyConfig>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, String>,
+}
+
+/// Proxy settings shared by the project config, environments and `[method.proxy]` in requests.
+///
+/// ```toml
+/// [get.proxy]
+/// url = "socks5://127.0.0.1:1080"
+/// username = "_.PROXY_USER"
+/// password = "_.PROXY_PASSWORD"
+/// no_proxy = "localhost,*.internal"
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ProxyConfig {
+    /// Proxy used for every scheme eg. `http://127.0.0.1:8080` or `socks5://127.0.0.1:1080`.
+    pub url: Option<String>,
+    /// Proxy used for `http://` urls only.
+    pub http: Option<String>,
+    /// Proxy used for `https://` urls only.
+    pub https: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Comma separated hosts that bypass the proxy, same format as `NO_PROXY`.
+    pub no_proxy: Option<String>,
+    /// Honour `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY`, defaults to `true`.
+    pub use_env: Option<bool>,
+}
+
+/// Merges two optional proxy configs, `primary` fields take precedence.
+pub fn merge_proxy(
+    primary: Option<ProxyConfig>,
+    fallback: Option<ProxyConfig>,
+) -> Option<ProxyConfig> {
+    match (primary, fallback) {
+        (Some(primary), Some(fallback)) => Some(primary.merge(fallback)),
+        (primary, fallback) => primary.or(fallback),
+    }
+}
+
+impl ProxyConfig {
+    /// Fills the fields missing from `self` with the ones from `fallback`.
+    pub fn merge(self, fallback: ProxyConfig) -> ProxyConfig {
+        ProxyConfig {
+            url: self.url.or(fallback.url),
+            http: self.http.or(fallback.http),
+            https: self.https.or(fallback.https),
+            username: self.username.or(fallback.username),
+            password: self.password.or(fallback.password),
+            no_proxy: self.no_proxy.or(fallback.no_proxy),
+            use_env: self.use_env.or(fallback.use_env),
+        }
+    }
+}
+
+/// What is recorded in a response snapshot, shared by the project config and
+/// `[method.snapshot]` in requests.
+///
+/// ```toml
+/// [get.snapshot]
+/// headers = ["content-type", "cache-control"]
+/// ignore = ["$.body.id", "$.body.items[*].created_at", "$.headers.etag"]
+/// ```
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    /// Response headers recorded, defaults to `content-type`.
+    pub headers: Option<Vec<String>>,
+    /// JSONPath expressions evaluated against the snapshot, `$.status`, `$.headers` and
+    /// `$.body` like the paths of its changes. Matching values are not compared.
+    pub ignore: Vec<String>,
+}
+
+impl SnapshotConfig {
+    /// Request `headers` replace the project ones, `ignore` expressions are combined.
+    pub fn merge(self, fallback: SnapshotConfig) -> SnapshotConfig {
+        SnapshotConfig {
+            headers: self.headers.or(fallback.headers),
+            ignore: fallback.ignore.into_iter().chain(self.ignore).collect(),
+        }
+    }
+}
+
+/// A client certificate presented to hosts matching `host`.
+///
+/// Either `cert` (PEM, optionally followed by its `key`) or `pkcs12` should be provided.
+/// Paths are relative to the directory containing `panda.config.json`.
+///
+/// ```json
+/// { "host": "*.mesh.internal", "cert": "./certs/client.pem", "key": "./certs/client.key", "ca": "./certs/mesh-ca.pem" }
+/// { "host": "payments.internal", "pkcs12": "./certs/client.p12", "passphrase": "_.CERT_PASSWORD" }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClientCertificate {
+    /// Hostname glob eg. `*.example.com`.
+    pub host: String,
+    pub cert: Option<String>,
+    pub key: Option<String>,
+    pub pkcs12: Option<String>,
+    /// Supports variables eg. `_.CERT_PASSWORD`.
+    pub passphrase: Option<String>,
+    /// PEM bundle of extra root certificates trusted for these hosts eg. a private mesh CA.
+    pub ca: Option<String>,
+}
+
+/// Reads `panda.config.json` from the project root.
+pub fn read_config(project_root: &str) -> Result<PandaConfig, PandaError> {
+    let config_path = Path::new(project_root).join(PANDA_CONFIG);
+
+    let config = fs::read_to_string(&config_path).map_err(|err| {
+        PandaError::Config(format!("Failed to read config {:?}: {}", config_path, err).into())
+    })?;
+
+    serde_json::from_str(&config).map_err(|err| {
+        PandaError::Config(ErrorDetails::at(
+            format!("Invalid config: {}", err),
+            SourceLocation::from_json(&err, Some(config_path.to_string_lossy().to_string())),
+        ))
+    })
+}
+
+/// Adds variables to `panda.config.json`, to an environment when `environment` is set.
+///
+/// Values already in the config are kept, the names of the variables that were not
+/// added are returned. Other settings are left as they are.
+pub fn add_variables(
+    project_root: &Path,
+    environment: Option<&str>,
+    variables: &BTreeMap<String, String>,
+) -> Result<Vec<String>, PandaError> {
+    let config_path = project_root.join(PANDA_CONFIG);
+
+    // Validates the config before editing it as plain JSON.
+    read_config(&project_root.to_string_lossy())?;
+
+    let content = fs::read_to_string(&config_path).map_err(|err| {
+        PandaError::Config(format!("Failed to read config {:?}: {}", config_path, err).into())
+    })?;
+
+    let mut config: Value = serde_json::from_str(&content)
+        .map_err(|err| PandaError::Config(format!("Invalid config: {}", err).into()))?;
+
+    let mut target = &mut config;
+
+    if let Some(environment) = environment {
+        target = target
+            .as_object_mut()
+            .and_then(|c| {
+                Some(
+                    c.entry("environments")
+                        .or_insert_with(|| json!({}))
+                        .as_object_mut()?
+                        .entry(environment)
+                        .or_insert_with(|| json!({})),
+                )
+            })
+            .ok_or_else(|| PandaError::Config("Invalid environments in config.".into()))?;
+    }
+
+    let existing = target
+        .as_object_mut()
+        .and_then(|t| {
+            t.entry("variables")
+                .or_insert_with(|| json!({}))
+                .as_object_mut()
+        })
+        .ok_or_else(|| PandaError::Config("Invalid variables in config.".into()))?;
+
+    let mut kept = vec![];
+
+    for (name, value) in variables {
+        match existing.get(name) {
+            Some(current) if current.as_str() != Some(value) => kept.push(name.clone()),
+            Some(_) => {}
+            None => {
+                existing.insert(name.clone(), Value::String(value.clone()));
+            }
+        }
+    }
+
+    let content = serde_json::to_string_pretty(&config)
+        .map_err(|err| PandaError::Config(format!("Failed to serialize config: {}", err).into()))?;
+
+    fs::write(&config_path, content).map_err(|err| {
+        PandaError::Io(format!("Failed to write {:?}: {}", config_path, err).into())
+    })?;
+
+    Ok(kept)
+}
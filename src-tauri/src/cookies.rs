@@ -0,0 +1,193 @@
+use crate::utils::get_panda_dir;
+use cookie_store::{Cookie, CookieDomain, CookieExpiration, CookieStore};
+use reqwest::header::HeaderValue;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const COOKIE_JAR: &str = "cookies.json";
+
+/// Environment used when none is active.
+pub const DEFAULT_ENVIRONMENT: &str = "default";
+
+/// Cookies of every environment keyed by environment name, as stored in `.panda/cookies.json`.
+type CookieJarFile = HashMap<String, Vec<Cookie<'static>>>;
+
+/// Cookie jar for one project environment, persisted under `.panda/cookies.json`.
+#[derive(Debug)]
+pub struct PandaCookieJar {
+    path: PathBuf,
+    environment: String,
+    store: RwLock<CookieStore>,
+}
+
+/// A cookie as exposed to the UI and the CLI.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CookieEntry {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    pub path: String,
+    /// When `false` the cookie is also sent to subdomains of `domain`.
+    pub host_only: bool,
+    /// Unix timestamp in seconds, `None` for session cookies.
+    pub expires: Option<i64>,
+    pub secure: bool,
+    pub http_only: bool,
+}
+
+impl PandaCookieJar {
+    /// Loads the jar of `environment`, a missing file yields an empty jar.
+    pub fn load(project_root: &Path, environment: Option<&str>) -> Result<Self, String> {
+        let path = get_panda_dir(project_root)?.join(COOKIE_JAR);
+        let environment = environment.unwrap_or(DEFAULT_ENVIRONMENT).to_string();
+
+        let cookies = read_jar_file(&path)?
+            .remove(&environment)
+            .unwrap_or_default();
+
+        let store = CookieStore::from_cookies(cookies.into_iter().map(Ok::<_, String>), false)?;
+
+        Ok(PandaCookieJar {
+            path,
+            environment,
+            store: RwLock::new(store),
+        })
+    }
+
+    /// Writes the unexpired cookies back, leaving other environments untouched.
+    pub fn save(&self) -> Result<(), String> {
+        let mut jar = read_jar_file(&self.path)?;
+
+        let cookies = self
+            .store
+            .read()
+            .unwrap()
+            .iter_unexpired()
+            .cloned()
+            .collect();
+
+        jar.insert(self.environment.clone(), cookies);
+
+        let content = serde_json::to_string_pretty(&jar).map_err(|err| err.to_string())?;
+
+        fs::write(&self.path, content)
+            .map_err(|err| format!("Failed to write cookies {:?}: {}", self.path, err))
+    }
+
+    pub fn list(&self) -> Vec<CookieEntry> {
+        self.store
+            .read()
+            .unwrap()
+            .iter_unexpired()
+            .map(|cookie| CookieEntry {
+                name: cookie.name().to_string(),
+                value: cookie.value().to_string(),
+                domain: String::from(&cookie.domain),
+                path: String::from(&cookie.path),
+                host_only: matches!(cookie.domain, CookieDomain::HostOnly(_)),
+                expires: match cookie.expires {
+                    CookieExpiration::AtUtc(at) => Some(at.unix_timestamp()),
+                    CookieExpiration::SessionEnd => None,
+                },
+                secure: cookie.secure().unwrap_or(false),
+                http_only: cookie.http_only().unwrap_or(false),
+            })
+            .collect()
+    }
+
+    /// Inserts or replaces a cookie.
+    pub fn set(&self, entry: &CookieEntry) -> Result<(), String> {
+        let domain = entry.domain.trim_start_matches('.');
+        let url = Url::parse(&format!("https://{}{}", domain, entry.path))
+            .map_err(|err| format!("Invalid cookie domain {:?}: {}", entry.domain, err))?;
+
+        let mut set_cookie = format!("{}={}; Path={}", entry.name, entry.value, entry.path);
+
+        if !entry.host_only {
+            set_cookie.push_str(&format!("; Domain={}", domain));
+        }
+
+        if let Some(expires) = entry.expires {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64;
+
+            set_cookie.push_str(&format!("; Max-Age={}", expires - now));
+        }
+
+        if entry.secure {
+            set_cookie.push_str("; Secure");
+        }
+
+        if entry.http_only {
+            set_cookie.push_str("; HttpOnly");
+        }
+
+        self.store
+            .write()
+            .unwrap()
+            .parse(&set_cookie, &url)
+            .map(|_| ())
+            .map_err(|err| format!("Invalid cookie {:?}: {}", entry.name, err))
+    }
+
+    pub fn remove(&self, domain: &str, path: &str, name: &str) -> bool {
+        self.store
+            .write()
+            .unwrap()
+            .remove(domain.trim_start_matches('.'), path, name)
+            .is_some()
+    }
+
+    pub fn clear(&self) {
+        self.store.write().unwrap().clear();
+    }
+}
+
+impl reqwest::cookie::CookieStore for PandaCookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let cookies = cookie_headers.filter_map(|header| {
+            let value = header.to_str().ok()?;
+            cookie_store::RawCookie::parse(value.to_string()).ok()
+        });
+
+        self.store
+            .write()
+            .unwrap()
+            .store_response_cookies(cookies, url);
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let header = self
+            .store
+            .read()
+            .unwrap()
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        if header.is_empty() {
+            return None;
+        }
+
+        HeaderValue::from_str(&header).ok()
+    }
+}
+
+fn read_jar_file(path: &Path) -> Result<CookieJarFile, String> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read cookies {:?}: {}", path, err))?;
+
+    serde_json::from_str(&content).map_err(|err| format!("Invalid cookies {:?}: {}", path, err))
+}
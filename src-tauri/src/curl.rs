@@ -0,0 +1,887 @@
+use crate::error::PandaError;
+use crate::headers::Header;
+use crate::import::{
+    basic_auth, form_fields, method_table, request_name, FormValue, ImportedBody, ImportedRequest,
+    Templates,
+};
+use crate::utils::encode_component;
+
+/// Short flags followed by a value, eg. `-XPOST` or `-X POST`.
+const SHORT_WITH_VALUE: &str = "XHdFubAeoDwxmETrKCyYzcU";
+
+/// Long flags followed by a value that are not imported, their value must not be taken
+/// for the url.
+const LONG_WITH_VALUE: [&str; 44] = [
+    "output",
+    "write-out",
+    "dump-header",
+    "stderr",
+    "trace",
+    "trace-ascii",
+    "proxy",
+    "proxy-user",
+    "proxy-header",
+    "noproxy",
+    "socks5",
+    "socks5-hostname",
+    "max-time",
+    "connect-timeout",
+    "cert",
+    "key",
+    "cacert",
+    "capath",
+    "pass",
+    "ciphers",
+    "upload-file",
+    "resolve",
+    "connect-to",
+    "retry",
+    "retry-delay",
+    "retry-max-time",
+    "limit-rate",
+    "max-redirs",
+    "max-filesize",
+    "cookie-jar",
+    "range",
+    "time-cond",
+    "continue-at",
+    "config",
+    "speed-time",
+    "speed-limit",
+    "interface",
+    "local-port",
+    "dns-servers",
+    "unix-socket",
+    "request-target",
+    "aws-sigv4",
+    "netrc-file",
+    "variable",
+];
+
+/// Flags that only change what curl prints or how it connects, ignored without a warning.
+const IGNORED: [&str; 22] = [
+    "silent",
+    "show-error",
+    "verbose",
+    "include",
+    "output",
+    "write-out",
+    "dump-header",
+    "stderr",
+    "trace",
+    "trace-ascii",
+    "progress-bar",
+    "no-progress-meter",
+    "fail",
+    "fail-with-body",
+    "no-buffer",
+    "remote-name",
+    "remote-header-name",
+    "globoff",
+    "create-dirs",
+    "location",
+    "compressed",
+    "basic",
+];
+
+/// A request converted from a curl command line and what could not be converted.
+#[derive(Clone, Debug)]
+pub struct CurlRequest {
+    pub request: ImportedRequest,
+    pub warnings: Vec<String>,
+}
+
+/// Converts a curl command, as copied from a terminal or browser devtools.
+///
+/// `-H`, `-d` and its variants, `--json`, `-F`, `-u`, `-b`, `-A`, `-e`, `-G` and `-I` are
+/// converted, shell variables eg. `$TOKEN` become Panda variables.
+pub fn parse(command: &str) -> Result<CurlRequest, PandaError> {
+    let (words, shell_variables) = split_words(command)?;
+    let mut words = words.into_iter().peekable();
+
+    if words
+        .peek()
+        .is_some_and(|w| w == "curl" || w.ends_with("/curl") || w.ends_with("curl.exe"))
+    {
+        words.next();
+    }
+
+    let mut curl = Curl::default();
+    let mut flags = vec![];
+
+    // Short flags can be grouped eg. `-sSL` and their value attached eg. `-XPOST`.
+    while let Some(word) = words.next() {
+        if word == "--" {
+            curl.urls.extend(words.by_ref());
+            break;
+        }
+
+        if let Some(long) = word.strip_prefix("--") {
+            let value = match takes_value(long) {
+                true => words.next(),
+                false => None,
+            };
+
+            flags.push((long.to_string(), value));
+            continue;
+        }
+
+        match word.strip_prefix('-').filter(|w| !w.is_empty()) {
+            Some(short) => {
+                for (i, c) in short.char_indices() {
+                    let long = long_name(c);
+
+                    if SHORT_WITH_VALUE.contains(c) {
+                        let value = match &short[i + c.len_utf8()..] {
+                            "" => words.next(),
+                            rest => Some(rest.to_string()),
+                        };
+
+                        flags.push((long, value));
+                        break;
+                    }
+
+                    flags.push((long, None));
+                }
+            }
+            None => curl.urls.push(word),
+        }
+    }
+
+    for (flag, value) in flags {
+        curl.flag(&flag, value);
+    }
+
+    let mut request = curl.request()?;
+    let mut templates = Templates::default();
+
+    request.url = templates.convert(&request.url);
+    request.name = request_name(&request.method, &request.url);
+
+    for header in request.headers.iter_mut() {
+        header.value = templates.convert(&header.value);
+    }
+
+    request.body = request.body.map(|body| match body {
+        ImportedBody::Json(text) => ImportedBody::Json(templates.convert(&text)),
+        ImportedBody::Text(text) => ImportedBody::Text(templates.convert(&text)),
+        ImportedBody::Xml(text) => ImportedBody::Xml(templates.convert(&text)),
+        ImportedBody::FormUrlEncoded(fields) => {
+            ImportedBody::FormUrlEncoded(convert_fields(&mut templates, fields))
+        }
+        ImportedBody::FormMultipart(fields) => ImportedBody::FormMultipart(
+            fields
+                .into_iter()
+                .map(|(name, value)| match value {
+                    FormValue::Text(text) => (name, FormValue::Text(templates.convert(&text))),
+                    file => (name, file),
+                })
+                .collect(),
+        ),
+    });
+
+    let mut warnings = curl.warnings;
+
+    for variable in shell_variables {
+        warnings.push(format!(
+            "Shell variable ${} became _.{}, set it in the project or an environment.",
+            variable,
+            templates.name(&variable)
+        ));
+    }
+
+    warnings.extend(templates.warnings);
+
+    let mut seen = std::collections::HashSet::new();
+    warnings.retain(|warning| seen.insert(warning.clone()));
+
+    Ok(CurlRequest { request, warnings })
+}
+
+fn convert_fields(
+    templates: &mut Templates,
+    fields: Vec<(String, String)>,
+) -> Vec<(String, String)> {
+    fields
+        .into_iter()
+        .map(|(name, value)| (name, templates.convert(&value)))
+        .collect()
+}
+
+fn takes_value(long: &str) -> bool {
+    matches!(
+        long,
+        "request"
+            | "header"
+            | "data"
+            | "data-ascii"
+            | "data-binary"
+            | "data-raw"
+            | "data-urlencode"
+            | "json"
+            | "form"
+            | "form-string"
+            | "user"
+            | "cookie"
+            | "user-agent"
+            | "referer"
+            | "url"
+            | "oauth2-bearer"
+    ) || LONG_WITH_VALUE.contains(&long)
+}
+
+fn long_name(short: char) -> String {
+    let long = match short {
+        'X' => "request",
+        'H' => "header",
+        'd' => "data",
+        'F' => "form",
+        'u' => "user",
+        'b' => "cookie",
+        'A' => "user-agent",
+        'e' => "referer",
+        'k' => "insecure",
+        'L' => "location",
+        'I' => "head",
+        'G' => "get",
+        's' => "silent",
+        'S' => "show-error",
+        'v' => "verbose",
+        'i' => "include",
+        'o' => "output",
+        'O' => "remote-name",
+        'J' => "remote-header-name",
+        'D' => "dump-header",
+        'w' => "write-out",
+        'x' => "proxy",
+        'U' => "proxy-user",
+        'm' => "max-time",
+        'E' => "cert",
+        'T' => "upload-file",
+        'r' => "range",
+        'K' => "config",
+        'C' => "continue-at",
+        'y' => "speed-time",
+        'Y' => "speed-limit",
+        'z' => "time-cond",
+        'c' => "cookie-jar",
+        'f' => "fail",
+        'g' => "globoff",
+        'N' => "no-buffer",
+        '#' => "progress-bar",
+        short => return short.to_string(),
+    };
+
+    long.to_string()
+}
+
+/// What the flags of the command set.
+#[derive(Default)]
+struct Curl {
+    urls: Vec<String>,
+    method: Option<String>,
+    head: bool,
+    get: bool,
+    headers: Vec<Header>,
+    data: Vec<String>,
+    json: bool,
+    form: Vec<(String, FormValue)>,
+    cookies: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl Curl {
+    fn flag(&mut self, flag: &str, value: Option<String>) {
+        let value = match (value, takes_value(flag)) {
+            (Some(value), _) => value,
+            (None, true) => {
+                self.warnings
+                    .push(format!("Flag --{} is missing its value.", flag));
+                return;
+            }
+            (None, false) => String::new(),
+        };
+
+        match flag {
+            "request" => self.method = Some(value),
+            "head" => self.head = true,
+            "get" => self.get = true,
+            "url" => self.urls.push(value),
+            "header" => self.header(&value),
+            "user-agent" => self.set_header("User-Agent", value),
+            "referer" => self.set_header("Referer", value),
+            "oauth2-bearer" => self.set_header("Authorization", format!("Bearer {}", value)),
+            "user" => self.user(&value),
+            "cookie" if value.contains('=') => self.cookies.push(value),
+            "cookie" => self.warnings.push(format!(
+                "Cookies are read from file {:?}, add them as a Cookie header.",
+                value
+            )),
+            "data" | "data-ascii" | "data-binary" => self.data(value, flag),
+            "data-raw" => self.data.push(value),
+            "data-urlencode" => self.data_urlencode(&value),
+            "json" => {
+                self.json = true;
+                self.data(value, flag);
+            }
+            "form" => self.form(&value),
+            "form-string" => match value.split_once('=') {
+                Some((name, value)) => self
+                    .form
+                    .push((name.to_string(), FormValue::Text(value.to_string()))),
+                None => self
+                    .warnings
+                    .push(format!("Invalid form field {:?}.", value)),
+            },
+            "insecure" => self.warnings.push(
+                "TLS certificate verification can't be disabled, trust the server with `ca` \
+                 in `client_certificates` of panda.config.json."
+                    .to_string(),
+            ),
+            flag if IGNORED.contains(&flag) => {}
+            flag => {
+                let flag = match flag.chars().count() {
+                    1 => format!("-{}", flag),
+                    _ => format!("--{}", flag),
+                };
+
+                self.warnings
+                    .push(format!("Flag {} is not supported and was ignored.", flag));
+            }
+        }
+    }
+
+    /// `Name: value`, `Name;` for an empty value. `Name:` removes a header in curl.
+    fn header(&mut self, value: &str) {
+        if let Some(file) = value.strip_prefix('@') {
+            self.warnings.push(format!(
+                "Headers are read from file {:?}, add them to the request.",
+                file
+            ));
+            return;
+        }
+
+        match value.split_once(':') {
+            Some((name, value)) if !value.trim().is_empty() => self.headers.push(Header {
+                name: name.trim().to_string(),
+                value: value.trim().to_string(),
+            }),
+            Some(_) => {}
+            None => match value.strip_suffix(';') {
+                Some(name) => self.headers.push(Header {
+                    name: name.trim().to_string(),
+                    value: String::new(),
+                }),
+                None => self
+                    .warnings
+                    .push(format!("Invalid header {:?}, expected Name: value.", value)),
+            },
+        }
+    }
+
+    fn set_header(&mut self, name: &str, value: String) {
+        self.headers.retain(|h| !h.name.eq_ignore_ascii_case(name));
+        self.headers.push(Header {
+            name: name.to_string(),
+            value,
+        });
+    }
+
+    fn user(&mut self, value: &str) {
+        let (username, password) = value.split_once(':').unwrap_or_else(|| {
+            self.warnings.push(format!(
+                "User {} has no password, curl would prompt for it.",
+                value
+            ));
+            (value, "")
+        });
+
+        match basic_auth(username, password) {
+            Some(authorization) => self.set_header("Authorization", authorization),
+            None => self.warnings.push(
+                "Credentials with variables can't be encoded, set the Authorization header."
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// `@file` reads the data from a file, `@-` from the standard input.
+    fn data(&mut self, value: String, flag: &str) {
+        match value.strip_prefix('@') {
+            Some(file) => self.warnings.push(format!(
+                "--{} reads the body from {:?}, paste its content in the request.",
+                flag, file
+            )),
+            None => self.data.push(value),
+        }
+    }
+
+    /// `content`, `=content`, `name=content`, `@file` or `name@file`, the content is
+    /// percent encoded.
+    fn data_urlencode(&mut self, value: &str) {
+        let at = value.find('@');
+        let equals = value.find('=');
+
+        if at.is_some_and(|at| equals.is_none_or(|equals| at < equals)) {
+            self.warnings.push(format!(
+                "--data-urlencode reads {:?} from a file, paste its content in the request.",
+                value
+            ));
+            return;
+        }
+
+        self.data.push(match value.split_once('=') {
+            Some(("", content)) => encode_component(content),
+            Some((name, content)) => format!("{}={}", name, encode_component(content)),
+            None => encode_component(value),
+        });
+    }
+
+    /// `name=value`, `name=@file` uploads a file and `name=<file` sends its content.
+    fn form(&mut self, value: &str) {
+        let (name, value) = match value.split_once('=') {
+            Some(field) => field,
+            None => {
+                self.warnings.push(format!(
+                    "Invalid form field {:?}, expected name=value.",
+                    value
+                ));
+                return;
+            }
+        };
+
+        if let Some(file) = value.strip_prefix('@') {
+            let file = file.split(';').next().unwrap_or(file);
+
+            self.warnings.push(format!(
+                "Form field {} sends file {:?}, copy it to the project if it is elsewhere.",
+                name, file
+            ));
+            self.form
+                .push((name.to_string(), FormValue::File(file.to_string())));
+            return;
+        }
+
+        if let Some(file) = value.strip_prefix('<') {
+            let file = file.split(';').next().unwrap_or(file);
+
+            self.warnings.push(format!(
+                "Form field {} reads its value from file {:?}, paste its content in the request.",
+                name, file
+            ));
+            self.form
+                .push((name.to_string(), FormValue::Text(String::new())));
+            return;
+        }
+
+        // Strips `;type=text/plain` and the other options of the field.
+        let value = match value.starts_with('"') {
+            true => value.trim_matches('"'),
+            false => value.split(";type=").next().unwrap_or(value),
+        };
+
+        self.form
+            .push((name.to_string(), FormValue::Text(value.to_string())));
+    }
+
+    fn request(&mut self) -> Result<ImportedRequest, PandaError> {
+        let mut urls = std::mem::take(&mut self.urls).into_iter();
+
+        let mut url = match urls.next() {
+            Some(url) => url,
+            None => return Err(PandaError::Parse("The curl command has no url.".into())),
+        };
+
+        if urls.next().is_some() {
+            self.warnings
+                .push("The command has several urls, only the first one was imported.".into());
+        }
+
+        // curl defaults to http when the scheme is left out.
+        if !url.contains("://") && !url.starts_with("{{") {
+            url = format!("http://{}", url);
+        }
+
+        let has_body = !self.data.is_empty() || !self.form.is_empty();
+
+        let method = match (&self.method, self.head, self.get, has_body) {
+            (Some(method), ..) => method.to_lowercase(),
+            (None, true, ..) => "head".to_string(),
+            (None, false, false, true) => "post".to_string(),
+            _ => "get".to_string(),
+        };
+
+        if method_table(&method).is_none() {
+            return Err(PandaError::Parse(
+                format!(
+                    "Method {} can't be used in request files.",
+                    method.to_uppercase()
+                )
+                .into(),
+            ));
+        }
+
+        if !self.cookies.is_empty() {
+            let cookie = self.cookies.join("; ");
+            self.set_header("Cookie", cookie);
+        }
+
+        if self.json {
+            for (name, value) in [
+                ("Content-Type", "application/json"),
+                ("Accept", "application/json"),
+            ] {
+                if !self
+                    .headers
+                    .iter()
+                    .any(|h| h.name.eq_ignore_ascii_case(name))
+                {
+                    self.headers.push(Header {
+                        name: name.to_string(),
+                        value: value.to_string(),
+                    });
+                }
+            }
+        }
+
+        // `-G` sends the data in the query.
+        if self.get && !self.data.is_empty() {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            url = format!("{}{}{}", url, separator, self.data.join("&"));
+            self.data.clear();
+        }
+
+        let body = self.body();
+
+        Ok(ImportedRequest {
+            name: String::new(),
+            method,
+            url,
+            params: vec![],
+            headers: std::mem::take(&mut self.headers),
+            body,
+        })
+    }
+
+    /// Typed from the Content-Type, curl sends `-d` as `application/x-www-form-urlencoded`.
+    fn body(&mut self) -> Option<ImportedBody> {
+        let content_type = self
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case("content-type"))
+            .map(|h| h.value.to_lowercase());
+
+        if !self.form.is_empty() {
+            if !self.data.is_empty() {
+                self.warnings
+                    .push("-d and -F can't be combined, only the form was imported.".into());
+            }
+
+            // The runner sets the content type with the multipart boundary.
+            self.remove_content_type("multipart/form-data");
+
+            return Some(ImportedBody::FormMultipart(std::mem::take(&mut self.form)));
+        }
+
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let data = match self.json {
+            true => self.data.concat(),
+            false => self.data.join("&"),
+        };
+
+        match content_type.as_deref() {
+            Some(c) if c.contains("json") => Some(ImportedBody::Json(data)),
+            Some(c) if c.contains("xml") => Some(ImportedBody::Xml(data)),
+            None | Some("application/x-www-form-urlencoded") if is_form(&data) => {
+                self.remove_content_type("application/x-www-form-urlencoded");
+                Some(ImportedBody::FormUrlEncoded(form_fields(&data)))
+            }
+            None => {
+                if serde_json::from_str::<serde_json::Value>(&data).is_ok_and(|v| !v.is_string()) {
+                    self.warnings.push(
+                        "The body looks like JSON but curl sends it as form data, add a \
+                         Content-Type: application/json header if the API expects JSON."
+                            .into(),
+                    );
+                }
+
+                self.headers.push(Header {
+                    name: "Content-Type".to_string(),
+                    value: "application/x-www-form-urlencoded".to_string(),
+                });
+
+                Some(ImportedBody::Text(data))
+            }
+            Some(_) => Some(ImportedBody::Text(data)),
+        }
+    }
+
+    fn remove_content_type(&mut self, prefix: &str) {
+        self.headers.retain(|h| {
+            !(h.name.eq_ignore_ascii_case("content-type")
+                && h.value.to_lowercase().starts_with(prefix))
+        });
+    }
+}
+
+/// `a=1&b=2`, every pair has a name.
+fn is_form(data: &str) -> bool {
+    data.split('&').all(|pair| {
+        pair.split_once('=')
+            .is_some_and(|(name, _)| !name.is_empty())
+    })
+}
+
+/// Splits a command line like a POSIX shell, with the line continuations of bash, cmd
+/// and PowerShell. Variables outside of single quotes become `{{NAME}}` templates, their
+/// names are returned too.
+fn split_words(command: &str) -> Result<(Vec<String>, Vec<String>), PandaError> {
+    let mut words = vec![];
+    let mut variables: Vec<String> = vec![];
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars().peekable();
+
+    let mut variable = |chars: &mut std::iter::Peekable<std::str::Chars>, word: &mut String| {
+        let braced = chars.next_if_eq(&'{').is_some();
+        let mut name = String::new();
+
+        while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+            name.push(c);
+        }
+
+        if braced {
+            chars.next_if_eq(&'}');
+        }
+
+        match name.is_empty() {
+            true => word.push('$'),
+            false => {
+                word.push_str(&format!("{{{{{}}}}}", name));
+
+                if !variables.contains(&name) {
+                    variables.push(name);
+                }
+            }
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' | '^' | '`' if matches!(chars.peek(), Some('\n') | Some('\r')) => {
+                chars.next_if_eq(&'\r');
+                chars.next_if_eq(&'\n');
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            '\\' => {
+                in_word = true;
+
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                }
+            }
+            '\'' => {
+                in_word = true;
+
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(unterminated("'")),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('$' | '`' | '"' | '\\')) => word.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(unterminated("\"")),
+                        },
+                        Some('$') => variable(&mut chars, &mut word),
+                        Some(c) => word.push(c),
+                        None => return Err(unterminated("\"")),
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                in_word = true;
+                chars.next();
+
+                // ANSI-C quoting eg. $'{"a":\n1}' used by browsers for bodies.
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => word.push('\n'),
+                            Some('t') => word.push('\t'),
+                            Some('r') => word.push('\r'),
+                            Some(c @ ('\\' | '\'' | '"' | '?')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(unterminated("$'")),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(unterminated("$'")),
+                    }
+                }
+            }
+            '$' => {
+                in_word = true;
+                variable(&mut chars, &mut word);
+            }
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+
+    if in_word {
+        words.push(word);
+    }
+
+    Ok((words, variables))
+}
+
+fn unterminated(quote: &str) -> PandaError {
+    PandaError::Parse(format!("The curl command has an unterminated {} quote.", quote).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(command: &str) -> Vec<String> {
+        split_words(command).unwrap().0
+    }
+
+    fn header<'a>(request: &'a ImportedRequest, name: &str) -> Option<&'a str> {
+        request
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+            .map(|h| h.value.as_str())
+    }
+
+    #[test]
+    fn splits_quoted_words() {
+        assert_eq!(
+            words(r#"curl 'a b' "c \"d\" \$e" f\ g 'h'"i"j"#),
+            ["curl", "a b", "c \"d\" $e", "f g", "hij"]
+        );
+        assert_eq!(words("curl \\\n  -s \\\r\n  url"), ["curl", "-s", "url"]);
+        assert_eq!(words("curl ^\n -s `\n url"), ["curl", "-s", "url"]);
+        assert!(split_words("curl 'open").is_err());
+        assert!(split_words("curl \"open").is_err());
+    }
+
+    #[test]
+    fn splits_ansi_c_quotes() {
+        assert_eq!(
+            words(r#"curl $'{"a":\n\t"it\'s"}' $'\x'"#),
+            ["curl", "{\"a\":\n\t\"it's\"}", "\\x"]
+        );
+        assert!(split_words("curl $'open").is_err());
+    }
+
+    #[test]
+    fn converts_shell_variables() {
+        let (words, variables) = split_words(r#"curl "$HOST/a" ${TOKEN} '$RAW'"#).unwrap();
+
+        assert_eq!(words, ["curl", "{{HOST}}/a", "{{TOKEN}}", "$RAW"]);
+        assert_eq!(variables, ["HOST", "TOKEN"]);
+
+        let curl = parse(r#"curl -H "Authorization: Bearer $TOKEN" https://example.com"#).unwrap();
+
+        assert_eq!(
+            header(&curl.request, "authorization"),
+            Some("Bearer _.TOKEN")
+        );
+    }
+
+    #[test]
+    fn reads_grouped_short_flags() {
+        let curl = parse("curl -sSLXPUT -HAccept:text/plain -d x=1 https://example.com").unwrap();
+
+        assert_eq!(curl.request.method, "put");
+        assert_eq!(header(&curl.request, "accept"), Some("text/plain"));
+        assert!(curl.warnings.is_empty(), "{:?}", curl.warnings);
+
+        let curl = parse("curl -X POST -uuser:pass example.com").unwrap();
+
+        assert_eq!(curl.request.method, "post");
+        assert_eq!(curl.request.url, "http://example.com");
+        assert_eq!(
+            header(&curl.request, "authorization"),
+            Some("Basic dXNlcjpwYXNz")
+        );
+    }
+
+    #[test]
+    fn sends_data_in_the_query_with_get() {
+        let curl = parse("curl -G -d a=1 -d b=2 'https://example.com/s?q=x'").unwrap();
+
+        assert_eq!(curl.request.method, "get");
+        assert_eq!(curl.request.url, "https://example.com/s?q=x&a=1&b=2");
+        assert_eq!(curl.request.body, None);
+    }
+
+    #[test]
+    fn encodes_data_urlencode() {
+        let curl = parse(
+            "curl --data-urlencode 'q=a b&c' --data-urlencode '=x/y' --data-urlencode z example.com",
+        )
+        .unwrap();
+
+        assert_eq!(
+            curl.request.body,
+            Some(ImportedBody::Text("q=a%20b%26c&x%2Fy&z".into()))
+        );
+
+        let curl = parse("curl --data-urlencode name@file.txt example.com").unwrap();
+
+        assert_eq!(curl.request.body, None);
+        assert_eq!(curl.warnings.len(), 1);
+    }
+
+    #[test]
+    fn reads_form_fields() {
+        let curl = parse(
+            r#"curl -F name=Ann -F 'avatar=@files/a.png;type=image/png' -F 'note=<n.txt' -F 'q="a;b"' --form-string 'raw=@x' -H 'Content-Type: multipart/form-data' example.com"#,
+        )
+        .unwrap();
+
+        assert_eq!(curl.request.method, "post");
+        assert_eq!(header(&curl.request, "content-type"), None);
+        assert_eq!(
+            curl.request.body,
+            Some(ImportedBody::FormMultipart(vec![
+                ("name".into(), FormValue::Text("Ann".into())),
+                ("avatar".into(), FormValue::File("files/a.png".into())),
+                ("note".into(), FormValue::Text(String::new())),
+                ("q".into(), FormValue::Text("a;b".into())),
+                ("raw".into(), FormValue::Text("@x".into())),
+            ]))
+        );
+        assert_eq!(curl.warnings.len(), 2, "{:?}", curl.warnings);
+    }
+}
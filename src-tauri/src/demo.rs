@@ -1,32 +1,37 @@
+use crate::error::PandaError;
 use crate::utils::PandaCollection;
-use std::fs::{self, File};
-use std::io::Write;
+use std::fs;
 use std::path::Path;
 
-pub fn create_collection(collections: &Vec<PandaCollection>, parent_path: &Path) {
+pub fn create_collection(
+    collections: &Vec<PandaCollection>,
+    parent_path: &Path,
+) -> Result<(), PandaError> {
     for item in collections {
         let item_path = parent_path.join(&item.name); // Use `name` instead of `path`
 
+        let io_error =
+            |err: std::io::Error| PandaError::Io(format!("{:?}: {}", item_path, err).into());
+
         if item.item_type == "folder" {
             // Ensure the directory exists
-            fs::create_dir_all(&item_path).expect("Failed to create directory");
+            fs::create_dir_all(&item_path).map_err(io_error)?;
 
             if let Some(children) = &item.children {
-                create_collection(children, &item_path);
+                create_collection(children, &item_path)?;
             }
         } else {
             // Ensure the parent directory exists before creating the file
             if let Some(parent) = item_path.parent() {
-                fs::create_dir_all(parent).expect("Failed to create parent directory");
+                fs::create_dir_all(parent).map_err(io_error)?;
             }
 
-            let mut file = File::create(&item_path).expect("Failed to create file");
-            if let Some(content) = &item.content {
-                file.write_all(content.as_bytes())
-                    .expect("Failed to write file");
-            }
+            let content = item.content.as_deref().unwrap_or_default();
+            fs::write(&item_path, content).map_err(io_error)?;
         }
     }
+
+    Ok(())
 }
 
 // pub fn get_demo_collection() -> Vec<PandaCollection> {
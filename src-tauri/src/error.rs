@@ -0,0 +1,299 @@
+use crate::timings::DnsError;
+use serde::{Deserialize, Serialize};
+use std::error::Error as StdError;
+use std::fmt;
+use thiserror::Error;
+
+/// Error returned by the backend, serialized as `{ "kind": "timeout", "message": "...", "location": null }`
+/// so the UI can tell failures apart.
+#[derive(Deserialize, Clone, Debug, Error, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PandaError {
+    /// `panda.config.json` is missing or invalid.
+    #[error("{0}")]
+    Config(ErrorDetails),
+    /// A request file or a JSON body could not be parsed.
+    #[error("{0}")]
+    Parse(ErrorDetails),
+    /// Undefined or malformed variables.
+    #[error("{0}")]
+    Variable(ErrorDetails),
+    /// The request is well formed but can't be sent eg. an invalid url or header.
+    #[error("{0}")]
+    Request(ErrorDetails),
+    #[error("{0}")]
+    Connect(ErrorDetails),
+    #[error("{0}")]
+    Timeout(ErrorDetails),
+    #[error("{0}")]
+    Tls(ErrorDetails),
+    #[error("{0}")]
+    Dns(ErrorDetails),
+    /// Reading, decoding or storing a response body failed.
+    #[error("{0}")]
+    Body(ErrorDetails),
+    #[error("{0}")]
+    Script(ErrorDetails),
+    /// A test or a snapshot comparison did not pass.
+    #[error("{0}")]
+    Assertion(ErrorDetails),
+    /// Reading or writing project files.
+    #[error("{0}")]
+    Io(ErrorDetails),
+    /// Any other failure while exchanging the request.
+    #[error("{0}")]
+    Network(ErrorDetails),
+}
+
+#[derive(Deserialize, Clone, Debug, Default, Serialize)]
+pub struct ErrorDetails {
+    pub message: String,
+    pub location: Option<SourceLocation>,
+}
+
+/// Position in a file, lines and columns start at 1.
+#[derive(Deserialize, Clone, Debug, Default, Serialize)]
+pub struct SourceLocation {
+    /// `None` when the content did not come from a file eg. the editor.
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ErrorDetails {
+    pub fn at(message: impl Into<String>, location: SourceLocation) -> Self {
+        ErrorDetails {
+            message: message.into(),
+            location: Some(location),
+        }
+    }
+}
+
+impl From<String> for ErrorDetails {
+    fn from(message: String) -> Self {
+        ErrorDetails {
+            message,
+            location: None,
+        }
+    }
+}
+
+impl From<&str> for ErrorDetails {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+impl fmt::Display for ErrorDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}: {}", location, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl SourceLocation {
+    /// Converts a byte offset in `source` to a line and column.
+    pub fn from_offset(source: &str, offset: usize, file: Option<String>) -> Self {
+        let mut offset = offset.min(source.len());
+
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+        SourceLocation {
+            file,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    pub fn from_json(err: &serde_json::Error, file: Option<String>) -> Self {
+        SourceLocation {
+            file,
+            line: err.line(),
+            column: err.column(),
+        }
+    }
+
+    /// `None` for errors that are not about a position eg. a recursion limit.
+    pub fn from_yaml(err: &serde_yaml::Error, file: Option<String>) -> Option<Self> {
+        let location = err.location()?;
+
+        Some(SourceLocation {
+            file,
+            line: location.line(),
+            column: location.column(),
+        })
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}:{}", file, self.line, self.column),
+            None => write!(f, "line {}, column {}", self.line, self.column),
+        }
+    }
+}
+
+impl PandaError {
+    pub fn details(&self) -> &ErrorDetails {
+        match self {
+            PandaError::Config(d)
+            | PandaError::Parse(d)
+            | PandaError::Variable(d)
+            | PandaError::Request(d)
+            | PandaError::Connect(d)
+            | PandaError::Timeout(d)
+            | PandaError::Tls(d)
+            | PandaError::Dns(d)
+            | PandaError::Body(d)
+            | PandaError::Script(d)
+            | PandaError::Assertion(d)
+            | PandaError::Io(d)
+            | PandaError::Network(d) => d,
+        }
+    }
+
+    pub fn details_mut(&mut self) -> &mut ErrorDetails {
+        match self {
+            PandaError::Config(d)
+            | PandaError::Parse(d)
+            | PandaError::Variable(d)
+            | PandaError::Request(d)
+            | PandaError::Connect(d)
+            | PandaError::Timeout(d)
+            | PandaError::Tls(d)
+            | PandaError::Dns(d)
+            | PandaError::Body(d)
+            | PandaError::Script(d)
+            | PandaError::Assertion(d)
+            | PandaError::Io(d)
+            | PandaError::Network(d) => d,
+        }
+    }
+
+    /// The serialized `kind` eg. `timeout`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PandaError::Config(_) => "config",
+            PandaError::Parse(_) => "parse",
+            PandaError::Variable(_) => "variable",
+            PandaError::Request(_) => "request",
+            PandaError::Connect(_) => "connect",
+            PandaError::Timeout(_) => "timeout",
+            PandaError::Tls(_) => "tls",
+            PandaError::Dns(_) => "dns",
+            PandaError::Body(_) => "body",
+            PandaError::Script(_) => "script",
+            PandaError::Assertion(_) => "assertion",
+            PandaError::Io(_) => "io",
+            PandaError::Network(_) => "network",
+        }
+    }
+
+    /// Parse error of a TOML request, `file` is `None` for content coming from the editor.
+    pub fn from_toml(err: toml::de::Error, source: &str, file: Option<String>) -> Self {
+        let message = err.message().to_string();
+
+        match err.span() {
+            Some(span) => PandaError::Parse(ErrorDetails::at(
+                message,
+                SourceLocation::from_offset(source, span.start, file),
+            )),
+            None => PandaError::Parse(message.into()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for PandaError {
+    fn from(err: reqwest::Error) -> Self {
+        // reqwest only describes the outer error, the cause is further down the chain.
+        let mut message = err.to_string();
+        let mut source = err.source();
+        let mut is_tls = false;
+        let mut is_dns = false;
+
+        while let Some(cause) = source {
+            message = format!("{}: {}", message, cause);
+
+            is_tls |= is_caused_by::<rustls::Error>(cause);
+            is_dns |= is_caused_by::<DnsError>(cause);
+
+            source = cause.source();
+        }
+
+        let details = ErrorDetails::from(message);
+
+        if err.is_timeout() {
+            PandaError::Timeout(details)
+        } else if is_dns {
+            PandaError::Dns(details)
+        } else if is_tls {
+            PandaError::Tls(details)
+        } else if err.is_connect() {
+            PandaError::Connect(details)
+        } else if err.is_body() || err.is_decode() {
+            PandaError::Body(details)
+        } else if err.is_builder() {
+            PandaError::Request(details)
+        } else {
+            PandaError::Network(details)
+        }
+    }
+}
+
+/// `io::Error` hides the error it wraps from `source` and TLS failures are nested in two of them.
+fn is_caused_by<T: StdError + 'static>(err: &(dyn StdError + 'static)) -> bool {
+    if err.is::<T>() {
+        return true;
+    }
+
+    match err
+        .downcast_ref::<std::io::Error>()
+        .and_then(|io| io.get_ref())
+    {
+        Some(inner) => is_caused_by::<T>(inner),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timings::{ConnectionProbe, TimedResolver};
+    use std::sync::Arc;
+
+    async fn get(url: &str) -> PandaError {
+        let client = reqwest::Client::builder()
+            .dns_resolver(Arc::new(TimedResolver::new(Arc::new(
+                ConnectionProbe::default(),
+            ))))
+            .no_proxy()
+            .build()
+            .unwrap();
+
+        client.get(url).send().await.unwrap_err().into()
+    }
+
+    #[tokio::test]
+    async fn tells_dns_failures_from_connect_ones() {
+        let err = get("http://panda.invalid/").await;
+        assert!(matches!(err, PandaError::Dns(_)), "{:?}", err);
+        assert!(err.to_string().contains("failed to resolve panda.invalid"));
+
+        // Nothing listens on the port once the listener is dropped.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let err = get(&format!("http://127.0.0.1:{}/", port)).await;
+        assert!(matches!(err, PandaError::Connect(_)), "{:?}", err);
+    }
+}
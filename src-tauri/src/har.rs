@@ -0,0 +1,705 @@
+use crate::body::BodyKind;
+use crate::error::{ErrorDetails, PandaError, SourceLocation};
+use crate::headers::Header;
+use crate::history::{HistoryEntry, HistoryResponse};
+use crate::http_runner::ResolvedRequest;
+use crate::import::{
+    form_fields, method_table, request_name, FormValue, Imported, ImportedBody, ImportedItem,
+    ImportedRequest,
+};
+use crate::report::RunReport;
+use crate::timings::Timings;
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime};
+
+/// Headers the browser or the client sets itself, they are not imported.
+const SKIPPED_HEADERS: [&str; 6] = [
+    "host",
+    "content-length",
+    "connection",
+    "accept-encoding",
+    "keep-alive",
+    "upgrade-insecure-requests",
+];
+
+/// Credentials of the browser session, they are not imported so they don't end up in the
+/// project.
+const CREDENTIAL_HEADERS: [&str; 3] = ["authorization", "cookie", "proxy-authorization"];
+
+/// A HAR 1.2 file, only what is imported.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Har {
+    pub log: Log,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Log {
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Entry {
+    pub request: Request,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: Vec<NameValue>,
+    pub post_data: Option<PostData>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct NameValue {
+    pub name: String,
+    #[serde(default)]
+    pub value: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostData {
+    #[serde(default)]
+    pub mime_type: String,
+    pub text: Option<String>,
+    #[serde(default)]
+    pub params: Vec<PostParam>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PostParam {
+    pub name: String,
+    #[serde(default)]
+    pub value: String,
+    /// Set for uploaded files.
+    pub file_name: Option<String>,
+}
+
+/// Which entries of a HAR file are imported.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct HarOptions {
+    /// Hosts to keep, their subdomains included. Every host when empty.
+    pub domains: Vec<String>,
+    /// Keeps a single request per method, url and body.
+    pub dedupe: bool,
+}
+
+pub fn read_har(path: &Path) -> Result<Har, PandaError> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| PandaError::Io(format!("Failed to read {:?}: {}", path, err).into()))?;
+
+    // Browsers may write a byte order mark.
+    serde_json::from_str(content.trim_start_matches('\u{feff}')).map_err(|err| {
+        PandaError::Parse(ErrorDetails::at(
+            format!("Not a HAR file: {}", err),
+            SourceLocation::from_json(&err, Some(path.to_string_lossy().to_string())),
+        ))
+    })
+}
+
+/// Converts the entries of a HAR file in the order they were sent, in a folder per host
+/// when there are several.
+pub fn convert(har: Har, name: &str, options: &HarOptions) -> Imported {
+    let mut warnings = vec![];
+    let mut seen = HashSet::new();
+    let mut hosts: Vec<(String, Vec<ImportedItem>)> = vec![];
+    let mut skipped = 0;
+
+    for entry in har.log.entries {
+        let request = entry.request;
+
+        let url = match Url::parse(&request.url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => url,
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+
+        let host = url.host_str().unwrap_or_default().to_lowercase();
+
+        if !options.domains.is_empty() && !options.domains.iter().any(|d| in_domain(&host, d)) {
+            continue;
+        }
+
+        let method = request.method.to_lowercase();
+
+        if method_table(&method).is_none() {
+            warnings.push(format!(
+                "{} {} uses an unsupported method and was skipped.",
+                request.method, request.url
+            ));
+            continue;
+        }
+
+        let body_text = request.post_data.as_ref().and_then(|p| p.text.clone());
+
+        if options.dedupe && !seen.insert((method.clone(), request.url.clone(), body_text)) {
+            continue;
+        }
+
+        let imported = convert_request(&request, &method, &url, &mut warnings);
+
+        match hosts.iter_mut().find(|(h, _)| *h == host) {
+            Some((_, items)) => items.push(imported),
+            None => hosts.push((host, vec![imported])),
+        }
+    }
+
+    if skipped > 0 {
+        warnings.push(format!(
+            "{} entries that are not http or https requests were skipped.",
+            skipped
+        ));
+    }
+
+    let items = match hosts.len() {
+        1 => hosts.remove(0).1,
+        _ => hosts
+            .into_iter()
+            .map(|(name, items)| ImportedItem::Folder { name, items })
+            .collect(),
+    };
+
+    let mut seen = HashSet::new();
+    warnings.retain(|warning| seen.insert(warning.clone()));
+
+    Imported {
+        name: name.to_string(),
+        items,
+        variables: BTreeMap::new(),
+        environments: BTreeMap::new(),
+        warnings,
+    }
+}
+
+/// `api.example.com` is in `example.com`, `notexample.com` is not.
+fn in_domain(host: &str, domain: &str) -> bool {
+    let domain = domain.trim().trim_start_matches("*.").to_lowercase();
+
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// HTTP/2 pseudo headers, the ones set by the client, and the form content type whose
+/// multipart boundary would not match the one sent.
+fn is_skipped_header(name: &str, is_form: bool) -> bool {
+    let lower = name.to_lowercase();
+
+    lower.starts_with(':')
+        || lower.starts_with("sec-ch-")
+        || lower.starts_with("sec-fetch-")
+        || SKIPPED_HEADERS.contains(&lower.as_str())
+        || (is_form && lower == "content-type")
+}
+
+fn convert_request(
+    request: &Request,
+    method: &str,
+    url: &Url,
+    warnings: &mut Vec<String>,
+) -> ImportedItem {
+    let name = request_name(method, url.as_str());
+
+    let post_data = request.post_data.as_ref();
+    let mime_type = post_data
+        .map(|p| p.mime_type.to_lowercase())
+        .unwrap_or_default();
+    let is_form = mime_type.starts_with("application/x-www-form-urlencoded")
+        || mime_type.starts_with("multipart/form-data");
+
+    let headers = request
+        .headers
+        .iter()
+        .filter(|h| !is_skipped_header(&h.name, is_form))
+        .filter(|h| {
+            let lower = h.name.to_lowercase();
+
+            if !CREDENTIAL_HEADERS.contains(&lower.as_str()) {
+                return true;
+            }
+
+            warnings.push(format!(
+                "The {} header was not imported to keep credentials out of the project, set it with a secret variable eg. _.TOKEN.",
+                lower
+            ));
+            false
+        })
+        .map(|h| Header {
+            name: h.name.clone(),
+            value: h.value.clone(),
+        })
+        .collect();
+
+    let body = post_data.and_then(|post_data| {
+        let fields = || {
+            post_data
+                .params
+                .iter()
+                .map(|p| (p.name.clone(), p.value.clone()))
+                .collect::<Vec<_>>()
+        };
+        let text = post_data.text.clone().unwrap_or_default();
+
+        if mime_type.starts_with("multipart/form-data") {
+            let fields = post_data.params.iter().map(|p| match &p.file_name {
+                Some(file) => {
+                    warnings.push(format!(
+                        "{} uploads file {:?} as field {}, copy it to the project.",
+                        name, file, p.name
+                    ));
+
+                    (p.name.clone(), FormValue::File(file.clone()))
+                }
+                None => (p.name.clone(), FormValue::Text(p.value.clone())),
+            });
+
+            return Some(ImportedBody::FormMultipart(fields.collect()));
+        }
+
+        if mime_type.starts_with("application/x-www-form-urlencoded") {
+            return Some(match post_data.params.is_empty() {
+                true => ImportedBody::FormUrlEncoded(form_fields(&text)),
+                false => ImportedBody::FormUrlEncoded(fields()),
+            });
+        }
+
+        if text.is_empty() {
+            return None;
+        }
+
+        Some(match mime_type {
+            ref m if m.contains("json") => ImportedBody::Json(text),
+            ref m if m.contains("xml") => ImportedBody::Xml(text),
+            _ => ImportedBody::Text(text),
+        })
+    });
+
+    ImportedItem::Request(ImportedRequest {
+        name,
+        method: method.to_string(),
+        url: request.url.clone(),
+        params: vec![],
+        headers,
+        body,
+    })
+}
+
+/// History entries as a HAR log, entries without a request are left out.
+pub fn from_history(entries: &[HistoryEntry]) -> Value {
+    let entries = entries
+        .iter()
+        .filter_map(|entry| {
+            let started =
+                OffsetDateTime::from_unix_timestamp_nanos(entry.timestamp as i128 * 1_000_000)
+                    .unwrap_or(OffsetDateTime::UNIX_EPOCH);
+
+            Some(har_entry(
+                started,
+                entry.request.as_ref()?,
+                entry.response.as_ref(),
+                entry.error.as_ref().map(|err| err.to_string()),
+                entry.file.as_deref(),
+            ))
+        })
+        .collect();
+
+    log(entries)
+}
+
+/// The requests of a run as a HAR log, each one starting when the previous one ended.
+pub fn from_report(report: &RunReport) -> Value {
+    let mut started =
+        OffsetDateTime::parse(&report.started_at, &Rfc3339).unwrap_or(OffsetDateTime::UNIX_EPOCH);
+
+    let entries = report
+        .results
+        .iter()
+        .filter_map(|result| {
+            let entry = har_entry(
+                started,
+                result.request.as_ref()?,
+                result.response.as_ref(),
+                result.error.as_ref().map(|err| err.to_string()),
+                Some(&result.file),
+            );
+
+            started += Duration::seconds_f64(result.elapsed_time.unwrap_or_default() / 1000.0);
+
+            Some(entry)
+        })
+        .collect();
+
+    log(entries)
+}
+
+fn log(entries: Vec<Value>) -> Value {
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": {
+                "name": "Panda",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "pages": [],
+            "entries": entries,
+        }
+    })
+}
+
+/// A HAR entry, requests that failed have a status of 0 and an `_error`.
+fn har_entry(
+    started: OffsetDateTime,
+    request: &ResolvedRequest,
+    response: Option<&HistoryResponse>,
+    error: Option<String>,
+    file: Option<&str>,
+) -> Value {
+    let query_string: Vec<Value> = Url::parse(&request.url)
+        .map(|url| {
+            url.query_pairs()
+                .map(|(name, value)| json!({ "name": name, "value": value }))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // The request was sent with the version the response came with.
+    let http_version = response
+        .map(|r| r.http_version.as_str())
+        .unwrap_or_default();
+
+    let mut har_request = json!({
+        "method": request.method.to_uppercase(),
+        "url": request.url,
+        "httpVersion": http_version,
+        "cookies": [],
+        "headers": headers(&request.headers),
+        "queryString": query_string,
+        "headersSize": -1,
+        "bodySize": request.body.as_ref().map_or(0, |body| body.len()),
+    });
+
+    if let Some(body) = &request.body {
+        let mime_type = request
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case("content-type"))
+            .map(|h| h.value.clone())
+            .unwrap_or_default();
+
+        har_request["postData"] = json!({ "mimeType": mime_type, "text": body });
+    }
+
+    let timings = response.map(|r| r.timings.clone()).unwrap_or_default();
+
+    let mut entry = json!({
+        "startedDateTime": started.format(&Rfc3339).unwrap_or_default(),
+        "time": response.map_or(0.0, |r| r.elapsed_time),
+        "request": har_request,
+        "response": har_response(response),
+        "cache": {},
+        "timings": har_timings(&timings),
+    });
+
+    if let Some(error) = error {
+        entry["_error"] = json!(error);
+    }
+
+    if let Some(file) = file {
+        entry["_file"] = json!(file);
+    }
+
+    entry
+}
+
+fn har_response(response: Option<&HistoryResponse>) -> Value {
+    let response = match response {
+        Some(response) => response,
+        None => {
+            return json!({
+                "status": 0,
+                "statusText": "",
+                "httpVersion": "",
+                "cookies": [],
+                "headers": [],
+                "content": { "size": 0, "mimeType": "" },
+                "redirectURL": "",
+                "headersSize": -1,
+                "bodySize": -1,
+            })
+        }
+    };
+
+    let status_text = StatusCode::from_u16(response.status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or_default();
+
+    let mut content = json!({
+        "size": response.size,
+        "mimeType": response.mime_type,
+        "text": response.body,
+    });
+
+    if matches!(response.body_kind, BodyKind::Binary) {
+        content["encoding"] = json!("base64");
+    }
+
+    if response.body_truncated {
+        content["comment"] = json!("The body was cut when it was recorded.");
+    }
+
+    let redirect_url = response
+        .headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case("location"))
+        .map(|h| h.value.clone())
+        .unwrap_or_default();
+
+    json!({
+        "status": response.status,
+        "statusText": status_text,
+        "httpVersion": response.http_version,
+        "cookies": [],
+        "headers": headers(&response.headers),
+        "content": content,
+        "redirectURL": redirect_url,
+        "headersSize": -1,
+        "bodySize": response.size,
+    })
+}
+
+fn headers(headers: &[Header]) -> Vec<Value> {
+    headers
+        .iter()
+        .map(|h| json!({ "name": h.name, "value": h.value }))
+        .collect()
+}
+
+/// HAR timings, `connect` includes `ssl` and phases that did not happen are -1.
+fn har_timings(timings: &Timings) -> Value {
+    let connect = match (timings.tcp, timings.tls) {
+        (None, None) => -1.0,
+        (tcp, tls) => tcp.unwrap_or_default() + tls.unwrap_or_default(),
+    };
+
+    json!({
+        "blocked": -1,
+        "dns": timings.dns.unwrap_or(-1.0),
+        "connect": connect,
+        "ssl": timings.tls.unwrap_or(-1.0),
+        "send": 0,
+        "wait": timings.ttfb,
+        "receive": timings.download,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn har(entries: Value) -> Har {
+        let entries: Vec<Value> = entries
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|request| json!({ "request": request }))
+            .collect();
+
+        serde_json::from_value(json!({ "log": { "entries": entries } })).unwrap()
+    }
+
+    fn requests(items: &[ImportedItem]) -> Vec<&ImportedRequest> {
+        items
+            .iter()
+            .flat_map(|item| match item {
+                ImportedItem::Request(request) => vec![request],
+                ImportedItem::Folder { items, .. } => requests(items),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_domains_and_their_subdomains() {
+        assert!(in_domain("example.com", "example.com"));
+        assert!(in_domain("api.example.com", "example.com"));
+        assert!(in_domain("api.example.com", "*.Example.com"));
+        assert!(!in_domain("notexample.com", "example.com"));
+        assert!(!in_domain("example.com.evil.test", "example.com"));
+
+        let imported = convert(
+            har(json!([
+                { "method": "GET", "url": "https://api.example.com/users" },
+                { "method": "GET", "url": "https://cdn.tracker.test/pixel.gif" },
+                { "method": "GET", "url": "https://notexample.com/" },
+            ])),
+            "session",
+            &HarOptions {
+                domains: vec!["example.com".into()],
+                ..Default::default()
+            },
+        );
+
+        let urls: Vec<&str> = requests(&imported.items)
+            .iter()
+            .map(|r| r.url.as_str())
+            .collect();
+        assert_eq!(urls, ["https://api.example.com/users"]);
+    }
+
+    #[test]
+    fn dedupes_by_method_url_and_body() {
+        let entries = json!([
+            { "method": "GET", "url": "https://example.com/users" },
+            { "method": "GET", "url": "https://example.com/users" },
+            { "method": "POST", "url": "https://example.com/users",
+              "postData": { "mimeType": "application/json", "text": "{\"name\":\"a\"}" } },
+            { "method": "POST", "url": "https://example.com/users",
+              "postData": { "mimeType": "application/json", "text": "{\"name\":\"b\"}" } },
+            { "method": "POST", "url": "https://example.com/users",
+              "postData": { "mimeType": "application/json", "text": "{\"name\":\"a\"}" } },
+            { "method": "GET", "url": "https://other.test/users" },
+        ]);
+
+        let all = convert(har(entries.clone()), "session", &HarOptions::default());
+        assert_eq!(requests(&all.items).len(), 6);
+
+        let deduped = convert(
+            har(entries),
+            "session",
+            &HarOptions {
+                dedupe: true,
+                ..Default::default()
+            },
+        );
+
+        // A folder per host.
+        assert!(
+            matches!(&deduped.items[..], [ImportedItem::Folder { name, items }, ImportedItem::Folder { .. }] if name == "example.com" && items.len() == 3)
+        );
+        assert_eq!(requests(&deduped.items).len(), 4);
+    }
+
+    #[test]
+    fn leaves_out_credentials() {
+        let imported = convert(
+            har(json!([
+                { "method": "GET", "url": "https://example.com/me", "headers": [
+                    { "name": "Accept", "value": "application/json" },
+                    { "name": "Authorization", "value": "Bearer eyJhbGciOi" },
+                    { "name": "cookie", "value": "session=abc" },
+                    { "name": ":authority", "value": "example.com" },
+                ] },
+                { "method": "GET", "url": "https://example.com/orders", "headers": [
+                    { "name": "Cookie", "value": "session=abc" },
+                ] },
+            ])),
+            "session",
+            &HarOptions::default(),
+        );
+
+        let requests = requests(&imported.items);
+        assert_eq!(
+            requests[0].headers,
+            [Header {
+                name: "Accept".into(),
+                value: "application/json".into(),
+            }]
+        );
+        assert!(requests[1].headers.is_empty());
+        assert_eq!(
+            imported.warnings,
+            [
+                "The authorization header was not imported to keep credentials out of the project, set it with a secret variable eg. _.TOKEN.",
+                "The cookie header was not imported to keep credentials out of the project, set it with a secret variable eg. _.TOKEN.",
+            ]
+        );
+    }
+
+    #[test]
+    fn imports_exported_history() {
+        let response = HistoryResponse {
+            status: 201,
+            http_version: "HTTP/2.0".into(),
+            final_url: "https://example.com/users".into(),
+            headers: vec![Header {
+                name: "content-type".into(),
+                value: "application/json".into(),
+            }],
+            mime_type: "application/json".into(),
+            body_kind: BodyKind::Text,
+            size: 9,
+            body: "{\"id\":1}".into(),
+            body_truncated: false,
+            elapsed_time: 12.0,
+            timings: Timings::default(),
+        };
+
+        let entry =
+            |method: &str, url: &str, body: Option<&str>, response: Option<_>| HistoryEntry {
+                id: "1".into(),
+                timestamp: 1_714_557_600_000,
+                environment: None,
+                file: Some("collection/users.toml".into()),
+                source: String::new(),
+                request: Some(ResolvedRequest {
+                    method: method.into(),
+                    url: url.into(),
+                    headers: vec![Header {
+                        name: "Content-Type".into(),
+                        value: "application/json".into(),
+                    }],
+                    body: body.map(str::to_string),
+                }),
+                response,
+                error: None,
+            };
+
+        let exported = from_history(&[
+            entry(
+                "post",
+                "https://example.com/users?notify=true",
+                Some("{\"name\":\"Ann\"}"),
+                Some(response),
+            ),
+            entry("get", "https://example.com/users", None, None),
+        ]);
+
+        let entries = &exported["log"]["entries"];
+        assert_eq!(entries[0]["startedDateTime"], "2024-05-01T10:00:00Z");
+        assert_eq!(entries[0]["request"]["httpVersion"], "HTTP/2.0");
+        assert_eq!(entries[0]["response"]["httpVersion"], "HTTP/2.0");
+        assert_eq!(
+            entries[0]["request"]["queryString"],
+            json!([{ "name": "notify", "value": "true" }])
+        );
+        assert_eq!(entries[1]["response"]["status"], 0);
+
+        let har: Har = serde_json::from_value(exported).unwrap();
+        let imported = convert(har, "history", &HarOptions::default());
+        let requests = requests(&imported.items);
+
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].method, "post");
+        assert_eq!(requests[0].url, "https://example.com/users?notify=true");
+        assert_eq!(
+            requests[0].body,
+            Some(ImportedBody::Json("{\"name\":\"Ann\"}".into()))
+        );
+        assert_eq!(requests[0].headers[0].name, "Content-Type");
+        assert_eq!(requests[1].method, "get");
+        assert_eq!(requests[1].body, None);
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+    }
+}
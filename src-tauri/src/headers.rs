@@ -0,0 +1,253 @@
+use crate::body::decode_header_value;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::de::{self, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+
+/// A single header line, names repeat when a header is sent more than once.
+#[derive(Deserialize, Clone, Debug, PartialEq, Serialize)]
+pub struct Header {
+    pub name: String,
+    pub value: String,
+}
+
+/// Headers of a request in the order they were written.
+///
+/// Each name maps to a string or an array of strings for headers sent several times:
+///
+/// ```toml
+/// [get.headers]
+/// Accept = "application/json"
+/// X-Forwarded-For = ["10.0.0.1", "10.0.0.2"]
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RequestHeaders(pub Vec<Header>);
+
+impl RequestHeaders {
+    /// Names are lowercased and repeated headers grouped at their first position like
+    /// `read_headers` describes, hyper sends them in that order.
+    pub fn to_header_map(&self) -> Result<HeaderMap, String> {
+        let mut header_map = HeaderMap::new();
+
+        for header in &self.0 {
+            let name = HeaderName::from_bytes(header.name.as_bytes())
+                .map_err(|err| format!("Invalid request header {:?}: {}", header.name, err))?;
+
+            let value = HeaderValue::from_str(&header.value).map_err(|err| {
+                format!(
+                    "Invalid value for request header {:?}: {}",
+                    header.name, err
+                )
+            })?;
+
+            header_map.append(name, value);
+        }
+
+        Ok(header_map)
+    }
+
+    /// Name of the header as written in the request, `name` being the lowercase one of
+    /// the header map.
+    pub fn written_name(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case(name))
+            .map(|header| header.name.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for RequestHeaders {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(RequestHeadersVisitor)
+    }
+}
+
+struct RequestHeadersVisitor;
+
+impl<'de> Visitor<'de> for RequestHeadersVisitor {
+    type Value = RequestHeaders;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a table of header names to a string or an array of strings")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut headers = vec![];
+
+        while let Some((name, value)) = map.next_entry::<String, Value>()? {
+            if name.is_empty() {
+                return Err(de::Error::custom(
+                    "Invalid request header, the name is empty.",
+                ));
+            }
+
+            let values = match value {
+                Value::String(v) => vec![Value::String(v)],
+                Value::Array(values) => values,
+                _ => vec![value],
+            };
+
+            for value in values {
+                let value = match value {
+                    Value::String(v) => v,
+                    _ => {
+                        return Err(de::Error::custom(format!(
+                            "Header {:?} must be a string or an array of strings, you may have provided a number.",
+                            name
+                        )))
+                    }
+                };
+
+                headers.push(Header {
+                    name: name.clone(),
+                    value,
+                });
+            }
+        }
+
+        Ok(RequestHeaders(headers))
+    }
+}
+
+impl Serialize for RequestHeaders {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut names: Vec<&str> = vec![];
+
+        for header in &self.0 {
+            if !names.contains(&header.name.as_str()) {
+                names.push(&header.name);
+            }
+        }
+
+        let mut map = serializer.serialize_map(Some(names.len()))?;
+
+        for name in names {
+            let values: Vec<&str> = self
+                .0
+                .iter()
+                .filter(|h| h.name == name)
+                .map(|h| h.value.as_str())
+                .collect();
+
+            match values.as_slice() {
+                [value] => map.serialize_entry(name, value)?,
+                _ => map.serialize_entry(name, &values)?,
+            }
+        }
+
+        map.end()
+    }
+}
+
+/// Reads headers as hyper parsed them into a `HeaderMap`.
+///
+/// hyper lowercases names, the case they were sent with is lost, and the map only keeps
+/// names in the order they first arrived: values of a repeated header are listed together
+/// at its first position even when other headers were interleaved on the wire. reqwest
+/// also removes `Content-Encoding` and `Content-Length` of the responses it decompresses,
+/// the map then moves its last header into their place.
+pub fn read_headers(headers: &HeaderMap) -> Vec<Header> {
+    headers
+        .iter()
+        .map(|(name, value)| Header {
+            name: name.to_string(),
+            value: decode_header_value(value.as_bytes()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::SET_COOKIE;
+
+    #[derive(Deserialize, Serialize)]
+    struct Request {
+        headers: RequestHeaders,
+    }
+
+    #[test]
+    fn reads_string_and_array_values_in_order() {
+        let request: Request = toml::from_str(
+            r#"
+            [headers]
+            Accept = "application/json"
+            X-Forwarded-For = ["10.0.0.1", "10.0.0.2"]
+            x-trace = "1"
+            "#,
+        )
+        .unwrap();
+
+        let headers: Vec<(&str, &str)> = request
+            .headers
+            .0
+            .iter()
+            .map(|h| (h.name.as_str(), h.value.as_str()))
+            .collect();
+        assert_eq!(
+            headers,
+            [
+                ("Accept", "application/json"),
+                ("X-Forwarded-For", "10.0.0.1"),
+                ("X-Forwarded-For", "10.0.0.2"),
+                ("x-trace", "1"),
+            ]
+        );
+
+        let map = request.headers.to_header_map().unwrap();
+        assert_eq!(map.get_all("x-forwarded-for").iter().count(), 2);
+        assert_eq!(
+            request.headers.written_name("x-forwarded-for"),
+            Some("X-Forwarded-For")
+        );
+        assert_eq!(request.headers.written_name("user-agent"), None);
+
+        let written = toml::to_string(&request).unwrap();
+        assert_eq!(
+            written,
+            "[headers]\nAccept = \"application/json\"\nX-Forwarded-For = [\"10.0.0.1\", \"10.0.0.2\"]\nx-trace = \"1\"\n"
+        );
+    }
+
+    #[test]
+    fn rejects_values_that_are_not_strings() {
+        for headers in ["X-Count = 1", "X-Count = [\"1\", 2]", "\"\" = \"empty\""] {
+            let err = toml::from_str::<Request>(&format!("[headers]\n{}", headers))
+                .err()
+                .unwrap();
+            assert!(
+                err.message().contains("Header") || err.message().contains("header"),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_repeated_set_cookie_headers() {
+        let mut map = HeaderMap::new();
+        map.append(SET_COOKIE, HeaderValue::from_static("a=1; Path=/"));
+        map.append("x-id", HeaderValue::from_static("7"));
+        map.append(SET_COOKIE, HeaderValue::from_static("b=2; HttpOnly"));
+
+        assert_eq!(
+            read_headers(&map),
+            [
+                Header {
+                    name: "set-cookie".into(),
+                    value: "a=1; Path=/".into()
+                },
+                Header {
+                    name: "set-cookie".into(),
+                    value: "b=2; HttpOnly".into()
+                },
+                Header {
+                    name: "x-id".into(),
+                    value: "7".into()
+                },
+            ]
+        );
+    }
+}
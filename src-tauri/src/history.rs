@@ -0,0 +1,833 @@
+use crate::body::BodyKind;
+use crate::error::PandaError;
+use crate::headers::Header;
+use crate::http_runner::{PandaHttpResponse, PandaTomlRequest, ResolvedRequest, RunOptions};
+use crate::timings::Timings;
+use crate::utils::{get_panda_dir, get_variables, normalise_path, parse_toml};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap_derive::Args;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::{Date, Duration, OffsetDateTime};
+use uuid::Uuid;
+
+/// One JSON entry per line under `.panda/`.
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// Response bodies are cut after this many bytes.
+const HISTORY_BODY_LIMIT: usize = 64 * 1024;
+
+const REDACTED: &str = "[redacted]";
+
+/// Header, query parameter and variable names containing one of these hold secrets.
+const SECRET_NAMES: [&str; 11] = [
+    "authorization",
+    "cookie",
+    "token",
+    "secret",
+    "password",
+    "passphrase",
+    "api-key",
+    "api_key",
+    "apikey",
+    "session",
+    "signature",
+];
+
+/// An executed request, secrets are redacted before it is stored.
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct HistoryEntry {
+    pub id: String,
+    /// Unix timestamp in milliseconds.
+    pub timestamp: i64,
+    pub environment: Option<String>,
+    /// Request file relative to the project root, `None` for unsaved requests.
+    pub file: Option<String>,
+    /// Request as written with secrets redacted, see `replay_source` for what is sent
+    /// again.
+    pub source: String,
+    /// `None` when the request file could not be parsed.
+    pub request: Option<ResolvedRequest>,
+    pub response: Option<HistoryResponse>,
+    pub error: Option<PandaError>,
+}
+
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct HistoryResponse {
+    pub status: u16,
+    /// eg. `HTTP/1.1`, empty for entries recorded before it was kept.
+    #[serde(default)]
+    pub http_version: String,
+    pub final_url: String,
+    pub headers: Vec<Header>,
+    pub mime_type: String,
+    pub body_kind: BodyKind,
+    /// Size of the whole body in bytes.
+    pub size: usize,
+    /// Text body or base64 for binary ones, cut after `HISTORY_BODY_LIMIT` bytes.
+    pub body: String,
+    pub body_truncated: bool,
+    pub elapsed_time: f64,
+    pub timings: Timings,
+}
+
+/// What history listings show for each entry.
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct HistorySummary {
+    pub id: String,
+    pub timestamp: i64,
+    pub environment: Option<String>,
+    pub file: Option<String>,
+    pub method: Option<String>,
+    pub url: Option<String>,
+    pub status: Option<u16>,
+    pub elapsed_time: Option<f64>,
+    pub size: Option<usize>,
+    pub error: Option<String>,
+}
+
+#[derive(Args, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct HistoryFilter {
+    /// Text looked for in the url, the request file and the response body.
+    #[arg(long)]
+    pub search: Option<String>,
+    /// Request file, relative to the project root.
+    #[arg(long)]
+    pub file: Option<String>,
+    /// Status code eg. `404`, a class eg. `5xx` or `error` for failed requests.
+    #[arg(long)]
+    pub status: Option<String>,
+    #[arg(long = "env")]
+    pub environment: Option<String>,
+    /// A date eg. `2025-03-01`, an RFC 3339 time or a duration ago eg. `12h` or `7d`.
+    #[arg(long)]
+    pub since: Option<String>,
+    /// Same formats as `since`, a date includes the whole day.
+    #[arg(long)]
+    pub until: Option<String>,
+    /// Maximum number of entries, newest first.
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Args, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct PruneOptions {
+    /// Removes entries older than a date or a duration eg. `30d`.
+    #[arg(long)]
+    pub older_than: Option<String>,
+    /// Keeps only this many of the newest entries.
+    #[arg(long)]
+    pub keep: Option<usize>,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        source: &str,
+        file: Option<&str>,
+        environment: Option<&str>,
+        options: &RunOptions,
+        result: &Result<PandaHttpResponse, PandaError>,
+    ) -> Self {
+        let redactor = Redactor::new(&options.variables);
+
+        let (id, request, response, error) = match result {
+            Ok(res) => (
+                res.id.clone(),
+                Some(redactor.request(&res.request)),
+                Some(redactor.response(res)),
+                None,
+            ),
+            Err(err) => {
+                // Only the method and url are known when the request could not be sent.
+                let request = parse_toml::<PandaTomlRequest>(source, &options.variables, None)
+                    .ok()
+                    .and_then(|req| {
+                        req.method_and_url().map(|(method, url)| ResolvedRequest {
+                            method: method.to_string(),
+                            url: url.to_string(),
+                            ..Default::default()
+                        })
+                    });
+
+                (
+                    Uuid::new_v4().to_string(),
+                    request.map(|r| redactor.request(&r)),
+                    None,
+                    Some(err.clone()),
+                )
+            }
+        };
+
+        HistoryEntry {
+            id,
+            timestamp: now().unix_timestamp_nanos() as i64 / 1_000_000,
+            environment: environment.map(|e| e.to_string()),
+            file: file.map(|f| relative_file(&options.project_root, f)),
+            source: redactor.source(source),
+            request,
+            response,
+            error,
+        }
+    }
+
+    /// Request to send again. The request file is read when it still exists since the
+    /// values redacted from `source` can't be sent, secrets that were variables are
+    /// substituted again from `source` otherwise.
+    pub fn replay_source(&self, project_root: &Path) -> Result<String, PandaError> {
+        if let Some(source) = self
+            .file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(project_root.join(file)).ok())
+        {
+            return Ok(source);
+        }
+
+        if self.source.contains(REDACTED) {
+            return Err(PandaError::Request(
+                format!(
+                    "Can't replay {}, some of its values were redacted and its request file is not available.",
+                    self.id
+                )
+                .into(),
+            ));
+        }
+
+        Ok(self.source.clone())
+    }
+
+    fn summary(&self) -> HistorySummary {
+        HistorySummary {
+            id: self.id.clone(),
+            timestamp: self.timestamp,
+            environment: self.environment.clone(),
+            file: self.file.clone(),
+            method: self.request.as_ref().map(|r| r.method.clone()),
+            url: self.request.as_ref().map(|r| r.url.clone()),
+            status: self.response.as_ref().map(|r| r.status),
+            elapsed_time: self.response.as_ref().map(|r| r.elapsed_time),
+            size: self.response.as_ref().map(|r| r.size),
+            error: self.error.as_ref().map(|e| e.to_string()),
+        }
+    }
+
+    fn matches(&self, filter: &HistoryFilter, since: Option<i64>, until: Option<i64>) -> bool {
+        if since.is_some_and(|since| self.timestamp < since)
+            || until.is_some_and(|until| self.timestamp >= until)
+        {
+            return false;
+        }
+
+        if let Some(environment) = &filter.environment {
+            if self.environment.as_deref().unwrap_or_default() != environment {
+                return false;
+            }
+        }
+
+        if let Some(file) = &filter.file {
+            let file = normalise_path(file.to_string());
+            let file = file.trim_start_matches("./");
+
+            if !self.file.as_ref().is_some_and(|f| f.ends_with(file)) {
+                return false;
+            }
+        }
+
+        if let Some(status) = &filter.status {
+            if !self.matches_status(&status.to_lowercase()) {
+                return false;
+            }
+        }
+
+        if let Some(search) = &filter.search {
+            let search = search.to_lowercase();
+
+            let haystacks = [
+                self.request.as_ref().map(|r| r.url.as_str()),
+                self.file.as_deref(),
+                self.response.as_ref().map(|r| r.body.as_str()),
+            ];
+
+            return haystacks
+                .into_iter()
+                .flatten()
+                .any(|h| h.to_lowercase().contains(&search));
+        }
+
+        true
+    }
+
+    fn matches_status(&self, status: &str) -> bool {
+        let code = match &self.response {
+            Some(response) => response.status.to_string(),
+            None => return status == "error",
+        };
+
+        match status.strip_suffix("xx") {
+            Some(class) => code.starts_with(class),
+            None => code == status,
+        }
+    }
+}
+
+/// History of a project stored in `.panda/history.jsonl`.
+pub struct HistoryStore {
+    path: PathBuf,
+}
+
+impl HistoryStore {
+    pub fn open(project_root: &Path) -> Result<Self, PandaError> {
+        let dir = get_panda_dir(project_root).map_err(|err| PandaError::Io(err.into()))?;
+
+        Ok(HistoryStore {
+            path: dir.join(HISTORY_FILE),
+        })
+    }
+
+    pub fn append(&self, entry: &HistoryEntry) -> Result<(), PandaError> {
+        let mut line = serde_json::to_string(entry).map_err(|err| {
+            PandaError::Io(format!("Failed to serialize history: {}", err).into())
+        })?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|err| self.io_error(err))
+    }
+
+    /// Every entry, oldest first. Lines that can't be read eg. after a crash are skipped.
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>, PandaError> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+
+        let content = fs::read_to_string(&self.path).map_err(|err| self.io_error(err))?;
+
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Entries matching `filter`, newest first.
+    pub fn list(&self, filter: &HistoryFilter) -> Result<Vec<HistorySummary>, PandaError> {
+        Ok(self
+            .find(filter)?
+            .iter()
+            .map(|entry| entry.summary())
+            .collect())
+    }
+
+    /// Whole entries matching `filter`, newest first.
+    pub fn find(&self, filter: &HistoryFilter) -> Result<Vec<HistoryEntry>, PandaError> {
+        let since = filter
+            .since
+            .as_deref()
+            .map(|s| parse_time(s, false))
+            .transpose()?;
+
+        let until = filter
+            .until
+            .as_deref()
+            .map(|u| parse_time(u, true))
+            .transpose()?;
+
+        Ok(self
+            .entries()?
+            .into_iter()
+            .rev()
+            .filter(|entry| entry.matches(filter, since, until))
+            .take(filter.limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    /// Finds an entry by id or by a unique id prefix.
+    pub fn get(&self, id: &str) -> Result<HistoryEntry, PandaError> {
+        let mut found: Vec<HistoryEntry> = self
+            .entries()?
+            .into_iter()
+            .filter(|entry| entry.id.starts_with(id))
+            .collect();
+
+        match found.len() {
+            1 => Ok(found.remove(0)),
+            0 => Err(PandaError::Request(
+                format!("History entry {:?} was not found.", id).into(),
+            )),
+            _ => Err(PandaError::Request(
+                format!("History entry {:?} is ambiguous, use a longer id.", id).into(),
+            )),
+        }
+    }
+
+    /// Removes entries older than `older_than` and all but the newest `keep`, returning
+    /// how many were removed.
+    pub fn prune(&self, options: &PruneOptions) -> Result<usize, PandaError> {
+        if options.older_than.is_none() && options.keep.is_none() {
+            return Err(PandaError::Request(
+                "Pass `older_than` or `keep` to prune history.".into(),
+            ));
+        }
+
+        let older_than = options
+            .older_than
+            .as_deref()
+            .map(|o| parse_time(o, false))
+            .transpose()?;
+
+        let entries = self.entries()?;
+        let total = entries.len();
+
+        let mut kept: Vec<HistoryEntry> = entries
+            .into_iter()
+            .filter(|entry| older_than.is_none_or(|older_than| entry.timestamp >= older_than))
+            .collect();
+
+        if let Some(keep) = options.keep {
+            kept.drain(..kept.len().saturating_sub(keep));
+        }
+
+        let mut content = String::new();
+
+        for entry in &kept {
+            let line = serde_json::to_string(entry).map_err(|err| {
+                PandaError::Io(format!("Failed to serialize history: {}", err).into())
+            })?;
+
+            content.push_str(&line);
+            content.push('\n');
+        }
+
+        // Written next to the history first so a failure can't leave it half written.
+        let temp_path = self.path.with_extension("jsonl.tmp");
+
+        fs::write(&temp_path, content)
+            .and_then(|_| fs::rename(&temp_path, &self.path))
+            .map_err(|err| self.io_error(err))?;
+
+        Ok(total - kept.len())
+    }
+
+    fn io_error(&self, err: std::io::Error) -> PandaError {
+        PandaError::Io(format!("Failed to access history {:?}: {}", self.path, err).into())
+    }
+}
+
+/// Replaces secret variable values with their `_.NAME` placeholder and masks secret
+/// headers and query parameters.
+pub struct Redactor {
+    secrets: Vec<(String, String)>,
+}
+
+impl Redactor {
+    pub fn new(variables: &HashMap<String, String>) -> Self {
+        let mut secrets: Vec<(String, String)> = variables
+            .iter()
+            .filter(|(name, value)| is_secret_name(name) && !value.is_empty())
+            .map(|(name, value)| (value.clone(), format!("_.{}", name)))
+            .collect();
+
+        // Longest first so a secret containing another one is replaced whole.
+        secrets.sort_by_key(|(secret, _)| std::cmp::Reverse(secret.len()));
+
+        Redactor { secrets }
+    }
+
+    pub fn text(&self, text: &str) -> String {
+        self.secrets
+            .iter()
+            .fold(text.to_string(), |text, (secret, placeholder)| {
+                text.replace(secret, placeholder)
+            })
+    }
+
+    pub fn url(&self, url: &str) -> String {
+        let mut parsed = match Url::parse(url) {
+            Ok(u) => u,
+            Err(_) => return self.text(url),
+        };
+
+        if parsed.password().is_some() {
+            let _ = parsed.set_password(Some(REDACTED));
+        }
+
+        let pairs: Vec<(String, String)> = parsed.query_pairs().into_owned().collect();
+
+        if pairs.iter().any(|(name, _)| is_secret_name(name)) {
+            parsed
+                .query_pairs_mut()
+                .clear()
+                .extend_pairs(
+                    pairs
+                        .iter()
+                        .map(|(name, value)| match is_secret_name(name) {
+                            true => (name.as_str(), REDACTED),
+                            false => (name.as_str(), value.as_str()),
+                        }),
+                );
+        }
+
+        self.text(parsed.as_str())
+    }
+
+    pub fn headers(&self, headers: &[Header]) -> Vec<Header> {
+        headers
+            .iter()
+            .map(|header| Header {
+                name: header.name.clone(),
+                value: match is_secret_name(&header.name) {
+                    true => REDACTED.to_string(),
+                    false => self.text(&header.value),
+                },
+            })
+            .collect()
+    }
+
+    /// Masks the values of variables with a secret name eg. `TOKEN`.
+    pub fn variables(&self, variables: &HashMap<String, String>) -> HashMap<String, String> {
+        variables
+            .iter()
+            .map(|(name, value)| match is_secret_name(name) {
+                true => (name.clone(), REDACTED.to_string()),
+                false => (name.clone(), self.text(value)),
+            })
+            .collect()
+    }
+
+    /// Masks the values of secret keys eg. headers or query parameters of a request file,
+    /// unless they only use variables, and replaces secret variable values written inline.
+    ///
+    /// Sources that aren't valid TOML have the lines assigning a secret key masked.
+    pub fn source(&self, source: &str) -> String {
+        let mut table = match toml::from_str::<toml::Table>(source) {
+            Ok(t) => t,
+            Err(_) => {
+                return source
+                    .lines()
+                    .map(|line| match line.split_once('=') {
+                        Some((key, _)) if is_secret_name(key) => {
+                            format!("{}= \"{}\"", key, REDACTED)
+                        }
+                        _ => self.text(line),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        };
+
+        for (key, value) in table.iter_mut() {
+            self.toml_value(value, is_secret_name(key));
+        }
+
+        toml::to_string(&table).unwrap_or_default()
+    }
+
+    fn toml_value(&self, value: &mut toml::Value, secret: bool) {
+        match value {
+            toml::Value::String(text) if secret && get_variables(text).is_empty() => {
+                *text = REDACTED.to_string()
+            }
+            toml::Value::String(text) => *text = self.text(text),
+            toml::Value::Integer(_) | toml::Value::Float(_) if secret => {
+                *value = toml::Value::String(REDACTED.to_string())
+            }
+            toml::Value::Array(values) => {
+                for value in values {
+                    self.toml_value(value, secret);
+                }
+            }
+            toml::Value::Table(table) => {
+                for (key, value) in table.iter_mut() {
+                    self.toml_value(value, secret || is_secret_name(key));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn request(&self, request: &ResolvedRequest) -> ResolvedRequest {
+        ResolvedRequest {
+            method: request.method.clone(),
+            url: self.url(&request.url),
+            headers: self.headers(&request.headers),
+            body: request.body.as_deref().map(|b| self.text(b)),
+        }
+    }
+
+    pub fn response(&self, response: &PandaHttpResponse) -> HistoryResponse {
+        let (body, body_truncated) = match (&response.text_response, response.body_kind) {
+            (Some(text), BodyKind::Text) => {
+                let mut end = text.len().min(HISTORY_BODY_LIMIT);
+
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+
+                (self.text(&text[..end]), end < text.len())
+            }
+            _ => {
+                let end = response.body.len().min(HISTORY_BODY_LIMIT);
+
+                (
+                    STANDARD.encode(&response.body[..end]),
+                    end < response.body.len(),
+                )
+            }
+        };
+
+        HistoryResponse {
+            status: response.status,
+            http_version: response.connection.http_version.clone(),
+            final_url: self.url(&response.final_url),
+            headers: self.headers(response.headers.as_deref().unwrap_or_default()),
+            mime_type: response.mime_type.clone(),
+            body_kind: response.body_kind,
+            size: response.size,
+            body,
+            body_truncated,
+            elapsed_time: response.elapsed_time,
+            timings: response.timings.clone(),
+        }
+    }
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+
+    SECRET_NAMES.iter().any(|secret| name.contains(secret))
+}
+
+/// Makes `file` relative to the project root when it is inside it.
+fn relative_file(project_root: &Path, file: &str) -> String {
+    let path = Path::new(file);
+
+    let relative = match path.strip_prefix(project_root) {
+        Ok(r) if project_root.is_absolute() => r,
+        _ => path,
+    };
+
+    normalise_path(relative.to_string_lossy().to_string())
+}
+
+fn now() -> OffsetDateTime {
+    OffsetDateTime::now_utc()
+}
+
+/// Parses a date, an RFC 3339 time or a duration ago eg. `12h` into a unix timestamp in
+/// milliseconds. With `end_of_day` a date means the end of that day.
+fn parse_time(value: &str, end_of_day: bool) -> Result<i64, PandaError> {
+    let value = value.trim();
+
+    let time = if let Some(ago) = parse_duration(value) {
+        now() - ago
+    } else if let Ok(date) = Date::parse(value, format_description!("[year]-[month]-[day]")) {
+        let start = date.midnight().assume_utc();
+
+        match end_of_day {
+            true => start + Duration::days(1),
+            false => start,
+        }
+    } else if let Ok(time) = OffsetDateTime::parse(value, &Rfc3339) {
+        time
+    } else {
+        return Err(PandaError::Parse(
+            format!(
+                "Invalid time {:?}, use a date like 2025-03-01, an RFC 3339 time or a duration like 12h or 7d.",
+                value
+            )
+            .into(),
+        ));
+    };
+
+    Ok((time.unix_timestamp_nanos() / 1_000_000) as i64)
+}
+
+/// Parses durations like `30m`, `12h`, `7d` or `2w`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let unit = value.chars().last()?;
+    let amount: i64 = value[..value.len() - unit.len_utf8()].parse().ok()?;
+
+    match unit {
+        'm' => Some(Duration::minutes(amount)),
+        'h' => Some(Duration::hours(amount)),
+        'd' => Some(Duration::days(amount)),
+        'w' => Some(Duration::weeks(amount)),
+        _ => None,
+    }
+}
+
+/// Formats a history timestamp for display eg. `2025-03-01T10:00:00Z`.
+pub fn format_timestamp(timestamp: i64) -> String {
+    OffsetDateTime::from_unix_timestamp(timestamp / 1000)
+        .ok()
+        .and_then(|time| time.format(&Rfc3339).ok())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_secrets_in_source() {
+        let variables = HashMap::from([
+            ("API_TOKEN".to_string(), "t0ken".to_string()),
+            ("HOST".to_string(), "example.com".to_string()),
+        ]);
+        let redactor = Redactor::new(&variables);
+
+        let source = r#"
+[post]
+url = "https://_.HOST/login"
+
+[post.headers]
+Authorization = "Bearer _.API_TOKEN"
+X-Api-Key = "abc123"
+Accept = "application/json"
+
+[post.query]
+session = 42
+page = "1"
+
+[post.json]
+content = '{"key": "t0ken"}'
+"#;
+
+        let redacted: toml::Table = toml::from_str(&redactor.source(source)).unwrap();
+        let post = &redacted["post"];
+
+        assert_eq!(post["url"].as_str(), Some("https://_.HOST/login"));
+        assert_eq!(
+            post["headers"]["Authorization"].as_str(),
+            Some("Bearer _.API_TOKEN")
+        );
+        assert_eq!(post["headers"]["X-Api-Key"].as_str(), Some(REDACTED));
+        assert_eq!(post["headers"]["Accept"].as_str(), Some("application/json"));
+        assert_eq!(post["query"]["session"].as_str(), Some(REDACTED));
+        assert_eq!(post["query"]["page"].as_str(), Some("1"));
+        assert_eq!(
+            post["json"]["content"].as_str(),
+            Some(r#"{"key": "_.API_TOKEN"}"#)
+        );
+    }
+
+    /// Answers a single request with a 200, sending its head back through the channel.
+    async fn start_server() -> (u16, tokio::sync::oneshot::Receiver<String>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 4096];
+            let read = stream.read(&mut request).await.unwrap();
+            let _ = tx.send(String::from_utf8_lossy(&request[..read]).to_string());
+
+            let _ = stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await;
+        });
+
+        (port, rx)
+    }
+
+    #[tokio::test]
+    async fn replays_inline_secrets_from_the_request_file() {
+        let project = tempfile::tempdir().unwrap();
+        let (port, received) = start_server().await;
+
+        let options = RunOptions {
+            project_root: project.path().to_path_buf(),
+            proxy: Some(crate::config::ProxyConfig {
+                use_env: Some(false),
+                ..Default::default()
+            }),
+            variables: HashMap::from([("API_TOKEN".to_string(), "t0ken".to_string())]),
+            ..Default::default()
+        };
+
+        let source = format!(
+            "[get]\nurl = \"http://127.0.0.1:{}/\"\n\n[get.headers]\nX-Api-Key = \"abc123\"\nAuthorization = \"Bearer _.API_TOKEN\"\n",
+            port
+        );
+        let file = project.path().join("login.toml");
+        fs::write(&file, &source).unwrap();
+
+        let entry = HistoryEntry::new(
+            &source,
+            file.to_str(),
+            None,
+            &options,
+            &Err(PandaError::Network("offline".into())),
+        );
+        assert!(entry.source.contains(REDACTED));
+        assert_eq!(entry.file.as_deref(), Some("login.toml"));
+
+        let (_cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+        let replay = entry.replay_source(project.path()).unwrap();
+        let response = crate::http_runner::run_toml_request(&replay, &options, cancel_rx)
+            .await
+            .unwrap();
+        assert_eq!(response.status, 200);
+
+        let request = received.await.unwrap().to_lowercase();
+        assert!(request.contains("x-api-key: abc123"), "{}", request);
+        assert!(
+            request.contains("authorization: bearer t0ken"),
+            "{}",
+            request
+        );
+
+        // Without the file the redacted value would be sent instead.
+        fs::remove_file(&file).unwrap();
+        let err = entry.replay_source(project.path()).unwrap_err();
+        assert!(err.to_string().contains("redacted"), "{}", err);
+    }
+
+    #[test]
+    fn replays_the_stored_source_when_nothing_was_redacted() {
+        let variables = HashMap::from([("API_TOKEN".to_string(), "t0ken".to_string())]);
+        let options = RunOptions {
+            variables,
+            ..Default::default()
+        };
+        let source = "[get]\nurl = \"https://example.com/?key=t0ken\"\n";
+
+        let entry = HistoryEntry::new(
+            source,
+            None,
+            None,
+            &options,
+            &Err(PandaError::Network("offline".into())),
+        );
+
+        assert_eq!(
+            entry.replay_source(Path::new("/nonexistent")).unwrap(),
+            "[get]\nurl = \"https://example.com/?key=_.API_TOKEN\"\n"
+        );
+    }
+
+    #[test]
+    fn redacts_secret_lines_of_invalid_source() {
+        let redactor = Redactor::new(&HashMap::new());
+
+        let redacted = redactor.source("[get\nurl = \"https://example.com\"\ntoken = \"abc\"");
+
+        assert_eq!(
+            redacted,
+            "[get\nurl = \"https://example.com\"\ntoken = \"[redacted]\""
+        );
+    }
+}
@@ -0,0 +1,471 @@
+use crate::body::BodyKind;
+use crate::headers::Header;
+use crate::history::HistoryResponse;
+use crate::report::{escape_xml as escape, RunReport};
+use crate::runner::RequestResult;
+use crate::snapshot::SnapshotOutcome;
+use std::fmt::Write;
+
+/// Styles are inlined and nothing is loaded from the network so the file can be opened
+/// anywhere, eg. from a CI artifact.
+const STYLE: &str = r#"
+body { font: 14px/1.5 -apple-system, "Segoe UI", Roboto, sans-serif; color: #1f2328; margin: 0; background: #f6f8fa; }
+main { max-width: 1100px; margin: 0 auto; padding: 24px; }
+h1 { font-size: 22px; margin: 0 0 4px; }
+h2 { font-size: 16px; margin: 32px 0 12px; }
+.meta { color: #59636e; margin-bottom: 20px; }
+.cards { display: flex; gap: 12px; flex-wrap: wrap; }
+.card { background: #fff; border: 1px solid #d1d9e0; border-radius: 6px; padding: 12px 16px; min-width: 110px; }
+.card b { display: block; font-size: 22px; }
+.passed { color: #1a7f37; }
+.failed { color: #d1242f; }
+.skipped { color: #9a6700; }
+table { width: 100%; border-collapse: collapse; background: #fff; border: 1px solid #d1d9e0; }
+th, td { text-align: left; padding: 6px 10px; border-bottom: 1px solid #d1d9e0; vertical-align: top; }
+th { background: #f6f8fa; font-weight: 600; }
+td.num { text-align: right; font-variant-numeric: tabular-nums; }
+.badge { display: inline-block; padding: 0 8px; border-radius: 10px; font-size: 12px; font-weight: 600; color: #fff; }
+.badge.passed { background: #1a7f37; }
+.badge.failed { background: #d1242f; }
+details { background: #fff; border: 1px solid #d1d9e0; border-radius: 6px; margin-bottom: 8px; }
+summary { cursor: pointer; padding: 8px 12px; }
+details > div { padding: 0 12px 12px; }
+h3 { font-size: 13px; margin: 12px 0 4px; text-transform: uppercase; color: #59636e; }
+pre { background: #f6f8fa; padding: 8px; border-radius: 4px; overflow-x: auto; white-space: pre-wrap; word-break: break-all; margin: 0; }
+ul.checks { list-style: none; padding: 0; margin: 0; }
+ul.checks li::before { content: "\2713  "; color: #1a7f37; }
+ul.checks li.failed::before { content: "\2717  "; color: #d1242f; }
+svg text { font-size: 11px; fill: #59636e; }
+.legend span { display: inline-block; margin-right: 12px; font-size: 12px; }
+.legend i { display: inline-block; width: 10px; height: 10px; margin-right: 4px; border-radius: 2px; }
+"#;
+
+/// Colors of the timing phases in the chart, in the order they happen.
+const PHASES: [(&str, &str); 5] = [
+    ("DNS", "#8250df"),
+    ("TCP", "#0969da"),
+    ("TLS", "#bf8700"),
+    ("Waiting", "#1a7f37"),
+    ("Download", "#57ab5a"),
+];
+
+/// A single HTML page with the summary, a timings chart and the details of each request.
+pub fn render(report: &RunReport) -> String {
+    let summary = &report.summary;
+    let mut html = String::new();
+
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>Panda run report - {}</title>\n<style>{}</style>\n</head>\n<body>\n<main>\n",
+        escape(&report.target),
+        STYLE
+    );
+
+    let _ = writeln!(html, "<h1>{}</h1>", escape(&report.target));
+    let _ = writeln!(
+        html,
+        "<div class=\"meta\">Started {}{}{}</div>",
+        escape(&report.started_at),
+        report
+            .environment
+            .as_ref()
+            .map(|env| format!(" &middot; environment {}", escape(env)))
+            .unwrap_or_default(),
+        match summary.cancelled {
+            true => " &middot; cancelled",
+            false => "",
+        }
+    );
+
+    let _ = writeln!(
+        html,
+        "<div class=\"cards\">\
+         <div class=\"card\"><b>{}</b>requests</div>\
+         <div class=\"card passed\"><b>{}</b>passed</div>\
+         <div class=\"card failed\"><b>{}</b>failed</div>\
+         <div class=\"card skipped\"><b>{}</b>skipped</div>\
+         <div class=\"card\"><b>{}</b>duration</div></div>",
+        summary.total,
+        summary.passed,
+        summary.failed,
+        summary.skipped,
+        format_ms(summary.elapsed_time)
+    );
+
+    if !report.results.is_empty() {
+        html.push_str("<h2>Requests</h2>\n");
+        results_table(&mut html, &report.results);
+
+        html.push_str("<h2>Timings</h2>\n");
+        timings_chart(&mut html, &report.results);
+
+        html.push_str("<h2>Details</h2>\n");
+
+        for (index, result) in report.results.iter().enumerate() {
+            details(&mut html, index, result);
+        }
+    }
+
+    html.push_str("</main>\n</body>\n</html>\n");
+
+    html
+}
+
+fn results_table(html: &mut String, results: &[RequestResult]) {
+    html.push_str(
+        "<table>\n<tr><th></th><th>Request</th><th>Status</th><th class=\"num\">Time</th></tr>\n",
+    );
+
+    for (index, result) in results.iter().enumerate() {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td><a href=\"#request-{}\">{}</a></td><td>{}</td><td class=\"num\">{}</td></tr>",
+            badge(result),
+            index,
+            escape(&title(result)),
+            result
+                .status
+                .map(|s| s.to_string())
+                .or_else(|| result.error.as_ref().map(|e| e.kind().to_string()))
+                .unwrap_or_default(),
+            result.elapsed_time.map(format_ms).unwrap_or_default()
+        );
+    }
+
+    html.push_str("</table>\n");
+}
+
+/// Horizontal bars split by connection phase, scaled to the slowest request.
+fn timings_chart(html: &mut String, results: &[RequestResult]) {
+    const LABEL_WIDTH: f64 = 260.0;
+    const BAR_WIDTH: f64 = 720.0;
+    const ROW_HEIGHT: f64 = 22.0;
+
+    let slowest = results
+        .iter()
+        .filter_map(|r| r.elapsed_time)
+        .fold(0.0, f64::max);
+
+    let height = ROW_HEIGHT * results.len() as f64;
+
+    let _ = writeln!(
+        html,
+        "<svg width=\"100%\" viewBox=\"0 0 {} {}\" role=\"img\">",
+        LABEL_WIDTH + BAR_WIDTH + 80.0,
+        height
+    );
+
+    for (index, result) in results.iter().enumerate() {
+        let y = index as f64 * ROW_HEIGHT;
+        let label: String = title(result).chars().take(40).collect();
+
+        let _ = writeln!(
+            html,
+            "<text x=\"0\" y=\"{}\">{}</text>",
+            y + 15.0,
+            escape(&label)
+        );
+
+        let phases = match (&result.response, result.elapsed_time) {
+            (Some(response), _) => phases(response),
+            (None, Some(elapsed)) => vec![0.0, 0.0, 0.0, elapsed, 0.0],
+            (None, None) => vec![],
+        };
+
+        let mut x = LABEL_WIDTH;
+
+        for (duration, (name, color)) in phases.iter().zip(PHASES) {
+            if *duration <= 0.0 || slowest <= 0.0 {
+                continue;
+            }
+
+            let width = duration / slowest * BAR_WIDTH;
+
+            let _ = writeln!(
+                html,
+                "<rect x=\"{:.1}\" y=\"{}\" width=\"{:.1}\" height=\"14\" fill=\"{}\"><title>{} {}</title></rect>",
+                x,
+                y + 4.0,
+                width.max(0.5),
+                color,
+                name,
+                format_ms(*duration)
+            );
+
+            x += width;
+        }
+
+        if let Some(elapsed) = result.elapsed_time {
+            let _ = writeln!(
+                html,
+                "<text x=\"{:.1}\" y=\"{}\">{}</text>",
+                x + 6.0,
+                y + 15.0,
+                format_ms(elapsed)
+            );
+        }
+    }
+
+    html.push_str("</svg>\n<div class=\"legend\">");
+
+    for (name, color) in PHASES {
+        let _ = write!(
+            html,
+            "<span><i style=\"background: {}\"></i>{}</span>",
+            color, name
+        );
+    }
+
+    html.push_str("</div>\n");
+}
+
+/// Durations of `PHASES`, redirects are counted as waiting.
+fn phases(response: &HistoryResponse) -> Vec<f64> {
+    let timings = &response.timings;
+    let connection = timings.dns.unwrap_or_default()
+        + timings.tcp.unwrap_or_default()
+        + timings.tls.unwrap_or_default();
+    let redirects = (response.elapsed_time - timings.total).max(0.0);
+
+    vec![
+        timings.dns.unwrap_or_default(),
+        timings.tcp.unwrap_or_default(),
+        timings.tls.unwrap_or_default(),
+        (timings.total - connection - timings.download).max(0.0) + redirects,
+        timings.download,
+    ]
+}
+
+fn details(html: &mut String, index: usize, result: &RequestResult) {
+    let _ = writeln!(
+        html,
+        "<details id=\"request-{}\"{}>\n<summary>{} {}</summary>\n<div>",
+        index,
+        match result.passed() {
+            true => "",
+            false => " open",
+        },
+        badge(result),
+        escape(&title(result))
+    );
+
+    html.push_str("<h3>Checks</h3>\n<ul class=\"checks\">\n");
+
+    match &result.error {
+        Some(err) => {
+            let _ = writeln!(
+                html,
+                "<li class=\"failed\">{}: {}</li>",
+                err.kind(),
+                escape(&err.to_string())
+            );
+        }
+        None => html.push_str("<li>Response received</li>\n"),
+    }
+
+    let mut captured: Vec<&String> = result.captured.keys().collect();
+    captured.sort();
+
+    for name in captured {
+        let _ = writeln!(html, "<li>Captured {}</li>", escape(name));
+    }
+
+    match &result.snapshot {
+        Some(SnapshotOutcome::Matched) => html.push_str("<li>Snapshot matched</li>\n"),
+        Some(SnapshotOutcome::Created) => html.push_str("<li>Snapshot written</li>\n"),
+        Some(SnapshotOutcome::Updated(changes)) => {
+            let _ = writeln!(html, "<li>Snapshot updated, {} changes</li>", changes.len());
+        }
+        Some(SnapshotOutcome::Changed(changes)) => {
+            let _ = writeln!(
+                html,
+                "<li class=\"failed\">Snapshot changed<pre>{}</pre></li>",
+                escape(
+                    &changes
+                        .iter()
+                        .map(|c| c.to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                )
+            );
+        }
+        None => {}
+    }
+
+    html.push_str("</ul>\n");
+
+    if let (None, Some(method), Some(url)) = (&result.request, &result.method, &result.url) {
+        // The request could not be sent, only what the file says is known.
+        let _ = writeln!(
+            html,
+            "<h3>Request</h3>\n<pre>{} {}</pre>",
+            escape(method),
+            escape(url)
+        );
+    }
+
+    if let Some(request) = &result.request {
+        let _ = writeln!(
+            html,
+            "<h3>Request</h3>\n<pre>{} {}\n{}</pre>",
+            escape(&request.method),
+            escape(&request.url),
+            escape(&format_headers(&request.headers))
+        );
+
+        if let Some(body) = &request.body {
+            let _ = writeln!(html, "<pre>{}</pre>", escape(&pretty(body)));
+        }
+    }
+
+    if let Some(response) = &result.response {
+        let _ = writeln!(
+            html,
+            "<h3>Response</h3>\n<pre>{} {}\n{}</pre>",
+            response.status,
+            escape(&response.final_url),
+            escape(&format_headers(&response.headers))
+        );
+
+        let body = match response.body_kind {
+            BodyKind::Text => pretty(&response.body),
+            BodyKind::Binary => format!("<{} bytes of {}>", response.size, response.mime_type),
+        };
+
+        if !body.is_empty() {
+            let _ = writeln!(
+                html,
+                "<pre>{}{}</pre>",
+                escape(&body),
+                match response.body_truncated {
+                    true => "\n…",
+                    false => "",
+                }
+            );
+        }
+    }
+
+    html.push_str("</div>\n</details>\n");
+}
+
+fn badge(result: &RequestResult) -> &'static str {
+    match result.passed() {
+        true => "<span class=\"badge passed\">PASS</span>",
+        false => "<span class=\"badge failed\">FAIL</span>",
+    }
+}
+
+/// `GET users/list.toml` with the data row for iterations.
+fn title(result: &RequestResult) -> String {
+    let mut title = match &result.method {
+        Some(method) => format!("{} {}", method, result.file),
+        None => result.file.clone(),
+    };
+
+    if let Some(i) = &result.iteration {
+        let _ = write!(title, " [{}/{} {}]", i.index, i.total, i.data_file);
+    }
+
+    title
+}
+
+fn format_headers(headers: &[Header]) -> String {
+    headers
+        .iter()
+        .map(|h| format!("{}: {}", h.name, h.value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Indents JSON bodies, other ones are kept as is.
+fn pretty(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| serde_json::to_string_pretty(&json).ok())
+        .unwrap_or_else(|| body.to_string())
+}
+
+fn format_ms(ms: f64) -> String {
+    match ms >= 1000.0 {
+        true => format!("{:.2}s", ms / 1000.0),
+        false => format!("{:.1}ms", ms),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::Redactor;
+    use crate::http_runner::ResolvedRequest;
+    use crate::runner::RunSummary;
+    use std::collections::HashMap;
+
+    const SCRIPT: &str = "<script>alert(1)</script>";
+
+    #[test]
+    fn redacts_secrets_and_escapes_markup() {
+        let variables = HashMap::from([("API_TOKEN".to_string(), "s3cret".to_string())]);
+        let redactor = Redactor::new(&variables);
+
+        let url = "https://example.com/search?q=%3Cscript%3E&api_key=s3cret".to_string();
+        let body = format!(r#"{{"token": "s3cret", "html": "{}"}}"#, SCRIPT);
+
+        let request = ResolvedRequest {
+            method: "POST".to_string(),
+            url: url.clone(),
+            headers: vec![Header {
+                name: "Authorization".to_string(),
+                value: "Bearer s3cret".to_string(),
+            }],
+            body: Some(body.clone()),
+        };
+
+        let result = RequestResult {
+            file: format!("collection/{}.toml", SCRIPT),
+            method: Some("POST".to_string()),
+            url: Some(redactor.url(&url)),
+            status: Some(200),
+            elapsed_time: Some(5.0),
+            request: Some(redactor.request(&request)),
+            response: Some(HistoryResponse {
+                status: 200,
+                http_version: "HTTP/1.1".to_string(),
+                final_url: redactor.url(&url),
+                headers: vec![],
+                mime_type: "text/html".to_string(),
+                body_kind: BodyKind::Text,
+                size: body.len(),
+                body: redactor.text(&format!("<p>s3cret</p>{}", SCRIPT)),
+                body_truncated: false,
+                elapsed_time: 5.0,
+                timings: Default::default(),
+            }),
+            ..Default::default()
+        };
+
+        let html = render(&RunReport {
+            target: SCRIPT.to_string(),
+            environment: Some(SCRIPT.to_string()),
+            started_at: "2024-01-01T00:00:00Z".to_string(),
+            summary: RunSummary {
+                total: 1,
+                passed: 1,
+                ..Default::default()
+            },
+            results: vec![result],
+        });
+
+        assert!(!html.contains("s3cret"));
+        assert!(html.contains("_.API_TOKEN"));
+        assert!(html.contains("Authorization: [redacted]"));
+        assert!(!html.contains("<script"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+
+        // Nothing is loaded from the network.
+        for reference in ["src=", "href=\"http", "url(", "@import", "<link"] {
+            assert!(!html.contains(reference), "{}", reference);
+        }
+    }
+}
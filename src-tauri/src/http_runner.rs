@@ -1,26 +1,157 @@
-use reqwest::header::CONTENT_TYPE;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::header::{CONTENT_TYPE, USER_AGENT};
 use reqwest::multipart;
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::str::FromStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use std::{collections::HashMap, time::Instant};
-use tauri::http::HeaderMap;
-use tauri::http::HeaderName;
 use tauri::http::HeaderValue;
 use tokio::sync::oneshot;
 use tokio::sync::watch::Receiver;
+use uuid::Uuid;
+
+use crate::body::{self, BodyKind, TextEncoding, INLINE_BINARY_LIMIT};
+use crate::config::{merge_proxy, read_config, ClientCertificate, ProxyConfig, SnapshotConfig};
+use crate::cookies::PandaCookieJar;
+use crate::error::PandaError;
+use crate::headers::{self, Header, RequestHeaders};
+use crate::import::{FormValue, ImportedBody};
+use crate::redirect::{self, RedirectHop, Redirected};
+use crate::retry::{RetryAttempt, RetryConfig};
+use crate::timings::{ConnectionProbe, ProbeLayer, TimedResolver, Timings};
+use crate::tls::TlsDetails;
+use crate::{proxy, tls, utils};
 
 type Json = serde_json::Value;
 
 // Separate logic
-#[derive(Deserialize, Clone, Debug, Serialize)]
+#[derive(Deserialize, Clone, Debug, Default, Serialize)]
 pub struct PandaHttpResponse {
+    /// Identifies the body kept in memory for chunked reads and saving to a file.
+    pub id: String,
+    /// The request that was sent for the first hop.
+    pub request: ResolvedRequest,
     pub status: u16,
-    pub elapsed_time: u64,
+    /// Url of the final response once redirects were followed.
+    pub final_url: String,
+    /// Redirects followed before the final response, in order.
+    pub redirects: Vec<RedirectHop>,
+    /// Earlier attempts that were retried, empty when the first one was kept.
+    pub attempts: Vec<RetryAttempt>,
+    /// Total time in milliseconds redirects included.
+    pub elapsed_time: f64,
+    /// Breakdown of the final request, each redirect has its own.
+    pub timings: Timings,
+    pub connection: ConnectionInfo,
     pub text_response: Option<String>,
-    pub headers: Option<HashMap<String, String>>,
+    /// How `text_response` was decoded.
+    pub text_encoding: Option<TextEncoding>,
+    /// Binary bodies up to `INLINE_BINARY_LIMIT` bytes, larger ones are read in chunks.
+    pub base64_response: Option<String>,
+    pub body_kind: BodyKind,
+    /// Size of the body in bytes after decompression.
+    pub size: usize,
+    /// Mime type sniffed from the body, falls back to `Content-Type`.
+    pub mime_type: String,
+    /// Response headers with lowercase names and repeated ones included, see
+    /// `headers::read_headers` for how hyper orders them.
+    pub headers: Option<Vec<Header>>,
     pub content_type: String,
+    #[serde(skip)]
+    pub body: Vec<u8>,
+}
+
+/// A request after variables were substituted.
+#[derive(Deserialize, Clone, Debug, Default, Serialize)]
+pub struct ResolvedRequest {
+    pub method: String,
+    pub url: String,
+    /// Headers set by the request file with the case they were written in, the ones added
+    /// by the client are not included.
+    pub headers: Vec<Header>,
+    /// `None` without a body or for multipart forms which are streamed.
+    pub body: Option<String>,
+}
+
+impl ResolvedRequest {
+    fn from_request(request: &reqwest::Request, written: Option<&RequestHeaders>) -> Self {
+        let headers = headers::read_headers(request.headers())
+            .into_iter()
+            .map(|header| Header {
+                name: written
+                    .and_then(|w| w.written_name(&header.name))
+                    .map(str::to_string)
+                    .unwrap_or(header.name),
+                ..header
+            })
+            .collect();
+
+        ResolvedRequest {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            headers,
+            body: request
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map(|b| String::from_utf8_lossy(b).to_string()),
+        }
+    }
+}
+
+/// How the response was delivered, useful when debugging load balancers and certificates.
+#[derive(Deserialize, Clone, Debug, Default, Serialize)]
+pub struct ConnectionInfo {
+    /// eg. `HTTP/1.1` or `HTTP/2.0`.
+    pub http_version: String,
+    /// Address the client connected to, the proxy's when one is used.
+    pub remote_ip: Option<String>,
+    pub remote_port: Option<u16>,
+    /// `true` when an already open connection was used.
+    pub reused: bool,
+    pub tls: Option<TlsDetails>,
+}
+
+/// Project level settings applied to the client built for each request.
+#[derive(Clone, Debug, Default)]
+pub struct RunOptions {
+    /// Directory containing `panda.config.json`, relative paths are resolved against it.
+    pub project_root: PathBuf,
+    pub client_certificates: Vec<ClientCertificate>,
+    /// Project proxy merged with the active environment's.
+    pub proxy: Option<ProxyConfig>,
+    /// Cookie jar of the active environment, `None` disables cookies entirely.
+    pub cookie_jar: Option<Arc<PandaCookieJar>>,
+    /// Variables of `panda.config.json` overridden by the ones given to the run.
+    pub variables: HashMap<String, String>,
+}
+
+impl RunOptions {
+    /// Reads the options of the project at `project_root` for `environment`.
+    pub fn for_project(
+        project_root: &Path,
+        environment: Option<&str>,
+        variables: HashMap<String, String>,
+    ) -> Result<Self, PandaError> {
+        let config = read_config(&project_root.to_string_lossy())?;
+
+        let cookie_jar = PandaCookieJar::load(project_root, environment)
+            .map_err(|err| PandaError::Io(err.into()))?;
+
+        let mut all_variables = config.get_variables(environment);
+        all_variables.extend(variables);
+
+        Ok(RunOptions {
+            proxy: config.get_proxy(environment),
+            client_certificates: config.client_certificates,
+            cookie_jar: Some(Arc::new(cookie_jar)),
+            project_root: project_root.to_path_buf(),
+            variables: all_variables,
+        })
+    }
 }
 
 #[derive(Deserialize, Clone, Debug, Serialize)]
@@ -34,6 +165,124 @@ pub struct PandaTomlRequest {
     pub options: Option<RequestParams>,
 }
 
+impl PandaTomlRequest {
+    /// The method and parameters of the first request defined in the file.
+    pub fn params(&self) -> Option<(&'static str, &RequestParams)> {
+        let requests = [
+            ("GET", &self.get),
+            ("HEAD", &self.head),
+            ("POST", &self.post),
+            ("PUT", &self.put),
+            ("PATCH", &self.patch),
+            ("DELETE", &self.delete),
+            ("OPTIONS", &self.options),
+        ];
+
+        requests
+            .into_iter()
+            .find_map(|(method, params)| params.as_ref().map(|p| (method, p)))
+    }
+
+    /// Method and url of the request as written.
+    pub fn method_and_url(&self) -> Option<(&'static str, &str)> {
+        self.params()
+            .map(|(method, params)| (method, params.url.as_str()))
+    }
+
+    /// The `[method.snapshot]` table of the request.
+    pub fn snapshot_config(&self) -> Option<&SnapshotConfig> {
+        self.params()
+            .and_then(|(_, params)| params.snapshot.as_ref())
+    }
+
+    /// The `[method.capture]` table of the request, variable names to JSONPath expressions.
+    pub fn captures(&self) -> Option<&HashMap<String, String>> {
+        self.params()
+            .and_then(|(_, params)| params.capture.as_ref())
+    }
+
+    /// The `[method.retry]` table of the request.
+    pub fn retry_config(&self) -> Option<&RetryConfig> {
+        self.params().and_then(|(_, params)| params.retry.as_ref())
+    }
+}
+
+impl RequestParams {
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The `[method.query]` table, strings without their quotes.
+    pub fn query(&self) -> Vec<(String, String)> {
+        table_text(self.query.as_ref())
+    }
+
+    /// The `[method.params]` table, values of the path variables not set by the run.
+    pub fn path_params(&self) -> Vec<(String, String)> {
+        table_text(self.params.as_ref())
+    }
+
+    pub fn headers(&self) -> &[Header] {
+        self.headers
+            .as_ref()
+            .map(|headers| headers.0.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The body table of the request, in the order `prepare_request` picks them.
+    pub fn body(&self) -> Option<ImportedBody> {
+        let fields = |form: &FormPayload| {
+            form.content
+                .iter()
+                .map(|item| (item.field.clone(), item.value.clone()))
+                .collect()
+        };
+
+        if let Some(text) = &self.text {
+            Some(ImportedBody::Text(text.content.clone()))
+        } else if let Some(json) = &self.json {
+            Some(ImportedBody::Json(json.content.clone()))
+        } else if let Some(form) = &self.form_multipart {
+            let fields = form.content.iter().map(|item| {
+                let value = match &item.file {
+                    Some(file) => FormValue::File(file.clone()),
+                    None => FormValue::Text(item.value.clone()),
+                };
+
+                (item.field.clone(), value)
+            });
+
+            Some(ImportedBody::FormMultipart(fields.collect()))
+        } else if let Some(form) = &self.form_urlencoded {
+            Some(ImportedBody::FormUrlEncoded(fields(form)))
+        } else {
+            self.xml
+                .as_ref()
+                .map(|xml| ImportedBody::Xml(xml.content.clone()))
+        }
+    }
+}
+
+/// Keys and values of a table, strings without their quotes.
+fn table_text(table: Option<&Json>) -> Vec<(String, String)> {
+    let table = match table.and_then(Value::as_object) {
+        Some(table) => table,
+        None => return vec![],
+    };
+
+    table
+        .iter()
+        .map(|(key, value)| match value {
+            Value::String(value) => (key.clone(), value.clone()),
+            value => (key.clone(), value.to_string()),
+        })
+        .collect()
+}
+
 #[derive(Deserialize, Clone, Debug, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub struct RequestParams {
@@ -42,14 +291,52 @@ pub struct RequestParams {
 
     url: String,
     name: Option<String>,
+    /// Values of the path variables of `url` the run does not set.
+    ///
+    /// ```toml
+    /// [get]
+    /// url = "_.base_url/users/_.user_id"
+    ///
+    /// [get.params]
+    /// user_id = 12345
+    /// ```
     params: Option<Json>,
     query: Option<Json>,
-    headers: Option<Json>,
+    headers: Option<RequestHeaders>,
     pre_request: Option<Script>,
+    proxy: Option<ProxyConfig>,
+    /// Set to `false` to neither send nor store cookies from the project jar.
+    cookie_jar: Option<bool>,
+    /// Set to `false` to get the first redirect response instead of following it.
+    follow_redirects: Option<bool>,
+    /// What `panda test --snapshot` records for this request.
+    snapshot: Option<SnapshotConfig>,
+    /// Variables set from the response for the following requests of a collection run.
+    ///
+    /// ```toml
+    /// [post.capture]
+    /// TOKEN = "$.body.access_token"
+    /// REQUEST_ID = "$.headers['x-request-id']"
+    /// ```
+    capture: Option<HashMap<String, String>>,
+    /// Sends the request again on some statuses and errors, see `RetryConfig`.
+    retry: Option<RetryConfig>,
+    /// Milliseconds before each attempt fails with a `timeout` error, body included.
+    /// Unlimited by default.
+    timeout: Option<u64>,
 
     // Request Bodies
     text: Option<BodyText>,
     json: Option<BodyText>,
+    /// Fields with a `file` send its content, the path is relative to the project root.
+    ///
+    /// ```toml
+    /// [post.form_multipart]
+    /// content = [
+    ///     { field = "title", value = "Mars" },
+    ///     { field = "image", file = "./images/mars.png" },
+    /// ]
+    /// ```
     form_multipart: Option<FormPayload>,
     form_urlencoded: Option<FormPayload>,
     xml: Option<BodyText>,
@@ -89,9 +376,12 @@ enum BodyVariants {
 }
 
 #[derive(serde::Deserialize, Debug, Clone, serde::Serialize)]
-struct FormContent {
-    field: String,
-    value: String,
+pub struct FormContent {
+    pub field: String,
+    #[serde(default)]
+    pub value: String,
+    /// File sent as the value of a multipart field.
+    pub file: Option<String>,
 }
 
 #[derive(Deserialize, Clone, Debug, Serialize)]
@@ -99,21 +389,202 @@ struct Script {
     code: String,
 }
 
+/// Substitutes `variables` in a TOML request and runs it.
+pub async fn run_toml_request(
+    toml_schema: &str,
+    options: &RunOptions,
+    cancelled_rx: Receiver<bool>,
+) -> Result<PandaHttpResponse, PandaError> {
+    let schema: PandaTomlRequest = utils::parse_toml(toml_schema, &options.variables, None)?;
+
+    run_single_request(schema, options, cancelled_rx).await
+}
+
 pub async fn run_single_request(
     req: PandaTomlRequest,
+    options: &RunOptions,
+    cancelled_rx: Receiver<bool>,
+) -> Result<PandaHttpResponse, PandaError> {
+    let retry = req.retry_config().cloned();
+    let prepared = prepare_request(req, options)?;
+
+    match retry {
+        Some(retry) => send_with_retry(prepared, &retry, cancelled_rx).await,
+        None => send_prepared(prepared, cancelled_rx).await,
+    }
+}
+
+/// Sends a prepared request until it gets a response or an error that is not retried,
+/// or until it runs out of attempts. Cancelling also interrupts the delays.
+pub async fn send_with_retry(
+    prepared: PreparedRequest,
+    retry: &RetryConfig,
     mut cancelled_rx: Receiver<bool>,
-) -> Result<PandaHttpResponse, String> {
+) -> Result<PandaHttpResponse, PandaError> {
+    let mut prepared = prepared;
+    let mut attempts = vec![];
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+
+        // Multipart bodies are streamed, they can only be sent once.
+        let next = match attempt < retry.attempts {
+            true => prepared.try_clone(),
+            false => None,
+        };
+
+        let started = Instant::now();
+        let result = send_prepared(prepared, cancelled_rx.clone()).await;
+        let elapsed_time = started.elapsed().as_secs_f64() * 1000.0;
+
+        let next = match next {
+            Some(next) if !*cancelled_rx.borrow() => next,
+            _ => return with_attempts(result, attempts),
+        };
+
+        let (status, error, headers) = match &result {
+            Ok(response) if retry.should_retry_status(response.status) => (
+                Some(response.status),
+                None,
+                response.headers.clone().unwrap_or_default(),
+            ),
+            Err(err) if retry.should_retry_error(err) => (None, Some(err.clone()), vec![]),
+            _ => return with_attempts(result, attempts),
+        };
+
+        let (delay, retry_after) = retry.delay_after(attempt, &headers);
+
+        attempts.push(RetryAttempt {
+            attempt,
+            status,
+            error,
+            elapsed_time,
+            delay: delay.as_secs_f64() * 1000.0,
+            retry_after,
+        });
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = cancelled_rx.changed() => return Ok(cancelled_response(next.resolved)),
+        }
+
+        prepared = next;
+    }
+}
+
+/// Records the retried attempts in the response, or in the message of the final error.
+fn with_attempts(
+    result: Result<PandaHttpResponse, PandaError>,
+    attempts: Vec<RetryAttempt>,
+) -> Result<PandaHttpResponse, PandaError> {
+    match result {
+        Ok(response) => Ok(PandaHttpResponse {
+            attempts,
+            ..response
+        }),
+        Err(mut err) if !attempts.is_empty() => {
+            let details = err.details_mut();
+            details.message = format!(
+                "{} (after {} attempts)",
+                details.message,
+                attempts.len() + 1
+            );
+
+            Err(err)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn cancelled_response(request: ResolvedRequest) -> PandaHttpResponse {
+    PandaHttpResponse {
+        id: Uuid::new_v4().to_string(),
+        request,
+        text_response: Some("Request was cancelled.".to_string()),
+        headers: Some(vec![]),
+        content_type: "application/text".to_string(),
+        mime_type: "text/plain".to_string(),
+        ..Default::default()
+    }
+}
+
+/// Reads the file of a multipart field, paths leaving the project root are refused so a
+/// shared request file can't upload eg. `~/.ssh/id_rsa`.
+fn read_form_file(project_root: &Path, file: &str) -> Result<Vec<u8>, String> {
+    let root = project_root
+        .canonicalize()
+        .map_err(|err| format!("Failed to read {:?}: {}", project_root, err))?;
+
+    let path = root
+        .join(file)
+        .canonicalize()
+        .map_err(|err| format!("Failed to read {:?}: {}", file, err))?;
+
+    if !path.starts_with(&root) {
+        return Err(format!("{:?} is outside of the project.", file));
+    }
+
+    fs::read(&path).map_err(|err| format!("Failed to read {:?}: {}", file, err))
+}
+
+/// A request built from a TOML file along with the client configured for it.
+pub struct PreparedRequest {
+    /// Can be reused to send the request again, connections are pooled.
+    pub client: reqwest::Client,
+    pub request: reqwest::Request,
+    pub resolved: ResolvedRequest,
+    pub follow_redirects: bool,
+    /// Host the client certificate was chosen for, if any matched.
+    identity_host: Option<String>,
+    probe: Arc<ConnectionProbe>,
+    cookie_jar: Option<Arc<PandaCookieJar>>,
+}
+
+impl PreparedRequest {
+    /// A copy sharing the client, `None` for streamed bodies eg. multipart forms.
+    pub fn try_clone(&self) -> Option<PreparedRequest> {
+        Some(PreparedRequest {
+            client: self.client.clone(),
+            request: self.request.try_clone()?,
+            resolved: self.resolved.clone(),
+            follow_redirects: self.follow_redirects,
+            identity_host: self.identity_host.clone(),
+            probe: Arc::clone(&self.probe),
+            cookie_jar: self.cookie_jar.clone(),
+        })
+    }
+    /// Sends the request following redirects if configured and returns the final
+    /// response, its body is left unread.
+    pub async fn send(self) -> Result<reqwest::Response, PandaError> {
+        let redirected = redirect::send(
+            &self.client,
+            self.request,
+            self.follow_redirects,
+            self.identity_host.as_deref(),
+            &self.probe,
+        )
+        .await?;
+
+        Ok(redirected.response)
+    }
+}
+
+/// Builds the client and the request without sending it.
+pub fn prepare_request(
+    req: PandaTomlRequest,
+    options: &RunOptions,
+) -> Result<PreparedRequest, PandaError> {
+    let probe = Arc::new(ConnectionProbe::default());
+
     let client_builder = reqwest::ClientBuilder::new()
-        .connection_verbose(true)
+        .dns_resolver(Arc::new(TimedResolver::new(Arc::clone(&probe))))
+        .connector_layer(ProbeLayer::new(Arc::clone(&probe)))
         .gzip(true)
         .brotli(true)
         .deflate(true)
         .referer(false)
-        .tls_info(true);
-
-    let client = client_builder
-        .build()
-        .expect("was unable to build client_builder");
+        .redirect(reqwest::redirect::Policy::none());
 
     let params = match req {
         PandaTomlRequest {
@@ -163,19 +634,31 @@ pub async fn run_single_request(
             method: String::from("options"),
             ..payload
         },
-        _ => return Err("Invalid or unsupported request method".to_string()),
+        _ => {
+            return Err(PandaError::Request(
+                "Invalid or unsupported request method".into(),
+            ))
+        }
     };
 
-    let url: String = params.url;
+    // Path variables the run did not set eg. `_.user_id` fall back to `[method.params]`.
+    let url: String = match params.path_params() {
+        path_params if path_params.is_empty() => params.url,
+        path_params => utils::replace_variables(&params.url, &path_params.into_iter().collect()),
+    };
     let method: String = params.method;
-    let headers: Option<Value> = params.headers;
+    let headers: Option<RequestHeaders> = params.headers;
 
     if url.is_empty() {
-        return Err("Please provide a valid request url.".to_string());
+        return Err(PandaError::Request(
+            "Please provide a valid request url.".into(),
+        ));
     };
 
     if method.is_empty() {
-        return Err("Please provide a valid request method.".to_string());
+        return Err(PandaError::Request(
+            "Please provide a valid request method.".into(),
+        ));
     };
 
     let body = (
@@ -204,33 +687,97 @@ pub async fn run_single_request(
         _ => BodyVariants::Unknown,
     };
 
-    let now = Instant::now();
-
     // Here we can run pre-request scripts
 
-    let query_url = match params.query {
-        Some(query) => {
-            let parse_object = query
-                .as_object()
-                .expect("query should be a valid json value keypair");
-
-            let mut key_value: Vec<(String, String)> = vec![];
+    let undefined_variables = utils::get_variables(&url);
 
-            for item in parse_object {
-                let key = item.0.to_string();
-                let value = item.1.to_string();
+    if !undefined_variables.is_empty() {
+        return Err(PandaError::Variable(
+            format!(
+                "Undefined variables in request url: {}",
+                undefined_variables.join(", ")
+            )
+            .into(),
+        ));
+    }
 
-                key_value.push((key, value));
+    let query_url = match params.query {
+        Some(query) => {
+            if !query.is_object() {
+                return Err(PandaError::Parse(
+                    "Request query should be a table of key value pairs.".into(),
+                ));
             }
 
-            let query_str = reqwest::Url::parse_with_params(&url, &key_value)
-                .expect("should be a valid query string.");
+            // Strings are sent without their quotes, like `RequestParams::query` reads them.
+            let key_value = table_text(Some(&query));
+
+            let query_str = match reqwest::Url::parse_with_params(&url, &key_value) {
+                Ok(q) => q,
+                Err(err) => {
+                    return Err(PandaError::Request(
+                        format!("Invalid request url: {}", err).into(),
+                    ))
+                }
+            };
 
             query_str.to_string()
         }
         None => url.to_string(),
     };
 
+    let host = match reqwest::Url::parse(&query_url) {
+        Ok(parsed) => parsed.host_str().unwrap_or_default().to_string(),
+        Err(err) => {
+            return Err(PandaError::Request(
+                format!("Invalid request url: {}", err).into(),
+            ))
+        }
+    };
+
+    let (tls_config, identity) = tls::build_tls_config(
+        &options.client_certificates,
+        &host,
+        &options.project_root,
+        &options.variables,
+        Arc::clone(&probe),
+    )
+    .map_err(|err| PandaError::Tls(err.into()))?;
+
+    let identity_host = identity.map(|_| host.clone());
+
+    let client_builder = client_builder.use_preconfigured_tls(tls_config);
+
+    let request_proxy = merge_proxy(params.proxy, options.proxy.clone());
+
+    let client_builder =
+        proxy::with_proxy(client_builder, request_proxy.as_ref(), &options.variables)
+            .map_err(|err| PandaError::Config(err.into()))?;
+
+    let cookie_jar = match params.cookie_jar {
+        Some(false) => None,
+        _ => options.cookie_jar.clone(),
+    };
+
+    let client_builder = match &cookie_jar {
+        Some(jar) => client_builder.cookie_provider(Arc::clone(jar)),
+        None => client_builder,
+    };
+
+    let client_builder = match params.timeout {
+        Some(timeout) => client_builder.timeout(Duration::from_millis(timeout)),
+        None => client_builder,
+    };
+
+    let client = match client_builder.build() {
+        Ok(c) => c,
+        Err(err) => {
+            return Err(PandaError::Request(
+                format!("Unable to build http client: {}", err).into(),
+            ))
+        }
+    };
+
     let request = match method.to_lowercase().as_str() {
         "get" => client.get(&query_url),
         "head" => client.head(&query_url),
@@ -250,7 +797,11 @@ pub async fn run_single_request(
 
             match parse_json {
                 Ok(valid_json) => request.json(&valid_json),
-                Err(msg) => return Err(msg.to_string()),
+                Err(msg) => {
+                    return Err(PandaError::Parse(
+                        format!("Invalid JSON body: {}", msg).into(),
+                    ))
+                }
             }
         }
         BodyVariants::BodyFormUrlEncoded { content } => {
@@ -266,12 +817,26 @@ pub async fn run_single_request(
             let mut form = multipart::Form::new();
 
             for item in content {
-                let key = item.field;
+                let file = match item.file {
+                    Some(file) => file,
+                    None => {
+                        form = form.text(item.field, item.value);
+                        continue;
+                    }
+                };
 
-                // try reading the value as file initially, if it fails then return the value as string or null
-                let value = item.value;
+                let bytes = read_form_file(&options.project_root, &file).map_err(|err| {
+                    PandaError::Request(format!("Field {}: {}", item.field, err).into())
+                })?;
+
+                let part = multipart::Part::bytes(bytes).file_name(
+                    Path::new(&file)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                );
 
-                form = form.text(key.clone(), value.to_string());
+                form = form.part(item.field, part);
             }
 
             request.multipart(form)
@@ -280,35 +845,14 @@ pub async fn run_single_request(
         BodyVariants::Unknown => request,
     };
 
-    let with_request_headers = match headers {
+    let with_request_headers = match &headers {
         Some(valid_headers) => {
-            let parse_headers = valid_headers.as_object().unwrap();
+            let mut reqwest_headers = valid_headers
+                .to_header_map()
+                .map_err(|err| PandaError::Request(err.into()))?;
 
-            let mut reqwest_headers: HeaderMap = HeaderMap::new();
-
-            reqwest_headers.insert("User-Agent", "Worm".parse().unwrap());
-
-            for item in parse_headers {
-                let key = item.0.to_owned();
-                let value = item.1;
-
-                if key.is_empty() {
-                    return Err("Invalid request header".into());
-                }
-
-                if value.is_number() {
-                    return Err("Invalid request value, you may have provided a number.".into());
-                }
-
-                let value = value
-                    .as_str()
-                    .expect("should expect header value to be a valid string.");
-
-                // TODO handle cases where the header key and values may be invalid.
-                reqwest_headers.insert(
-                    HeaderName::from_str(key.as_str()).unwrap(),
-                    HeaderValue::from_str(value).unwrap(),
-                );
+            if !reqwest_headers.contains_key(USER_AGENT) {
+                reqwest_headers.insert(USER_AGENT, HeaderValue::from_static("Worm"));
             }
 
             with_request_body.headers(reqwest_headers)
@@ -316,12 +860,48 @@ pub async fn run_single_request(
         None => with_request_body,
     };
 
-    let (resp_tx, resp_rx) = oneshot::channel::<Result<reqwest::Response, reqwest::Error>>();
+    let request = with_request_headers.build()?;
+
+    Ok(PreparedRequest {
+        client,
+        resolved: ResolvedRequest::from_request(&request, headers.as_ref()),
+        request,
+        follow_redirects: params.follow_redirects.unwrap_or(true),
+        identity_host,
+        probe,
+        cookie_jar,
+    })
+}
+
+/// Sends a prepared request and reads its response unless `cancelled_rx` changes first.
+pub async fn send_prepared(
+    prepared: PreparedRequest,
+    mut cancelled_rx: Receiver<bool>,
+) -> Result<PandaHttpResponse, PandaError> {
+    let PreparedRequest {
+        client,
+        request,
+        resolved: resolved_request,
+        follow_redirects,
+        identity_host,
+        probe,
+        cookie_jar,
+    } = prepared;
+
+    let (resp_tx, resp_rx) = oneshot::channel::<Result<Redirected, PandaError>>();
 
     // Send the request in a separate thread.
+    let redirect_probe = Arc::clone(&probe);
+
     tokio::spawn(async move {
-        let send_request: Result<reqwest::Response, reqwest::Error> =
-            with_request_headers.send().await;
+        let send_request = redirect::send(
+            &client,
+            request,
+            follow_redirects,
+            identity_host.as_deref(),
+            &redirect_probe,
+        )
+        .await;
 
         let _ = resp_tx.send(send_request);
     });
@@ -331,69 +911,84 @@ pub async fn run_single_request(
         Ok(r) = resp_rx => r,
         _ = cancelled_rx.changed() => {
             println!("Request cancelled");
-            return Ok(PandaHttpResponse {
-                status: 0, elapsed_time: 0,
-                text_response: Some("Request was cancelled.".to_string()),
-                headers: Some(HashMap::new()),
-                content_type: "application/text".to_string()
-            });
+            return Ok(cancelled_response(resolved_request));
         }
     };
 
     {
         // Handle the actual response for this request.
-        let response = match raw_response {
-            Ok(a) => a,
-            Err(err) => {
-                if err.is_connect() {
-                    return Err(format!("Connection error: {}", err.to_string()));
-                } else if err.is_timeout() {
-                    return Err(format!("Timeout error: {}", err.to_string()));
-                } else if err.is_status() {
-                    return Err(format!("HTTP error: {}", err.to_string()));
-                } else {
-                    return Err(format!("Other error: {}", err.to_string()));
-                };
-            }
-        };
-
-        let mut response_headers: HashMap<String, String> = HashMap::new();
+        let Redirected {
+            response,
+            sent_at,
+            headers_at,
+            hops,
+        } = raw_response?;
 
         let status = response.status().as_u16();
+        let final_url = response.url().to_string();
 
-        let headers = response.headers().clone();
-        let response_type = response.headers().clone();
-        let content_type = response_type.get(CONTENT_TYPE).unwrap();
+        let http_version = response.version();
 
-        for (k, v) in headers {
-            if let Some(valid_header) = k {
-                let key = valid_header.to_string();
+        let connection = ConnectionInfo {
+            http_version: format!("{:?}", http_version),
+            remote_ip: response.remote_addr().map(|addr| addr.ip().to_string()),
+            remote_port: response.remote_addr().map(|addr| addr.port()),
+            reused: probe.is_reused(sent_at),
+            tls: probe.get_tls_details(),
+        };
 
-                let value = v.as_bytes();
-                let value = String::from_utf8_lossy(value).to_string();
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .map(|v| body::decode_header_value(v.as_bytes()))
+            .unwrap_or_default();
 
-                response_headers.insert(key, value);
-            }
-        }
+        let response_headers = headers::read_headers(response.headers());
+
+        let response_body = match response.bytes().await {
+            Ok(bytes) => bytes.to_vec(),
+            Err(msg) => return Err(msg.into()),
+        };
 
-        let response_as_text = response.text().await;
+        let mime_type = body::detect_mime_type(&response_body, &content_type);
+        let body_kind = body::get_body_kind(&response_body, &mime_type);
 
-        let text = match response_as_text {
-            Ok(text) => text,
-            Err(msg) => return Err(msg.to_string()),
+        let (text_response, text_encoding, base64_response) = match body_kind {
+            BodyKind::Text => {
+                let (text, encoding) = body::decode_text(&response_body, &content_type);
+                (Some(text), Some(encoding), None)
+            }
+            BodyKind::Binary if response_body.len() <= INLINE_BINARY_LIMIT => {
+                (None, None, Some(STANDARD.encode(&response_body)))
+            }
+            BodyKind::Binary => (None, None, None),
         };
 
-        let elapsed_time = now.elapsed();
+        let timings = probe.get_timings(sent_at, headers_at, Instant::now());
+
+        if let Some(jar) = cookie_jar {
+            jar.save().map_err(|err| PandaError::Io(err.into()))?;
+        }
 
         let after_response = PandaHttpResponse {
+            id: Uuid::new_v4().to_string(),
+            request: resolved_request,
             status,
+            final_url,
+            elapsed_time: hops.iter().map(|hop| hop.timings.total).sum::<f64>() + timings.total,
+            redirects: hops,
+            attempts: vec![],
             headers: Some(response_headers),
-            text_response: Some(text),
-            elapsed_time: elapsed_time.as_secs(),
-            content_type: content_type
-                .to_str()
-                .expect("should be a valid content type")
-                .to_string(),
+            text_response,
+            text_encoding,
+            base64_response,
+            body_kind,
+            size: response_body.len(),
+            mime_type,
+            timings,
+            connection,
+            content_type,
+            body: response_body,
         };
 
         // Here we can run post-request scripts
@@ -0,0 +1,643 @@
+use crate::config::{add_variables, read_config};
+use crate::demo::create_collection;
+use crate::error::PandaError;
+use crate::headers::Header;
+use crate::http_runner::PandaTomlRequest;
+use crate::runner::FOLDER_CONFIG;
+use crate::utils::{encode_component, PandaCollection};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use normalize_path::NormalizePath;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Tables of the methods a request file can define.
+const METHODS: [&str; 7] = ["get", "head", "post", "put", "patch", "delete", "options"];
+
+/// A request converted from another tool, or read from a request file to be exported.
+#[derive(Clone, Debug, Default)]
+pub struct ImportedRequest {
+    pub name: String,
+    /// Lowercase, also the table of the request file eg. `post`.
+    pub method: String,
+    pub url: String,
+    /// Values of the path variables of `url`, the `[method.params]` table.
+    pub params: Vec<(String, String)>,
+    pub headers: Vec<Header>,
+    pub body: Option<ImportedBody>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportedBody {
+    Json(String),
+    Text(String),
+    Xml(String),
+    FormUrlEncoded(Vec<(String, String)>),
+    FormMultipart(Vec<(String, FormValue)>),
+}
+
+/// Value of a multipart form field.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FormValue {
+    Text(String),
+    /// Path of a file sent as the field, relative to the project root.
+    File(String),
+}
+
+#[derive(Clone, Debug)]
+pub enum ImportedItem {
+    Folder {
+        name: String,
+        items: Vec<ImportedItem>,
+    },
+    Request(ImportedRequest),
+}
+
+/// A collection converted from another tool, ready to be written to the project.
+#[derive(Clone, Debug, Default)]
+pub struct Imported {
+    pub name: String,
+    pub items: Vec<ImportedItem>,
+    pub variables: BTreeMap<String, String>,
+    /// Variables by environment name.
+    pub environments: BTreeMap<String, BTreeMap<String, String>>,
+    /// What could not be converted, eg. scripts.
+    pub warnings: Vec<String>,
+}
+
+/// Formats `cmd_import_collection` reads.
+#[derive(Deserialize, Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    /// Postman collection v2.0 or v2.1.
+    Postman,
+    /// Insomnia v4 export, JSON or YAML.
+    Insomnia,
+    /// OpenAPI 3.0 or 3.1 specification, JSON or YAML.
+    Openapi,
+}
+
+/// Result of an import, shown to the user.
+#[derive(Deserialize, Clone, Debug, Default, Serialize)]
+pub struct ImportSummary {
+    /// Folder containing the imported requests.
+    pub path: String,
+    pub folders: usize,
+    pub requests: usize,
+    pub variables: usize,
+    pub environments: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// `post` for `POST`, `None` when request files can't define the method.
+pub fn method_table(method: &str) -> Option<&'static str> {
+    let method = method.to_lowercase();
+
+    METHODS.into_iter().find(|m| *m == method)
+}
+
+impl ImportedRequest {
+    /// Reads a request file as written, the query table is appended to the url percent
+    /// encoded like the runner sends it.
+    pub fn from_toml(request: &PandaTomlRequest, file_name: &str) -> Option<ImportedRequest> {
+        let (method, params) = request.params()?;
+        let mut url = params.url().to_string();
+
+        for (key, value) in params.query() {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            let _ = write!(
+                url,
+                "{}{}={}",
+                separator,
+                encode_component(&key),
+                encode_component(&value)
+            );
+        }
+
+        Some(ImportedRequest {
+            name: params
+                .name()
+                .map(str::to_string)
+                .unwrap_or_else(|| file_name.trim_end_matches(".toml").to_string()),
+            method: method.to_lowercase(),
+            url,
+            params: params.path_params(),
+            headers: params.headers().to_vec(),
+            body: params.body(),
+        })
+    }
+
+    /// The request file, `order` keeps the position the request had in the source.
+    pub fn to_toml(&self, order: Option<usize>) -> String {
+        let mut toml = String::new();
+        let method = &self.method;
+
+        if let Some(order) = order {
+            let _ = writeln!(toml, "order = {}\n", order);
+        }
+
+        let _ = writeln!(toml, "[{}]", method);
+        let _ = writeln!(toml, "name = {}", string(&self.name));
+        let _ = writeln!(toml, "url = {}", string(&self.url));
+
+        if !self.params.is_empty() {
+            let _ = writeln!(toml, "\n[{}.params]", method);
+
+            for (name, value) in &self.params {
+                let _ = writeln!(toml, "{} = {}", key(name), string(value));
+            }
+        }
+
+        if !self.headers.is_empty() {
+            let _ = writeln!(toml, "\n[{}.headers]", method);
+
+            // Repeated headers are written once with an array of values.
+            let mut names: Vec<&str> = vec![];
+
+            for header in &self.headers {
+                if !names.iter().any(|n| n.eq_ignore_ascii_case(&header.name)) {
+                    names.push(&header.name);
+                }
+            }
+
+            for name in names {
+                let values: Vec<String> = self
+                    .headers
+                    .iter()
+                    .filter(|h| h.name.eq_ignore_ascii_case(name))
+                    .map(|h| string(&h.value))
+                    .collect();
+
+                match values.len() {
+                    1 => {
+                        let _ = writeln!(toml, "{} = {}", key(name), values[0]);
+                    }
+                    _ => {
+                        let _ = writeln!(toml, "{} = [{}]", key(name), values.join(", "));
+                    }
+                }
+            }
+        }
+
+        let (table, content) = match &self.body {
+            None => return toml,
+            Some(ImportedBody::Json(content)) => ("json", string(content)),
+            Some(ImportedBody::Text(content)) => ("text", string(content)),
+            Some(ImportedBody::Xml(content)) => ("xml", string(content)),
+            Some(ImportedBody::FormUrlEncoded(fields)) => ("form_urlencoded", form(fields)),
+            Some(ImportedBody::FormMultipart(fields)) => ("form_multipart", multipart(fields)),
+        };
+
+        let _ = writeln!(toml, "\n[{}.{}]", method, table);
+        let _ = writeln!(toml, "content = {}", content);
+
+        toml
+    }
+}
+
+/// `Basic dXNlcjpwYXNz`, `None` when the credentials contain templates as they are
+/// encoded before variables can be resolved.
+pub fn basic_auth(username: &str, password: &str) -> Option<String> {
+    let credentials = format!("{}:{}", username, password);
+
+    if credentials.contains("{{") {
+        return None;
+    }
+
+    Some(format!("Basic {}", STANDARD.encode(credentials)))
+}
+
+/// `GET users/1` for a request without a name, or `GET example.com` for the root.
+pub fn request_name(method: &str, url: &str) -> String {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let without_query = without_scheme
+        .split(['?', '#'])
+        .next()
+        .unwrap_or(without_scheme);
+
+    let (host, path) = without_query.split_once('/').unwrap_or((without_query, ""));
+
+    let path = match path.trim_matches('/') {
+        "" => host,
+        path => path,
+    };
+
+    format!("{} {}", method.to_uppercase(), path)
+}
+
+/// Fields of an urlencoded body, read as the query of an url.
+pub fn form_fields(text: &str) -> Vec<(String, String)> {
+    Url::parse(&format!("http://localhost/?{}", text))
+        .map(|url| url.query_pairs().into_owned().collect())
+        .unwrap_or_default()
+}
+
+/// A TOML string, multi-line when it contains line breaks.
+fn string(value: &str) -> String {
+    toml::Value::String(value.to_string()).to_string()
+}
+
+/// A bare key when possible eg. `Content-Type`, a quoted one otherwise.
+fn key(name: &str) -> String {
+    let is_bare = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    match is_bare {
+        true => name.to_string(),
+        false => string(name),
+    }
+}
+
+fn form(fields: &[(String, String)]) -> String {
+    let mut content = String::from("[\n");
+
+    for (field, value) in fields {
+        let _ = writeln!(
+            content,
+            "    {{ field = {}, value = {} }},",
+            string(field),
+            string(value)
+        );
+    }
+
+    content.push(']');
+    content
+}
+
+fn multipart(fields: &[(String, FormValue)]) -> String {
+    let mut content = String::from("[\n");
+
+    for (field, value) in fields {
+        let _ = match value {
+            FormValue::Text(value) => writeln!(
+                content,
+                "    {{ field = {}, value = {} }},",
+                string(field),
+                string(value)
+            ),
+            FormValue::File(file) => writeln!(
+                content,
+                "    {{ field = {}, file = {} }},",
+                string(field),
+                string(file)
+            ),
+        };
+    }
+
+    content.push(']');
+    content
+}
+
+/// Converts `{{name}}` templates to `_.name` variables.
+///
+/// Variable names can only contain letters and a single underscore so other names are
+/// renamed eg. `api-key` becomes `api_key` and `x-api-key` `x_apiKey`, renames and clashes
+/// are reported.
+#[derive(Default)]
+pub struct Templates {
+    /// Converted names to the original ones.
+    names: BTreeMap<String, String>,
+    pub warnings: Vec<String>,
+}
+
+impl Templates {
+    pub fn name(&mut self, original: &str) -> String {
+        let original = original.trim();
+        let words: Vec<&str> = original
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .filter(|w| !w.is_empty())
+            .collect();
+
+        let name = match words.as_slice() {
+            [] => "var".to_string(),
+            [word] => word.to_string(),
+            [first, second] => format!("{}_{}", first, second),
+            [first, rest @ ..] => {
+                let rest: String = rest.iter().map(|w| capitalize(w)).collect();
+                format!("{}_{}", first, rest)
+            }
+        };
+
+        match self.names.get(&name) {
+            Some(existing) if existing != original => self.warnings.push(format!(
+                "Variables {:?} and {:?} are both named _.{}.",
+                existing, original, name
+            )),
+            Some(_) => {}
+            None => {
+                if name != original {
+                    self.warnings.push(format!(
+                        "Variable {:?} was renamed to _.{}.",
+                        original, name
+                    ));
+                }
+
+                self.names.insert(name.clone(), original.to_string());
+            }
+        }
+
+        name
+    }
+
+    /// Replaces the templates of `text`, dynamic ones eg. `{{$guid}}` are kept as is.
+    pub fn convert(&mut self, text: &str) -> String {
+        let mut converted = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(start) = rest.find("{{") {
+            let end = match rest[start..].find("}}") {
+                Some(end) => start + end,
+                None => break,
+            };
+
+            let inner = &rest[start + 2..end];
+            converted.push_str(&rest[..start]);
+
+            if inner.trim_start().starts_with('$') || inner.contains('{') {
+                self.warnings.push(format!(
+                    "Dynamic variable {{{{{}}}}} is not supported.",
+                    inner.trim()
+                ));
+                converted.push_str(&rest[start..end + 2]);
+            } else {
+                let name = self.name(inner);
+                let _ = write!(converted, "_.{}", name);
+
+                // `_.hostname` can't be told apart from `_.host` followed by `name`.
+                if rest[end + 2..]
+                    .chars()
+                    .next()
+                    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                {
+                    self.warnings.push(format!(
+                        "Variable _.{} is directly followed by text in {:?}, add a separator.",
+                        name, text
+                    ));
+                }
+            }
+
+            rest = &rest[end + 2..];
+        }
+
+        converted.push_str(rest);
+        converted
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+/// Keeps characters that are valid in file names on every platform.
+fn file_name(name: &str, fallback: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => '-',
+            c => c,
+        })
+        .collect();
+
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+
+    match cleaned.is_empty() {
+        true => fallback.to_string(),
+        false => cleaned.to_string(),
+    }
+}
+
+/// Appends ` 2`, ` 3`... to names already used in the same folder, case insensitively.
+fn unique(name: String, extension: &str, used: &mut HashSet<String>) -> String {
+    let mut candidate = format!("{}{}", name, extension);
+    let mut n = 2;
+
+    while !used.insert(candidate.to_lowercase()) {
+        candidate = format!("{} {}{}", name, n, extension);
+        n += 1;
+    }
+
+    candidate
+}
+
+/// Converts items to the tree written by `create_collection`, folders and requests keep
+/// their position with an `order`.
+fn to_collection(
+    items: &[ImportedItem],
+    parent: &str,
+    summary: &mut ImportSummary,
+) -> Vec<PandaCollection> {
+    let mut used = HashSet::from([FOLDER_CONFIG.to_string()]);
+    let mut collection = vec![];
+
+    for (order, item) in items.iter().enumerate() {
+        match item {
+            ImportedItem::Folder { name, items } => {
+                let name = unique(file_name(name, "folder"), "", &mut used);
+                let path = format!("{}/{}", parent, name);
+
+                let mut children = vec![file(FOLDER_CONFIG, &path, format!("order = {}\n", order))];
+                children.extend(to_collection(items, &path, summary));
+
+                summary.folders += 1;
+
+                collection.push(PandaCollection {
+                    id: Uuid::new_v4().to_string(),
+                    name,
+                    path,
+                    content: None,
+                    children: Some(children),
+                    is_selectable: false,
+                    item_type: "folder".to_string(),
+                });
+            }
+            ImportedItem::Request(request) => {
+                let name = unique(file_name(&request.name, "request"), ".toml", &mut used);
+
+                summary.requests += 1;
+
+                collection.push(file(&name, parent, request.to_toml(Some(order))));
+            }
+        }
+    }
+
+    collection
+}
+
+fn file(name: &str, parent: &str, content: String) -> PandaCollection {
+    PandaCollection {
+        id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        path: format!("{}/{}", parent, name),
+        content: Some(content),
+        children: None,
+        is_selectable: true,
+        item_type: "file".to_string(),
+    }
+}
+
+/// Writes an imported collection to a new folder named after it, inside `into` or the
+/// project collection, and adds its variables to `panda.config.json`.
+pub fn write_import(
+    project_root: &Path,
+    imported: Imported,
+    into: Option<&Path>,
+) -> Result<ImportSummary, PandaError> {
+    let config = read_config(&project_root.to_string_lossy())?;
+
+    let parent = match into {
+        Some(into) => into.to_path_buf(),
+        None => project_root.join(&config.collection).normalize(),
+    };
+
+    let folder = file_name(&imported.name, "imported");
+    let destination = parent.join(&folder);
+
+    if destination.exists() {
+        return Err(PandaError::Io(
+            format!(
+                "{:?} already exists, rename it or import into another folder.",
+                destination
+            )
+            .into(),
+        ));
+    }
+
+    let mut summary = ImportSummary {
+        path: destination.to_string_lossy().to_string(),
+        variables: imported.variables.len(),
+        environments: imported.environments.keys().cloned().collect(),
+        warnings: imported.warnings,
+        ..Default::default()
+    };
+
+    let root = PandaCollection {
+        id: Uuid::new_v4().to_string(),
+        name: folder.clone(),
+        path: folder.clone(),
+        content: None,
+        children: Some(to_collection(&imported.items, &folder, &mut summary)),
+        is_selectable: false,
+        item_type: "folder".to_string(),
+    };
+
+    create_collection(&vec![root], &parent)?;
+
+    let environments = imported
+        .environments
+        .iter()
+        .map(|(name, variables)| (Some(name.as_str()), variables));
+
+    for (environment, variables) in [(None, &imported.variables)]
+        .into_iter()
+        .chain(environments)
+    {
+        if variables.is_empty() {
+            continue;
+        }
+
+        for name in add_variables(project_root, environment, variables)? {
+            summary.warnings.push(format!(
+                "Variable {} already has another value in {}, it was kept.",
+                name,
+                environment
+                    .map(|e| format!("environment {}", e))
+                    .unwrap_or_else(|| "the project".to_string())
+            ));
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Writes a single request file inside `into` or the project collection, next to the
+/// existing files.
+pub fn write_request(
+    project_root: &Path,
+    request: &ImportedRequest,
+    warnings: Vec<String>,
+    into: Option<&Path>,
+) -> Result<ImportSummary, PandaError> {
+    let config = read_config(&project_root.to_string_lossy())?;
+
+    let folder = match into {
+        Some(into) => into.to_path_buf(),
+        None => project_root.join(&config.collection).normalize(),
+    };
+
+    fs::create_dir_all(&folder)
+        .map_err(|err| PandaError::Io(format!("Failed to create {:?}: {}", folder, err).into()))?;
+
+    let mut used: HashSet<String> = fs::read_dir(&folder)
+        .map_err(|err| PandaError::Io(format!("Failed to read {:?}: {}", folder, err).into()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_lowercase())
+        .collect();
+
+    let name = unique(file_name(&request.name, "request"), ".toml", &mut used);
+    let path = folder.join(name);
+
+    fs::write(&path, request.to_toml(None))
+        .map_err(|err| PandaError::Io(format!("Failed to write {:?}: {}", path, err).into()))?;
+
+    Ok(ImportSummary {
+        path: path.to_string_lossy().to_string(),
+        requests: 1,
+        warnings,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_and_reads_multipart_files() {
+        let body = ImportedBody::FormMultipart(vec![
+            ("name".into(), FormValue::Text("Ann \"A\"".into())),
+            ("avatar".into(), FormValue::File("files/avatar.png".into())),
+        ]);
+
+        let request = ImportedRequest {
+            name: "upload".into(),
+            method: "post".into(),
+            url: "https://example.com/upload".into(),
+            body: Some(body.clone()),
+            ..Default::default()
+        };
+
+        let toml = request.to_toml(None);
+        assert!(toml.contains(r#"{ field = "avatar", file = "files/avatar.png" }"#));
+
+        let parsed: PandaTomlRequest = toml::from_str(&toml).unwrap();
+        let read = ImportedRequest::from_toml(&parsed, "upload.toml").unwrap();
+
+        assert_eq!(read.body, Some(body));
+    }
+
+    #[test]
+    fn encodes_the_query_table() {
+        let parsed: PandaTomlRequest = toml::from_str(
+            "[get]\nurl = \"https://example.com/search?page=1\"\n\n[get.query]\nq = \"tom & jerry\"\nlimit = 10\ntoken = \"_.API_TOKEN\"\n",
+        )
+        .unwrap();
+        let read = ImportedRequest::from_toml(&parsed, "search.toml").unwrap();
+
+        assert_eq!(
+            read.url,
+            "https://example.com/search?page=1&limit=10&q=tom%20%26%20jerry&token=_.API_TOKEN"
+        );
+    }
+}
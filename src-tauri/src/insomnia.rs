@@ -0,0 +1,763 @@
+use crate::error::{ErrorDetails, PandaError, SourceLocation};
+use crate::headers::Header;
+use crate::import::{
+    basic_auth, method_table, FormValue, Imported, ImportedBody, ImportedItem, ImportedRequest,
+    Templates,
+};
+use fancy_regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// The `_.` of `{{ _.base_url }}`, Insomnia also accepts `{{ base_url }}`.
+static VARIABLE_PREFIX_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*_\.").unwrap());
+
+/// An Insomnia v4 export, every workspace, folder, request and environment is a resource
+/// pointing to its parent.
+#[derive(Deserialize, Debug)]
+pub struct Export {
+    #[serde(rename = "__export_format")]
+    pub format: Option<u32>,
+    #[serde(default)]
+    pub resources: Vec<Resource>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Resource {
+    #[serde(rename = "_id")]
+    pub id: String,
+    /// `workspace`, `request_group`, `request`, `environment`...
+    #[serde(rename = "_type")]
+    pub kind: String,
+    #[serde(rename = "parentId")]
+    pub parent_id: Option<String>,
+    pub name: String,
+    #[serde(rename = "metaSortKey")]
+    pub sort_key: Option<f64>,
+    pub method: Option<String>,
+    pub url: Option<String>,
+    pub headers: Vec<Pair>,
+    /// Query parameters.
+    pub parameters: Vec<Pair>,
+    pub body: Option<Body>,
+    pub authentication: Option<Auth>,
+    /// Variables of an environment.
+    pub data: Option<Value>,
+    /// Variables of a folder.
+    pub environment: Option<Value>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Pair {
+    pub name: String,
+    pub value: Value,
+    pub disabled: bool,
+    /// `file` for multipart files.
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    #[serde(rename = "fileName")]
+    pub file_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Body {
+    #[serde(rename = "mimeType")]
+    pub mime_type: Option<String>,
+    pub text: Option<String>,
+    pub params: Vec<Pair>,
+    #[serde(rename = "fileName")]
+    pub file_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Auth {
+    /// Empty or missing when the parent folder's auth is used.
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub disabled: bool,
+    pub token: Option<String>,
+    pub prefix: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub key: Option<String>,
+    pub value: Option<String>,
+    /// `header`, `queryParams` or `cookie` for API keys.
+    #[serde(rename = "addTo")]
+    pub add_to: Option<String>,
+}
+
+/// Reads a JSON or YAML export.
+pub fn read_export(path: &Path) -> Result<Export, PandaError> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| PandaError::Io(format!("Failed to read {:?}: {}", path, err).into()))?;
+
+    let file = path.to_string_lossy().to_string();
+    let is_yaml = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("yaml") || e.eq_ignore_ascii_case("yml"));
+
+    let export: Export = match is_yaml {
+        true => serde_yaml::from_str(&content).map_err(|err| {
+            let message = format!("Not an Insomnia export: {}", err);
+
+            PandaError::Parse(match SourceLocation::from_yaml(&err, Some(file.clone())) {
+                Some(location) => ErrorDetails::at(message, location),
+                None => message.into(),
+            })
+        })?,
+        false => serde_json::from_str(&content).map_err(|err| {
+            PandaError::Parse(ErrorDetails::at(
+                format!("Not an Insomnia export: {}", err),
+                SourceLocation::from_json(&err, Some(file)),
+            ))
+        })?,
+    };
+
+    match export.format {
+        Some(4) => Ok(export),
+        Some(format) => Err(PandaError::Parse(
+            format!(
+                "Insomnia export format {} is not supported, export the data as v4.",
+                format
+            )
+            .into(),
+        )),
+        None => Err(PandaError::Parse(
+            "Not an Insomnia v4 export, `__export_format` is missing.".into(),
+        )),
+    }
+}
+
+/// Converts an export, each workspace becomes a folder when there are several of them.
+///
+/// Base environments become project variables and their sub environments Panda
+/// environments, `{{ _.var }}` templates become `_.var` variables.
+pub fn convert(export: Export, fallback_name: &str) -> Imported {
+    let mut converter = Converter::new(&export.resources);
+
+    let workspaces: Vec<&Resource> = export
+        .resources
+        .iter()
+        .filter(|r| r.kind == "workspace")
+        .collect();
+
+    let (name, items) = match workspaces.as_slice() {
+        [workspace] => (
+            workspace.name.clone(),
+            converter.items(&workspace.id, None, ""),
+        ),
+        // Resources whose parent is not in the export, eg. a single folder.
+        [] => {
+            let ids: HashSet<&str> = export.resources.iter().map(|r| r.id.as_str()).collect();
+            let mut roots: Vec<String> = export
+                .resources
+                .iter()
+                .filter_map(|r| r.parent_id.clone())
+                .filter(|parent| !ids.contains(parent.as_str()))
+                .collect();
+            let mut seen = HashSet::new();
+            roots.retain(|root| seen.insert(root.clone()));
+
+            let items = roots
+                .iter()
+                .flat_map(|root| converter.items(root, None, ""))
+                .collect();
+
+            (fallback_name.to_string(), items)
+        }
+        workspaces => {
+            let items = workspaces
+                .iter()
+                .map(|workspace| ImportedItem::Folder {
+                    name: workspace.name.clone(),
+                    items: converter.items(&workspace.id, None, &workspace.name),
+                })
+                .collect();
+
+            (fallback_name.to_string(), items)
+        }
+    };
+
+    let mut variables = BTreeMap::new();
+    let mut environments: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+
+    for workspace in &workspaces {
+        for base in converter.children(&workspace.id, "environment") {
+            let data = converter.environment(base.data.as_ref());
+            variables.extend(data);
+
+            for environment in converter.children(&base.id, "environment") {
+                let data = converter.environment(environment.data.as_ref());
+                environments
+                    .entry(environment.name.clone())
+                    .or_default()
+                    .extend(data);
+            }
+        }
+    }
+
+    // Panda has no folder variables, they are shared by the whole project.
+    for (folder, data) in std::mem::take(&mut converter.folder_variables) {
+        for (name, value) in data {
+            if variables
+                .get(&name)
+                .is_some_and(|existing| *existing != value)
+            {
+                converter.warnings.push(format!(
+                    "{} sets variable {} which already has another value, it was skipped.",
+                    folder, name
+                ));
+                continue;
+            }
+
+            variables.insert(name, value);
+        }
+    }
+
+    let mut warnings = converter.warnings;
+    warnings.extend(converter.templates.warnings);
+
+    let mut seen = HashSet::new();
+    warnings.retain(|warning| seen.insert(warning.clone()));
+
+    Imported {
+        name,
+        items,
+        variables,
+        environments,
+        warnings,
+    }
+}
+
+struct Converter<'a> {
+    /// Resources by parent id, sorted as in Insomnia.
+    children: HashMap<&'a str, Vec<&'a Resource>>,
+    templates: Templates,
+    /// Variables of each folder that has some, by folder path.
+    folder_variables: Vec<(String, BTreeMap<String, String>)>,
+    warnings: Vec<String>,
+}
+
+impl<'a> Converter<'a> {
+    fn new(resources: &'a [Resource]) -> Self {
+        let mut children: HashMap<&str, Vec<&Resource>> = HashMap::new();
+
+        for resource in resources {
+            if let Some(parent) = &resource.parent_id {
+                children.entry(parent.as_str()).or_default().push(resource);
+            }
+        }
+
+        for siblings in children.values_mut() {
+            siblings.sort_by(|a, b| {
+                let a = a.sort_key.unwrap_or_default();
+                let b = b.sort_key.unwrap_or_default();
+
+                a.total_cmp(&b)
+            });
+        }
+
+        Converter {
+            children,
+            templates: Templates::default(),
+            folder_variables: vec![],
+            warnings: vec![],
+        }
+    }
+
+    fn children(&self, parent: &str, kind: &str) -> Vec<&'a Resource> {
+        self.children
+            .get(parent)
+            .map(|children| {
+                children
+                    .iter()
+                    .copied()
+                    .filter(|child| child.kind == kind)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn items(&mut self, parent: &str, auth: Option<&'a Auth>, path: &str) -> Vec<ImportedItem> {
+        let children = self.children.get(parent).cloned().unwrap_or_default();
+        let mut items = vec![];
+
+        for child in children {
+            let path = match path {
+                "" => child.name.clone(),
+                path => format!("{}/{}", path, child.name),
+            };
+
+            let auth = match &child.authentication {
+                Some(own)
+                    if !own.kind.as_deref().unwrap_or_default().is_empty()
+                        && own.kind.as_deref() != Some("inherit") =>
+                {
+                    Some(own)
+                }
+                _ => auth,
+            };
+
+            match child.kind.as_str() {
+                "request_group" => {
+                    let variables = self.environment(child.environment.as_ref());
+
+                    if !variables.is_empty() {
+                        self.warnings.push(format!(
+                            "{} has folder variables, they were added to the project.",
+                            path
+                        ));
+                        self.folder_variables.push((path.clone(), variables));
+                    }
+
+                    items.push(ImportedItem::Folder {
+                        name: child.name.clone(),
+                        items: self.items(&child.id, auth, &path),
+                    });
+                }
+                "request" => {
+                    if let Some(request) = self.request(child, auth, &path) {
+                        items.push(ImportedItem::Request(request));
+                    }
+                }
+                "grpc_request" | "websocket_request" | "socketio_request" => {
+                    self.warnings.push(format!(
+                        "{} is a {} and was skipped.",
+                        path,
+                        child.kind.replace('_', " ")
+                    ));
+                }
+                "unit_test_suite" => self
+                    .warnings
+                    .push(format!("{} is a test suite and was skipped.", path)),
+                _ => {}
+            }
+        }
+
+        items
+    }
+
+    fn request(
+        &mut self,
+        resource: &Resource,
+        auth: Option<&Auth>,
+        path: &str,
+    ) -> Option<ImportedRequest> {
+        let method = resource.method.as_deref().unwrap_or("GET");
+
+        let method = match method_table(method) {
+            Some(method) => method.to_string(),
+            None => {
+                self.warnings
+                    .push(format!("{} uses method {} and was skipped.", path, method));
+                return None;
+            }
+        };
+
+        let mut url = resource.url.clone().unwrap_or_default();
+
+        for parameter in resource.parameters.iter().filter(|p| !p.disabled) {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            url = format!(
+                "{}{}{}={}",
+                url,
+                separator,
+                parameter.name,
+                text(&parameter.value)
+            );
+        }
+
+        let mut headers: Vec<(String, String)> = resource
+            .headers
+            .iter()
+            .filter(|h| !h.disabled && !h.name.is_empty())
+            .map(|h| (h.name.clone(), text(&h.value)))
+            .collect();
+
+        if let Some(auth) = auth.filter(|auth| !auth.disabled) {
+            self.auth(auth, &mut headers, &mut url, path);
+        }
+
+        let headers = headers
+            .into_iter()
+            .map(|(name, value)| Header {
+                name: self.text(&name, path),
+                value: self.text(&value, path),
+            })
+            .collect();
+
+        let body = match &resource.body {
+            Some(body) => self.body(body, path),
+            None => None,
+        };
+
+        Some(ImportedRequest {
+            name: resource.name.clone(),
+            method,
+            url: self.text(&url, path),
+            params: vec![],
+            headers,
+            body,
+        })
+    }
+
+    /// Adds the header or query parameter the auth of the request sends.
+    fn auth(
+        &mut self,
+        auth: &Auth,
+        headers: &mut Vec<(String, String)>,
+        url: &mut String,
+        path: &str,
+    ) {
+        let mut header = |name: &str, value: String| {
+            if !headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name)) {
+                headers.push((name.to_string(), value));
+            }
+        };
+
+        let kind = auth.kind.as_deref().unwrap_or_default();
+
+        match kind {
+            "" | "none" | "inherit" => {}
+            "bearer" => {
+                let prefix = auth
+                    .prefix
+                    .as_deref()
+                    .filter(|p| !p.is_empty())
+                    .unwrap_or("Bearer");
+                let token = auth.token.as_deref().unwrap_or_default();
+
+                header("Authorization", format!("{} {}", prefix, token));
+            }
+            "basic" => {
+                let username = auth.username.as_deref().unwrap_or_default();
+                let password = auth.password.as_deref().unwrap_or_default();
+
+                match basic_auth(username, password) {
+                    Some(value) => header("Authorization", value),
+                    None => self.warnings.push(format!(
+                        "{} uses basic auth with variables, set the Authorization header manually.",
+                        path
+                    )),
+                }
+            }
+            "apikey" => {
+                let key = auth.key.as_deref().unwrap_or_default();
+                let value = auth.value.as_deref().unwrap_or_default();
+
+                match auth.add_to.as_deref() {
+                    Some("queryParams") => {
+                        let separator = if url.contains('?') { '&' } else { '?' };
+                        *url = format!("{}{}{}={}", url, separator, key, value);
+                    }
+                    Some("cookie") => header("Cookie", format!("{}={}", key, value)),
+                    _ => header(key, value.to_string()),
+                }
+            }
+            kind => self.warnings.push(format!(
+                "{} uses {} auth which is not supported.",
+                path, kind
+            )),
+        }
+    }
+
+    fn body(&mut self, body: &Body, path: &str) -> Option<ImportedBody> {
+        let mime_type = body.mime_type.as_deref().unwrap_or_default().to_lowercase();
+
+        let fields = |converter: &mut Self, params: &[Pair]| -> Vec<(String, FormValue)> {
+            let mut fields = vec![];
+
+            for param in params.iter().filter(|p| !p.disabled) {
+                let value = match param.kind.as_deref() {
+                    Some("file") => {
+                        let file = param.file_name.clone().unwrap_or_default();
+
+                        converter.warnings.push(format!(
+                            "{} sends file {:?} as field {}, copy it to the project.",
+                            path, file, param.name
+                        ));
+
+                        FormValue::File(file)
+                    }
+                    _ => FormValue::Text(converter.text(&text(&param.value), path)),
+                };
+
+                fields.push((converter.text(&param.name, path), value));
+            }
+
+            fields
+        };
+
+        if mime_type == "application/x-www-form-urlencoded" {
+            // Urlencoded forms can't send files, their path is sent as text.
+            let fields = fields(self, &body.params).into_iter().map(|(name, value)| {
+                let (FormValue::Text(value) | FormValue::File(value)) = value;
+                (name, value)
+            });
+
+            return Some(ImportedBody::FormUrlEncoded(fields.collect()));
+        }
+
+        if mime_type == "multipart/form-data" {
+            return Some(ImportedBody::FormMultipart(fields(self, &body.params)));
+        }
+
+        if mime_type == "application/octet-stream" || body.file_name.is_some() {
+            self.warnings.push(format!(
+                "{} sends a file as its body which is not supported.",
+                path
+            ));
+            return None;
+        }
+
+        let content = self.text(body.text.as_deref().filter(|t| !t.is_empty())?, path);
+
+        // GraphQL bodies are stored as the JSON that is sent.
+        if mime_type.contains("json") || mime_type == "application/graphql" {
+            Some(ImportedBody::Json(content))
+        } else if mime_type.contains("xml") {
+            Some(ImportedBody::Xml(content))
+        } else {
+            Some(ImportedBody::Text(content))
+        }
+    }
+
+    /// Variables of an environment, nested objects are flattened eg. `{{ _.api.key }}`
+    /// becomes `_.api_key`.
+    fn environment(&mut self, data: Option<&Value>) -> BTreeMap<String, String> {
+        let mut flat = vec![];
+
+        if let Some(Value::Object(data)) = data {
+            flatten("", data, &mut flat);
+        }
+
+        flat.into_iter()
+            .map(|(key, value)| {
+                let name = self.templates.name(&key);
+                let value = self.text(&value, "An environment");
+
+                (name, value)
+            })
+            .collect()
+    }
+
+    /// Converts variables and reports template tags eg. `{% uuid %}`, they are kept as is.
+    fn text(&mut self, text: &str, path: &str) -> String {
+        let mut rest = text;
+
+        while let Some(start) = rest.find("{%") {
+            let end = rest[start..].find("%}").map(|end| start + end + 2);
+            let tag = &rest[start + 2..end.map(|end| end - 2).unwrap_or(rest.len())];
+            let name = tag.split_whitespace().next().unwrap_or_default();
+
+            self.warnings.push(format!(
+                "{} uses template tag {:?} which is not supported.",
+                path, name
+            ));
+
+            rest = &rest[end.unwrap_or(rest.len())..];
+        }
+
+        let text = VARIABLE_PREFIX_REGEX.replace_all(text, "{{");
+
+        self.templates.convert(&text)
+    }
+}
+
+fn flatten(prefix: &str, data: &serde_json::Map<String, Value>, flat: &mut Vec<(String, String)>) {
+    for (key, value) in data {
+        let key = match prefix {
+            "" => key.clone(),
+            prefix => format!("{}.{}", prefix, key),
+        };
+
+        match value {
+            Value::Object(object) => flatten(&key, object, flat),
+            value => flat.push((key, text(value))),
+        }
+    }
+}
+
+/// Values are usually strings, YAML exports can hold numbers and booleans.
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn export(resources: Value) -> Export {
+        serde_json::from_value(json!({ "__export_format": 4, "resources": resources })).unwrap()
+    }
+
+    fn names(items: &[ImportedItem]) -> Vec<String> {
+        items
+            .iter()
+            .map(|item| match item {
+                ImportedItem::Folder { name, items } => {
+                    format!("{}/ [{}]", name, names(items).join(", "))
+                }
+                ImportedItem::Request(request) => request.name.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nests_groups_in_sort_order() {
+        let imported = convert(
+            export(json!([
+                { "_id": "wrk_1", "_type": "workspace", "name": "Shop" },
+                { "_id": "fld_1", "_type": "request_group", "parentId": "wrk_1", "name": "Orders", "metaSortKey": -10,
+                  "authentication": { "type": "bearer", "token": "{{ _.token }}" } },
+                { "_id": "fld_2", "_type": "request_group", "parentId": "fld_1", "name": "Refunds", "metaSortKey": 1 },
+                { "_id": "req_1", "_type": "request", "parentId": "fld_2", "name": "Refund", "method": "POST",
+                  "url": "{{ _.base_url }}/refunds" },
+                { "_id": "req_2", "_type": "request", "parentId": "fld_1", "name": "List orders", "metaSortKey": 0,
+                  "url": "{{base_url}}/orders", "parameters": [
+                      { "name": "page", "value": "1" },
+                      { "name": "draft", "value": "true", "disabled": true },
+                  ],
+                  "authentication": { "type": "inherit" } },
+                { "_id": "req_3", "_type": "request", "parentId": "wrk_1", "name": "Health", "metaSortKey": 5,
+                  "url": "{{ _.base_url }}/health" },
+                { "_id": "ws_1", "_type": "websocket_request", "parentId": "wrk_1", "name": "Live" },
+            ])),
+            "fallback",
+        );
+
+        assert_eq!(imported.name, "Shop");
+        assert_eq!(
+            names(&imported.items),
+            ["Orders/ [List orders, Refunds/ [Refund]]", "Health"]
+        );
+
+        let orders = match &imported.items[0] {
+            ImportedItem::Folder { items, .. } => items,
+            item => panic!("{:?}", item),
+        };
+        let list = match &orders[0] {
+            ImportedItem::Request(request) => request,
+            item => panic!("{:?}", item),
+        };
+
+        assert_eq!(list.method, "get");
+        assert_eq!(list.url, "_.base_url/orders?page=1");
+        assert_eq!(
+            list.headers,
+            [Header {
+                name: "Authorization".into(),
+                value: "Bearer _.token".into(),
+            }]
+        );
+        assert_eq!(
+            imported.warnings,
+            ["Live is a websocket request and was skipped."]
+        );
+    }
+
+    #[test]
+    fn puts_several_workspaces_in_folders() {
+        let imported = convert(
+            export(json!([
+                { "_id": "wrk_1", "_type": "workspace", "name": "Shop" },
+                { "_id": "wrk_2", "_type": "workspace", "name": "Admin" },
+                { "_id": "req_1", "_type": "request", "parentId": "wrk_1", "name": "Cart", "url": "https://shop.test" },
+                { "_id": "req_2", "_type": "request", "parentId": "wrk_2", "name": "Users", "url": "https://admin.test" },
+            ])),
+            "insomnia",
+        );
+
+        assert_eq!(imported.name, "insomnia");
+        assert_eq!(names(&imported.items), ["Shop/ [Cart]", "Admin/ [Users]"]);
+    }
+
+    #[test]
+    fn flattens_environments() {
+        let imported = convert(
+            export(json!([
+                { "_id": "wrk_1", "_type": "workspace", "name": "Shop" },
+                { "_id": "env_1", "_type": "environment", "parentId": "wrk_1", "name": "Base",
+                  "data": { "base_url": "https://shop.test", "api": { "key": "k3y", "version": 2 } } },
+                { "_id": "env_2", "_type": "environment", "parentId": "env_1", "name": "Staging",
+                  "data": { "base_url": "https://staging.shop.test", "auth": { "url": "{{ _.base_url }}/auth" } } },
+                { "_id": "fld_1", "_type": "request_group", "parentId": "wrk_1", "name": "Orders",
+                  "environment": { "page": { "size": 50 } } },
+            ])),
+            "fallback",
+        );
+
+        assert_eq!(
+            imported.variables,
+            BTreeMap::from([
+                ("api_key".to_string(), "k3y".to_string()),
+                ("api_version".to_string(), "2".to_string()),
+                ("base_url".to_string(), "https://shop.test".to_string()),
+                ("page_size".to_string(), "50".to_string()),
+            ])
+        );
+        assert_eq!(
+            imported.environments["Staging"],
+            BTreeMap::from([
+                ("auth_url".to_string(), "_.base_url/auth".to_string()),
+                (
+                    "base_url".to_string(),
+                    "https://staging.shop.test".to_string()
+                ),
+            ])
+        );
+        assert!(imported
+            .warnings
+            .contains(&"Orders has folder variables, they were added to the project.".to_string()));
+    }
+
+    #[test]
+    fn reports_template_tags() {
+        let imported = convert(
+            export(json!([
+                { "_id": "wrk_1", "_type": "workspace", "name": "Shop" },
+                { "_id": "req_1", "_type": "request", "parentId": "wrk_1", "name": "Create", "method": "POST",
+                  "url": "https://shop.test/orders",
+                  "headers": [{ "name": "X-Request-Id", "value": "{% uuid 'v4' %}" }],
+                  "body": { "mimeType": "application/json",
+                            "text": "{\"at\": \"{% now 'iso-8601' %}\", \"id\": \"{% uuid 'v4' %}\"}" } },
+            ])),
+            "fallback",
+        );
+
+        assert_eq!(
+            imported.warnings,
+            [
+                "Create uses template tag \"uuid\" which is not supported.",
+                "Create uses template tag \"now\" which is not supported.",
+            ]
+        );
+
+        let request = match &imported.items[0] {
+            ImportedItem::Request(request) => request,
+            item => panic!("{:?}", item),
+        };
+
+        // Tags are kept so they can be replaced by hand.
+        assert_eq!(request.headers[0].value, "{% uuid 'v4' %}");
+        assert_eq!(
+            request.body,
+            Some(ImportedBody::Json(
+                "{\"at\": \"{% now 'iso-8601' %}\", \"id\": \"{% uuid 'v4' %}\"}".into()
+            ))
+        );
+    }
+}
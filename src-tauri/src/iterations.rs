@@ -0,0 +1,180 @@
+use crate::error::{ErrorDetails, PandaError, SourceLocation};
+use crate::utils::is_variable_name;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// One row of a data file, column names to values.
+pub type DataRow = HashMap<String, String>;
+
+/// Reads the rows of a CSV file with a header line or of a JSON array of objects.
+///
+/// Each column is exposed as a `_.` variable, names that can't be written as one eg.
+/// `user_id2` or `first_last_name` are rejected.
+pub fn read_data_file(path: &Path) -> Result<Vec<DataRow>, PandaError> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| PandaError::Io(format!("Failed to read {:?}: {}", path, err).into()))?;
+
+    let file = path.to_string_lossy().to_string();
+
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(e) if e.eq_ignore_ascii_case("csv") => read_csv(&content, file),
+        Some(e) if e.eq_ignore_ascii_case("json") => read_json(&content, file),
+        _ => Err(PandaError::Config(
+            format!(
+                "Unsupported data file {:?}, use a .csv or .json file.",
+                path
+            )
+            .into(),
+        )),
+    }
+}
+
+fn read_csv(content: &str, file: String) -> Result<Vec<DataRow>, PandaError> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+
+    let csv_error = |err: csv::Error| {
+        let message = format!("Invalid CSV: {}", err);
+
+        match err.position() {
+            Some(position) => PandaError::Parse(ErrorDetails::at(
+                message,
+                SourceLocation {
+                    file: Some(file.clone()),
+                    line: position.line() as usize,
+                    column: 1,
+                },
+            )),
+            None => PandaError::Parse(message.into()),
+        }
+    };
+
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(csv_error)?
+        .iter()
+        .map(|h| h.trim().to_string())
+        .collect();
+
+    if let Some(header) = headers.iter().find(|h| !is_variable_name(h)) {
+        return Err(PandaError::Parse(ErrorDetails::at(
+            invalid_column(header),
+            SourceLocation {
+                file: Some(file),
+                line: 1,
+                column: 1,
+            },
+        )));
+    }
+
+    let mut rows = vec![];
+
+    for record in reader.records() {
+        let record = record.map_err(csv_error)?;
+
+        rows.push(
+            headers
+                .iter()
+                .cloned()
+                .zip(record.iter().map(|v| v.to_string()))
+                .collect(),
+        );
+    }
+
+    Ok(rows)
+}
+
+fn read_json(content: &str, file: String) -> Result<Vec<DataRow>, PandaError> {
+    let value: Value = serde_json::from_str(content).map_err(|err| {
+        PandaError::Parse(ErrorDetails::at(
+            format!("Invalid JSON: {}", err),
+            SourceLocation::from_json(&err, Some(file.clone())),
+        ))
+    })?;
+
+    let items = match value {
+        Value::Array(items) => items,
+        _ => {
+            return Err(PandaError::Parse(
+                format!("{} should contain an array of objects.", file).into(),
+            ))
+        }
+    };
+
+    let mut rows = vec![];
+
+    for (index, item) in items.into_iter().enumerate() {
+        let object = match item {
+            Value::Object(o) => o,
+            _ => {
+                return Err(PandaError::Parse(
+                    format!("Item {} of {} is not an object.", index, file).into(),
+                ))
+            }
+        };
+
+        if let Some(key) = object.keys().find(|key| !is_variable_name(key)) {
+            return Err(PandaError::Parse(
+                format!("Item {} of {}: {}", index, file, invalid_column(key)).into(),
+            ));
+        }
+
+        // Same conversion as the variables sent by the UI.
+        rows.push(
+            object
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Value::String(s) => s,
+                        value => value.to_string(),
+                    };
+
+                    (key, value)
+                })
+                .collect(),
+        );
+    }
+
+    Ok(rows)
+}
+
+fn invalid_column(name: &str) -> String {
+    format!(
+        "Column {:?} can't be used as a variable, use letters with at most one underscore eg. `EMAIL` or `USER_NAME`.",
+        name
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_csv_and_json_rows() {
+        let rows = read_csv(
+            "EMAIL, USER_NAME\na@x.io,\"Ann \"\"A\"\"\"\n",
+            "users.csv".into(),
+        )
+        .unwrap();
+
+        assert_eq!(rows[0]["EMAIL"], "a@x.io");
+        assert_eq!(rows[0]["USER_NAME"], "Ann \"A\"");
+
+        let rows = read_json(r#"[{"ID": 1, "ROLE": "admin"}]"#, "roles.json".into()).unwrap();
+
+        assert_eq!(rows[0]["ID"], "1");
+        assert_eq!(rows[0]["ROLE"], "admin");
+    }
+
+    #[test]
+    fn rejects_columns_that_are_not_variable_names() {
+        for column in ["user_id2", "first_last_name", "_NAME", "NAME_", "E-MAIL"] {
+            let csv = read_csv(&format!("EMAIL,{}\na,b\n", column), "users.csv".into());
+            assert!(csv.is_err(), "{}", column);
+
+            let json = read_json(&format!(r#"[{{"{}": "b"}}]"#, column), "users.json".into());
+            assert!(json.is_err(), "{}", column);
+        }
+    }
+}
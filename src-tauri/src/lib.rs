@@ -1,33 +1,55 @@
+pub mod bench;
+pub mod body;
+pub mod cli;
+pub mod codegen;
 pub mod command;
+pub mod config;
+pub mod cookies;
+pub mod curl;
 pub mod demo;
+pub mod error;
+pub mod har;
+pub mod headers;
+pub mod history;
+pub mod html_report;
 pub mod http_runner;
+pub mod import;
+pub mod insomnia;
+pub mod iterations;
+pub mod openapi;
+pub mod postman;
+pub mod proxy;
+pub mod redirect;
+pub mod report;
+pub mod retry;
+pub mod runner;
+pub mod snapshot;
+pub mod timings;
+pub mod tls;
 pub mod utils;
 
-use anyhow::{Context, Result as AnyResult};
+use body::ResponseStore;
 use clap::Parser;
 use clap_derive::Subcommand;
+use cli::{
+    BenchArgs, CodegenArgs, CookieCommands, ExportCommands, HistoryCommands, ImportCommands,
+    RunArgs, TestArgs,
+};
+use config::{read_config, PANDA_CONFIG};
 use demo::{create_collection, get_demo_collection};
+use error::PandaError;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::env::{current_dir, set_current_dir};
-use std::fs::{self, File};
-use std::io::Write;
+use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Mutex;
 use tauri::{self, Builder, Manager, Runtime, State};
 use utils::{get_collection_from_path, normalise_path, PandaCollection};
 
-#[derive(Serialize, Deserialize, Debug, Default)]
-struct PandaConfig {
-    name: String,
-    version: String,
-    /// The user defined directory relative to the `panda.config.json`
-    collection: String,
-}
-
 #[derive(Serialize, Deserialize, Default)]
-struct AppData {
+pub struct AppData {
     /// The app could be started using cli
     // gui_mode: &'static str,
     gui_mode: String,
@@ -41,8 +63,6 @@ struct JsError {
     message: &'static str,
 }
 
-const PANDA_CONFIG: &str = "panda.config.json";
-
 /// Simple cli interface used to pass collection path like vscode.
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -62,6 +82,50 @@ enum Commands {
         /// Name of the project to initialize.
         project_name: String,
     },
+    /// Manages the project's cookie jar.
+    Cookies {
+        /// Environment whose cookies are managed.
+        #[arg(long)]
+        env: Option<String>,
+
+        #[command(subcommand)]
+        action: CookieCommands,
+    },
+    /// Lists, replays and prunes the requests sent from the project.
+    History {
+        #[command(subcommand)]
+        action: HistoryCommands,
+    },
+    /// Converts collections of other tools to request files.
+    Import {
+        #[command(subcommand)]
+        action: ImportCommands,
+    },
+    /// Converts requests to the collection formats of other tools.
+    Export {
+        #[command(subcommand)]
+        action: ExportCommands,
+    },
+    /// Runs the requests of a file or folder in order, captured variables carry forward.
+    Run {
+        #[command(flatten)]
+        args: RunArgs,
+    },
+    /// Runs the requests of a file or folder, optionally comparing responses with snapshots.
+    Test {
+        #[command(flatten)]
+        args: TestArgs,
+    },
+    /// Sends requests repeatedly from concurrent workers and reports latencies.
+    Bench {
+        #[command(flatten)]
+        args: BenchArgs,
+    },
+    /// Prints code sending a request with curl, HTTPie, Python, JavaScript, Go or Rust.
+    Codegen {
+        #[command(flatten)]
+        args: CodegenArgs,
+    },
 }
 #[tauri::command]
 async fn cmd_get_app_state(state: State<'_, Mutex<AppData>>) -> Result<AppData, String> {
@@ -77,85 +141,63 @@ async fn cmd_get_app_state(state: State<'_, Mutex<AppData>>) -> Result<AppData,
 fn cmd_get_collections<R: Runtime>(
     config_path: String,
     app: tauri::AppHandle<R>,
-) -> AnyResult<Vec<PandaCollection>, String> {
+) -> Result<Vec<PandaCollection>, PandaError> {
     let state = app.state::<Mutex<AppData>>();
     let mut app_state = state.lock().unwrap();
 
     let config_path = normalise_path(config_path.clone());
 
     if config_path.is_empty() {
-        return Err("You provided an empty string for cwd.".into());
+        return Err(PandaError::Config(
+            "You provided an empty string for cwd.".into(),
+        ));
     }
 
-    if config_path.to_lowercase().ends_with(PANDA_CONFIG) {
+    let project_root = if config_path.to_lowercase().ends_with(PANDA_CONFIG) {
         // C:/Users/DELL/Desktop/Panda collections/panda.config.json
-        let config = match fs::read_to_string(config_path.clone())
-            .with_context(|| format!("Failed to read config: {:?}", config_path))
-        {
-            Ok(c) => c,
-            Err(err) => return Err(err.to_string()),
-        };
-
-        let config_content: PandaConfig =
-            serde_json::from_str(&config).expect("should have been able to read config.");
-
-        let project_root = Path::new(config_path.as_str())
-            .parent()
-            .expect("unable to strip config from path")
-            .to_str()
-            .expect("unable to extract the parent path from config path");
-
-        // Update app cwd
-        app_state.cwd = project_root.to_string();
-
-        // Here we read the collection defined in config and assume it is relative to `panda.config.json`
-        let relative_collection = normalise_path(format!(
-            "{}/{}",
-            project_root.to_string(),
-            config_content.collection
-        ));
-
-        let _temp: Vec<PandaCollection> = vec![];
-
-        let collections = get_collection_from_path(relative_collection, _temp);
-
-        if let Err(er) = collections {
-            return Err(er.to_string());
+        match Path::new(config_path.as_str()).parent() {
+            Some(parent) => normalise_path(parent.to_string_lossy().to_string()),
+            None => {
+                return Err(PandaError::Config(
+                    format!("Invalid config path: {:?}", config_path).into(),
+                ))
+            }
         }
-
-        Ok(collections.unwrap())
     } else {
         // C:/Users/DELL/Desktop/Panda collections
-        let relative_config = format!("{}/{}", config_path, PANDA_CONFIG);
-
         // Here we assume the user picked the folder containing `"panda.config.json"`
-        let config = match fs::read_to_string(relative_config.clone())
-            .with_context(|| format!("Invalid project directory: {:?}", config_path))
-        {
-            Ok(c) => c,
-            Err(err) => return Err(err.to_string()),
-        };
+        config_path
+    };
 
-        // Update app cwd
-        app_state.cwd = config_path.clone();
+    let config_content = read_config(&project_root)?;
 
-        let config_content: PandaConfig =
-            serde_json::from_str(&config).expect("should have been able to read config.");
+    // Update app cwd
+    app_state.cwd = project_root.clone();
 
-        // TODO normalise path
-        let read_collection_relative_to_config =
-            normalise_path(format!("{}/{}", config_path, config_content.collection));
+    // Here we read the collection defined in config and assume it is relative to `panda.config.json`
+    let relative_collection =
+        normalise_path(format!("{}/{}", project_root, config_content.collection));
 
-        let _temp: Vec<PandaCollection> = vec![];
+    get_collection_from_path(relative_collection, vec![])
+}
 
-        let collections = get_collection_from_path(read_collection_relative_to_config, _temp);
+/// Creates the demo collection and `panda.config.json` in the current directory.
+fn init_project(project_name: &str) -> Result<(), PandaError> {
+    let collection = get_demo_collection();
 
-        if let Err(er) = collections {
-            return Err(er.to_string());
-        }
+    let base_path = Path::new(".");
+    create_collection(&collection, base_path)?;
 
-        Ok(collections.unwrap())
-    }
+    // Use `serde_json::json!` to create structured JSON and format it with `to_string_pretty`
+    let panda_config = serde_json::to_string_pretty(&json!({
+        "name": project_name,
+        "version": "v0.0.1",
+        "collection": "./collection"
+    }))
+    .map_err(|err| PandaError::Config(format!("Failed to serialize config: {}", err).into()))?;
+
+    fs::write(PANDA_CONFIG, panda_config.as_bytes())
+        .map_err(|err| PandaError::Io(format!("Failed to write {}: {}", PANDA_CONFIG, err).into()))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -165,36 +207,103 @@ pub async fn run() {
     if let Some(command) = args.cmd {
         match command {
             Commands::Init { project_name } => {
-                let collection = get_demo_collection();
-
-                let base_path = Path::new(".");
-                create_collection(&collection, base_path);
-
-                // Use `serde_json::json!` to create structured JSON and format it with `to_string_pretty`
-                let panda_config = serde_json::to_string_pretty(&json!({
-                    "name": project_name,
-                    "version": "v0.0.1",
-                    "collection": "./collection"
-                }))
-                .expect("Failed to serialize config");
-
-                let mut config_file =
-                    File::create("panda.config.json").expect("Failed to create config file");
-                config_file
-                    .write_all(panda_config.as_bytes())
-                    .expect("Failed to write config file");
+                if let Err(err) = init_project(&project_name) {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
 
                 println!(
                     "Successfully initialized the project: {:?} 🎉",
                     project_name
                 );
 
+                return;
+            }
+            Commands::Cookies { env, action } => {
+                let project_root = args.path.unwrap_or_else(|| PathBuf::from("."));
+
+                if let Err(err) = cli::run_cookies(&project_root, env.as_deref(), action) {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+
+                return;
+            }
+            Commands::History { action } => {
+                let project_root = args.path.unwrap_or_else(|| PathBuf::from("."));
+
+                if let Err(err) = cli::run_history(&project_root, action).await {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+
+                return;
+            }
+            Commands::Import { action } => {
+                let project_root = args.path.unwrap_or_else(|| PathBuf::from("."));
+
+                if let Err(err) = cli::run_import(&project_root, action) {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+
+                return;
+            }
+            Commands::Export { action } => {
+                let project_root = args.path.unwrap_or_else(|| PathBuf::from("."));
+
+                if let Err(err) = cli::run_export(&project_root, action) {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+
+                return;
+            }
+            Commands::Run { args: run_args } => {
+                let project_root = args.path.unwrap_or_else(|| PathBuf::from("."));
+
+                if let Err(err) = cli::run_requests(&project_root, run_args).await {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+
+                return;
+            }
+            Commands::Test { args: test_args } => {
+                let project_root = args.path.unwrap_or_else(|| PathBuf::from("."));
+
+                if let Err(err) = cli::run_test(&project_root, test_args).await {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+
+                return;
+            }
+            Commands::Bench { args: bench_args } => {
+                let project_root = args.path.unwrap_or_else(|| PathBuf::from("."));
+
+                if let Err(err) = cli::run_bench(&project_root, bench_args).await {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+
+                return;
+            }
+            Commands::Codegen { args: codegen_args } => {
+                let project_root = args.path.unwrap_or_else(|| PathBuf::from("."));
+
+                if let Err(err) = cli::run_codegen(&project_root, codegen_args) {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+
                 return;
             }
         };
     }
 
     let builder = Builder::default()
+        .manage(ResponseStore::default())
         .setup(|app| {
             #[cfg(desktop)]
             let _ = app
@@ -208,21 +317,39 @@ pub async fn run() {
         .plugin(tauri_plugin_dialog::init())
         .invoke_handler(tauri::generate_handler![
             command::cmd_http_request,
+            command::cmd_get_cookies,
+            command::cmd_set_cookie,
+            command::cmd_delete_cookie,
+            command::cmd_clear_cookies,
+            command::cmd_read_response_body,
+            command::cmd_save_response_body,
+            command::cmd_list_history,
+            command::cmd_get_history_entry,
+            command::cmd_replay_history,
+            command::cmd_prune_history,
+            command::cmd_export_history_har,
+            command::cmd_run_collection,
+            command::cmd_import_collection,
+            command::cmd_import_har,
+            command::cmd_import_curl,
+            command::cmd_generate_code,
             cmd_get_app_state,
             cmd_get_collections,
         ]);
 
     let builder = if let Some(path) = args.path {
         if !path.is_dir() {
-            panic!("Project root directory is required! {:?}", path)
+            eprintln!("Project root directory is required! {:?}", path);
+            std::process::exit(1);
         };
 
-        let cli_path = path.to_str().unwrap();
-        let root = Path::new(cli_path);
-        set_current_dir(&root).expect(format!("Invalid project root: {:?}", &root).as_str());
-
-        let cwd = current_dir().unwrap();
-        let folder = cwd.to_string_lossy().to_string();
+        let folder = match set_current_dir(&path).and_then(|_| current_dir()) {
+            Ok(cwd) => cwd.to_string_lossy().to_string(),
+            Err(err) => {
+                eprintln!("Invalid project root {:?}: {}", path, err);
+                std::process::exit(1);
+            }
+        };
 
         builder.setup(move |app| {
             app.manage(Mutex::new(AppData {
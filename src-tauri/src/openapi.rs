@@ -0,0 +1,1108 @@
+use crate::error::{ErrorDetails, PandaError, SourceLocation};
+use crate::headers::Header;
+use crate::import::{FormValue, Imported, ImportedBody, ImportedItem, ImportedRequest, Templates};
+use normalize_path::NormalizePath;
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Methods of a path item, in the order they are imported.
+const METHODS: [&str; 8] = [
+    "get", "post", "put", "patch", "delete", "head", "options", "trace",
+];
+
+/// Nesting after which generated examples stop, recursive schemas end earlier.
+const MAX_DEPTH: usize = 10;
+
+/// An OpenAPI 3.x specification and the local files its `$ref`s point to.
+///
+/// Documents are kept as YAML values, unlike `serde_json::Value` they keep the order of
+/// paths and properties.
+pub struct Spec {
+    path: PathBuf,
+    documents: HashMap<PathBuf, Value>,
+}
+
+pub fn read_spec(path: &Path) -> Result<Spec, PandaError> {
+    let path = path.normalize();
+    let document = read_document(&path)?;
+
+    let version = document
+        .get("openapi")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+
+    if !version.starts_with("3.") {
+        let message = match document.get("swagger") {
+            Some(_) => "Swagger 2.0 specifications are not supported, convert it to OpenAPI 3.",
+            None => "Not an OpenAPI 3 specification, the `openapi` version is missing.",
+        };
+
+        return Err(PandaError::Parse(message.into()));
+    }
+
+    Ok(Spec {
+        documents: HashMap::from([(path.clone(), document)]),
+        path,
+    })
+}
+
+/// Reads a JSON or YAML document, JSON is deserialized to a YAML value to keep its order.
+fn read_document(path: &Path) -> Result<Value, PandaError> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| PandaError::Io(format!("Failed to read {:?}: {}", path, err).into()))?;
+
+    let file = path.to_string_lossy().to_string();
+    let is_json = path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+
+    match is_json {
+        true => serde_json::from_str(&content).map_err(|err| {
+            PandaError::Parse(ErrorDetails::at(
+                format!("Invalid JSON: {}", err),
+                SourceLocation::from_json(&err, Some(file)),
+            ))
+        }),
+        false => serde_yaml::from_str(&content).map_err(|err| {
+            let message = format!("Invalid YAML: {}", err);
+
+            PandaError::Parse(match SourceLocation::from_yaml(&err, Some(file)) {
+                Some(location) => ErrorDetails::at(message, location),
+                None => message.into(),
+            })
+        }),
+    }
+}
+
+/// Converts a specification, a folder per tag and a request per operation.
+///
+/// Requests use `_.base_url` set from the first server, other servers become
+/// environments. Path parameters are variables with an example in `[method.params]`,
+/// required query, header and cookie parameters are added with an example value.
+pub fn convert(spec: Spec) -> Imported {
+    let root = spec.documents[&spec.path].clone();
+    let root_path = spec.path.clone();
+    let mut converter = Converter {
+        spec,
+        templates: Templates::default(),
+        variables: BTreeMap::new(),
+        warnings: vec![],
+    };
+
+    let environments = converter.servers(&root);
+
+    // Folders follow the top level tags, then the order tags are first used in.
+    let mut tags: Vec<String> = root
+        .get("tags")
+        .and_then(Value::as_sequence)
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|tag| tag.get("name")?.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut tagged: HashMap<String, Vec<ImportedItem>> = HashMap::new();
+    let mut untagged = vec![];
+
+    let paths = root
+        .get("paths")
+        .and_then(Value::as_mapping)
+        .cloned()
+        .unwrap_or_default();
+
+    for (path, item) in &paths {
+        let path = match path.as_str() {
+            Some(path) => path,
+            None => continue,
+        };
+
+        let (item, doc) = converter.resolve(item, &root_path);
+
+        for method in METHODS {
+            let operation = match item.get(method) {
+                Some(operation) => operation,
+                None => continue,
+            };
+
+            let request = match converter.operation(path, method, &item, operation, &root, &doc) {
+                Some(request) => ImportedItem::Request(request),
+                None => continue,
+            };
+
+            let tag = operation
+                .get("tags")
+                .and_then(Value::as_sequence)
+                .and_then(|tags| tags.first())
+                .and_then(Value::as_str);
+
+            match tag {
+                Some(tag) => {
+                    if !tags.iter().any(|t| t == tag) {
+                        tags.push(tag.to_string());
+                    }
+
+                    tagged.entry(tag.to_string()).or_default().push(request);
+                }
+                None => untagged.push(request),
+            }
+        }
+    }
+
+    let mut items: Vec<ImportedItem> = tags
+        .into_iter()
+        .filter_map(|tag| {
+            let items = tagged.remove(&tag)?;
+
+            Some(ImportedItem::Folder { name: tag, items })
+        })
+        .collect();
+    items.extend(untagged);
+
+    let name = root
+        .get("info")
+        .and_then(|info| info.get("title"))
+        .and_then(Value::as_str)
+        .unwrap_or("OpenAPI")
+        .to_string();
+
+    let mut warnings = converter.warnings;
+    warnings.extend(converter.templates.warnings);
+
+    let mut seen = HashSet::new();
+    warnings.retain(|warning| seen.insert(warning.clone()));
+
+    Imported {
+        name,
+        items,
+        variables: converter.variables,
+        environments,
+        warnings,
+    }
+}
+
+struct Converter {
+    spec: Spec,
+    templates: Templates,
+    variables: BTreeMap<String, String>,
+    warnings: Vec<String>,
+}
+
+impl Converter {
+    /// Sets `base_url` from the first server, every server becomes an environment when
+    /// there are several.
+    fn servers(&mut self, root: &Value) -> BTreeMap<String, BTreeMap<String, String>> {
+        let servers = root
+            .get("servers")
+            .and_then(Value::as_sequence)
+            .cloned()
+            .unwrap_or_default();
+
+        let mut environments = BTreeMap::new();
+
+        for (i, server) in servers.iter().enumerate() {
+            let mut url = server
+                .get("url")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+
+            if let Some(variables) = server.get("variables").and_then(Value::as_mapping) {
+                for (name, variable) in variables {
+                    let name = name.as_str().unwrap_or_default();
+                    let default = variable.get("default").map(text).unwrap_or_default();
+
+                    url = url.replace(&format!("{{{}}}", name), &default);
+                }
+            }
+
+            let url = url.trim_end_matches('/').to_string();
+
+            if i == 0 {
+                if !url.contains("://") {
+                    self.warnings.push(format!(
+                        "Server {:?} is relative, set _.base_url to an absolute url.",
+                        url
+                    ));
+                }
+
+                self.variables.insert("base_url".to_string(), url.clone());
+            }
+
+            if servers.len() > 1 {
+                let name = server
+                    .get("description")
+                    .and_then(Value::as_str)
+                    .filter(|d| !d.trim().is_empty())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| host(&url));
+
+                environments.insert(name, BTreeMap::from([("base_url".to_string(), url)]));
+            }
+        }
+
+        if servers.is_empty() {
+            self.warnings
+                .push("The specification has no servers, set _.base_url.".to_string());
+            self.variables.insert("base_url".to_string(), String::new());
+        }
+
+        environments
+    }
+
+    fn operation(
+        &mut self,
+        path: &str,
+        method: &str,
+        item: &Value,
+        operation: &Value,
+        root: &Value,
+        doc: &Path,
+    ) -> Option<ImportedRequest> {
+        let name = operation
+            .get("summary")
+            .or_else(|| operation.get("operationId"))
+            .and_then(Value::as_str)
+            .filter(|name| !name.trim().is_empty())
+            .map(|name| name.trim().to_string())
+            .unwrap_or_else(|| format!("{} {}", method.to_uppercase(), path));
+
+        if method == "trace" {
+            self.warnings
+                .push(format!("{} uses method TRACE and was skipped.", name));
+            return None;
+        }
+
+        if operation.get("servers").is_some() || item.get("servers").is_some() {
+            self.warnings.push(format!(
+                "{} has its own servers, the request uses _.base_url.",
+                name
+            ));
+        }
+
+        // `{id}` becomes `_.id`, with the example of the parameter in `[method.params]`.
+        let url = format!(
+            "{{{{base_url}}}}{}",
+            path.replace('{', "{{").replace('}', "}}")
+        );
+        let mut url = self.templates.convert(&url);
+
+        let mut params = vec![];
+        let mut query = vec![];
+        let mut headers = vec![];
+        let mut cookies = vec![];
+
+        for (param, doc) in self.parameters(item, operation, doc) {
+            let param_name = match param.get("name").and_then(Value::as_str) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+
+            let location = param.get("in").and_then(Value::as_str).unwrap_or_default();
+            let required = param.get("required").and_then(Value::as_bool) == Some(true);
+            let (value, explicit) = self.parameter_example(&param, &doc);
+
+            match location {
+                "path" => params.push((self.templates.name(&param_name), value)),
+                _ if !required && !explicit => {}
+                "query" => query.push(format!("{}={}", encode(&param_name), encode(&value))),
+                // Set from the request body and the security schemes.
+                "header"
+                    if ["accept", "content-type", "authorization"]
+                        .contains(&param_name.to_lowercase().as_str()) => {}
+                "header" => headers.push(Header {
+                    name: param_name,
+                    value,
+                }),
+                "cookie" => cookies.push(format!("{}={}", param_name, value)),
+                _ => {}
+            }
+        }
+
+        let body = match operation.get("requestBody") {
+            Some(body) => self.request_body(body, doc, &name, &mut headers),
+            None => None,
+        };
+
+        self.security(
+            operation,
+            root,
+            &name,
+            &mut headers,
+            &mut query,
+            &mut cookies,
+        );
+
+        if !query.is_empty() {
+            url = format!("{}?{}", url, query.join("&"));
+        }
+
+        if !cookies.is_empty() {
+            headers.push(Header {
+                name: "Cookie".to_string(),
+                value: cookies.join("; "),
+            });
+        }
+
+        Some(ImportedRequest {
+            name,
+            method: method.to_string(),
+            url,
+            params,
+            headers,
+            body,
+        })
+    }
+
+    /// Parameters of the path item overridden by the ones of the operation, with the
+    /// document each one comes from.
+    fn parameters(&mut self, item: &Value, operation: &Value, doc: &Path) -> Vec<(Value, PathBuf)> {
+        let mut parameters: Vec<(Value, PathBuf)> = vec![];
+
+        for source in [item, operation] {
+            let list = source
+                .get("parameters")
+                .and_then(Value::as_sequence)
+                .cloned()
+                .unwrap_or_default();
+
+            for param in &list {
+                let (param, param_doc) = self.resolve(param, doc);
+                let key = |p: &Value| (p.get("name").cloned(), p.get("in").cloned());
+
+                parameters.retain(|(existing, _)| key(existing) != key(&param));
+                parameters.push((param, param_doc));
+            }
+        }
+
+        parameters
+    }
+
+    /// The example of a parameter as text, `true` when the specification gives one.
+    fn parameter_example(&mut self, param: &Value, doc: &Path) -> (String, bool) {
+        if let Some(example) = param.get("example") {
+            return (text(example), true);
+        }
+
+        if let Some(example) = self.first_example(param.get("examples"), doc) {
+            return (text(&example), true);
+        }
+
+        let schema = match param.get("schema") {
+            Some(schema) => schema.clone(),
+            None => return (String::new(), false),
+        };
+
+        let (resolved, _) = self.resolve(&schema, doc);
+        let explicit = ["example", "examples", "default", "enum"]
+            .iter()
+            .any(|key| resolved.get(*key).is_some());
+
+        (text(&self.example(&schema, doc, 0, &mut vec![])), explicit)
+    }
+
+    /// The value of the first entry of an `examples` map.
+    fn first_example(&mut self, examples: Option<&Value>, doc: &Path) -> Option<Value> {
+        let (_, example) = examples?.as_mapping()?.iter().next()?;
+        let (example, _) = self.resolve(example, doc);
+
+        example.get("value").cloned()
+    }
+
+    fn request_body(
+        &mut self,
+        body: &Value,
+        doc: &Path,
+        name: &str,
+        headers: &mut Vec<Header>,
+    ) -> Option<ImportedBody> {
+        let (body, doc) = self.resolve(body, doc);
+        let content = body.get("content")?.as_mapping()?;
+
+        let media_types: Vec<&str> = content.keys().filter_map(Value::as_str).collect();
+
+        // JSON first, then forms, then whatever the specification lists first.
+        let preferred = [
+            |m: &str| m == "application/json",
+            |m: &str| m.contains("json"),
+            |m: &str| m == "application/x-www-form-urlencoded",
+            |m: &str| m == "multipart/form-data",
+            |m: &str| m.contains("xml"),
+            |m: &str| m.starts_with("text/"),
+        ];
+
+        let media_type = preferred
+            .iter()
+            .find_map(|matches| media_types.iter().find(|m| matches(m)))
+            .or_else(|| media_types.first())?
+            .to_string();
+        let media = content.get(media_type.as_str())?.clone();
+
+        let schema = media.get("schema").cloned().unwrap_or(Value::Null);
+
+        let example = match media.get("example") {
+            Some(example) => example.clone(),
+            None => match self.first_example(media.get("examples"), &doc) {
+                Some(example) => example,
+                None => self.example(&schema, &doc, 0, &mut vec![]),
+            },
+        };
+
+        let content_type = Header {
+            name: "Content-Type".to_string(),
+            value: media_type.clone(),
+        };
+
+        match media_type.as_str() {
+            "application/x-www-form-urlencoded" => {
+                Some(ImportedBody::FormUrlEncoded(fields(&example)))
+            }
+            "multipart/form-data" => {
+                let (resolved, _) = self.resolve(&schema, &doc);
+                let properties = resolved
+                    .get("properties")
+                    .and_then(Value::as_mapping)
+                    .cloned()
+                    .unwrap_or_default();
+
+                let mut files = vec![];
+
+                for (field, property) in &properties {
+                    let (property, _) = self.resolve(property, &doc);
+                    let format = property.get("format").and_then(Value::as_str);
+
+                    if matches!(format, Some("binary") | Some("base64")) {
+                        self.warnings.push(format!(
+                            "{} sends file {}, set the path of a project file.",
+                            name,
+                            text(field)
+                        ));
+                        files.push(text(field));
+                    }
+                }
+
+                // File fields start with their name as path, examples can't hold files.
+                let fields = fields(&example).into_iter().map(|(field, value)| {
+                    match files.contains(&field) {
+                        true => (field.clone(), FormValue::File(field)),
+                        false => (field, FormValue::Text(value)),
+                    }
+                });
+
+                Some(ImportedBody::FormMultipart(fields.collect()))
+            }
+            media if media.contains("json") => {
+                headers.push(content_type);
+
+                let json = serde_json::to_string_pretty(&example).unwrap_or_default();
+
+                Some(ImportedBody::Json(json))
+            }
+            media if media.contains("xml") => match example {
+                Value::String(xml) => {
+                    headers.push(content_type);
+                    Some(ImportedBody::Xml(xml))
+                }
+                _ => {
+                    self.warnings.push(format!(
+                        "{} has an XML body without an example, it was left out.",
+                        name
+                    ));
+                    None
+                }
+            },
+            media if media.starts_with("text/") => {
+                headers.push(content_type);
+                Some(ImportedBody::Text(text(&example)))
+            }
+            media => {
+                self.warnings.push(format!(
+                    "{} has a {} body which is not supported.",
+                    name, media
+                ));
+                None
+            }
+        }
+    }
+
+    /// Applies the first security requirement of the operation, or of the specification
+    /// when the operation has none. Credentials are variables named after the schemes.
+    fn security(
+        &mut self,
+        operation: &Value,
+        root: &Value,
+        name: &str,
+        headers: &mut Vec<Header>,
+        query: &mut Vec<String>,
+        cookies: &mut Vec<String>,
+    ) {
+        let requirement = operation
+            .get("security")
+            .or_else(|| root.get("security"))
+            .and_then(Value::as_sequence)
+            .and_then(|requirements| requirements.first())
+            .and_then(Value::as_mapping)
+            .cloned()
+            .unwrap_or_default();
+
+        for scheme_name in requirement.keys().filter_map(Value::as_str) {
+            let scheme = root
+                .get("components")
+                .and_then(|c| c.get("securitySchemes"))
+                .and_then(|s| s.get(scheme_name))
+                .cloned();
+
+            let scheme = match scheme {
+                Some(scheme) => self.resolve(&scheme, &self.spec.path.clone()).0,
+                None => {
+                    self.warnings.push(format!(
+                        "{} uses security scheme {} which is not defined.",
+                        name, scheme_name
+                    ));
+                    continue;
+                }
+            };
+
+            let variable = self.templates.name(scheme_name);
+            self.variables.entry(variable.clone()).or_default();
+
+            let kind = scheme
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            let http_scheme = scheme
+                .get("scheme")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_lowercase();
+
+            let authorization = |value: String| Header {
+                name: "Authorization".to_string(),
+                value,
+            };
+
+            match (kind, http_scheme.as_str()) {
+                ("http", "bearer") => headers.push(authorization(format!("Bearer _.{}", variable))),
+                ("http", "basic") => {
+                    self.warnings.push(format!(
+                        "Set _.{} to the base64 encoded username:password of {}.",
+                        variable, scheme_name
+                    ));
+                    headers.push(authorization(format!("Basic _.{}", variable)));
+                }
+                ("apiKey", _) => {
+                    let key = scheme
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+
+                    match scheme.get("in").and_then(Value::as_str) {
+                        Some("query") => query.push(format!("{}=_.{}", encode(key), variable)),
+                        Some("cookie") => cookies.push(format!("{}=_.{}", key, variable)),
+                        _ => headers.push(Header {
+                            name: key.to_string(),
+                            value: format!("_.{}", variable),
+                        }),
+                    }
+                }
+                ("oauth2", _) | ("openIdConnect", _) => {
+                    self.warnings.push(format!(
+                        "{} is {}, get a token and set it to _.{}.",
+                        scheme_name, kind, variable
+                    ));
+                    headers.push(authorization(format!("Bearer _.{}", variable)));
+                }
+                (kind, scheme) => self.warnings.push(format!(
+                    "{} uses {} {} security which is not supported.",
+                    name, kind, scheme
+                )),
+            }
+        }
+    }
+
+    /// An example of the schema: its `example`, `default` or first `enum` value, or one
+    /// built from its type. Read only properties are left out as they are not sent.
+    fn example(
+        &mut self,
+        schema: &Value,
+        doc: &Path,
+        depth: usize,
+        refs: &mut Vec<String>,
+    ) -> Value {
+        if depth > MAX_DEPTH {
+            return Value::Null;
+        }
+
+        // Recursive schemas are left out where they repeat.
+        if let Some(key) = reference_key(schema, doc) {
+            if refs.contains(&key) {
+                return Value::Null;
+            }
+
+            let (resolved, resolved_doc) = self.resolve(schema, doc);
+
+            refs.push(key);
+            let example = self.example(&resolved, &resolved_doc, depth, refs);
+            refs.pop();
+
+            return example;
+        }
+
+        for key in ["example", "default", "const"] {
+            if let Some(example) = schema.get(key) {
+                return example.clone();
+            }
+        }
+
+        for key in ["examples", "enum"] {
+            if let Some(first) = schema
+                .get(key)
+                .and_then(Value::as_sequence)
+                .and_then(|v| v.first())
+            {
+                return first.clone();
+            }
+        }
+
+        if let Some(all_of) = schema.get("allOf").and_then(Value::as_sequence) {
+            let mut merged = Mapping::new();
+
+            for part in all_of {
+                if let Value::Mapping(part) = self.example(part, doc, depth, refs) {
+                    merged.extend(part);
+                }
+            }
+
+            if let Value::Mapping(own) = self.object(schema, doc, depth, refs) {
+                merged.extend(own);
+            }
+
+            return Value::Mapping(merged);
+        }
+
+        for key in ["oneOf", "anyOf"] {
+            if let Some(first) = schema
+                .get(key)
+                .and_then(Value::as_sequence)
+                .and_then(|v| v.first())
+            {
+                return self.example(first, doc, depth, refs);
+            }
+        }
+
+        // 3.1 allows a list of types eg. `[string, "null"]`.
+        let kind = match schema.get("type") {
+            Some(Value::String(kind)) => Some(kind.as_str()),
+            Some(Value::Sequence(kinds)) => kinds
+                .iter()
+                .filter_map(Value::as_str)
+                .find(|kind| *kind != "null"),
+            _ => None,
+        };
+
+        let kind = kind.or_else(|| {
+            if schema.get("properties").is_some() {
+                Some("object")
+            } else if schema.get("items").is_some() {
+                Some("array")
+            } else {
+                None
+            }
+        });
+
+        match kind {
+            Some("object") => self.object(schema, doc, depth, refs),
+            Some("array") => match schema.get("items") {
+                Some(items) => {
+                    let item = self.example(items, doc, depth + 1, refs);
+
+                    match item {
+                        Value::Null => Value::Sequence(vec![]),
+                        item => Value::Sequence(vec![item]),
+                    }
+                }
+                None => Value::Sequence(vec![]),
+            },
+            Some("string") => Value::String(string_example(schema)),
+            Some("integer") | Some("number") => schema
+                .get("minimum")
+                .cloned()
+                .unwrap_or(Value::Number(1.into())),
+            Some("boolean") => Value::Bool(true),
+            _ => Value::Null,
+        }
+    }
+
+    fn object(
+        &mut self,
+        schema: &Value,
+        doc: &Path,
+        depth: usize,
+        refs: &mut Vec<String>,
+    ) -> Value {
+        let mut object = Mapping::new();
+
+        let properties = schema
+            .get("properties")
+            .and_then(Value::as_mapping)
+            .cloned()
+            .unwrap_or_default();
+
+        for (name, property) in &properties {
+            if reference_key(property, doc).is_some_and(|key| refs.contains(&key)) {
+                continue;
+            }
+
+            let (resolved, _) = self.resolve(property, doc);
+
+            if resolved.get("readOnly").and_then(Value::as_bool) == Some(true) {
+                continue;
+            }
+
+            let example = self.example(property, doc, depth + 1, refs);
+            object.insert(name.clone(), example);
+        }
+
+        if properties.is_empty() {
+            if let Some(additional @ Value::Mapping(_)) = schema.get("additionalProperties") {
+                let example = self.example(additional, doc, depth + 1, refs);
+                object.insert(Value::String("key".to_string()), example);
+            }
+        }
+
+        Value::Mapping(object)
+    }
+
+    /// Follows `$ref`s, returning the value and the file it comes from. Relative
+    /// references in other files are relative to them.
+    fn resolve(&mut self, value: &Value, doc: &Path) -> (Value, PathBuf) {
+        let mut value = value.clone();
+        let mut doc = doc.to_path_buf();
+
+        for _ in 0..32 {
+            let reference = match value.get("$ref").and_then(Value::as_str) {
+                Some(reference) => reference.to_string(),
+                None => return (value, doc),
+            };
+
+            match self.reference(&reference, &doc) {
+                Some((resolved, resolved_doc)) => {
+                    value = resolved;
+                    doc = resolved_doc;
+                }
+                None => return (Value::Null, doc),
+            }
+        }
+
+        self.warnings
+            .push("A reference points back to itself, it was left out.".to_string());
+
+        (Value::Null, doc)
+    }
+
+    fn reference(&mut self, reference: &str, doc: &Path) -> Option<(Value, PathBuf)> {
+        let (file, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+
+        if file.starts_with("http://") || file.starts_with("https://") {
+            self.warnings.push(format!(
+                "Remote reference {} is not supported, download it next to the specification.",
+                reference
+            ));
+            return None;
+        }
+
+        let target = reference_file(file, doc);
+
+        if !self.spec.documents.contains_key(&target) {
+            match read_document(&target) {
+                Ok(document) => {
+                    self.spec.documents.insert(target.clone(), document);
+                }
+                Err(err) => {
+                    self.warnings
+                        .push(format!("Reference {} can't be read: {}", reference, err));
+                    return None;
+                }
+            }
+        }
+
+        match pointer_get(&self.spec.documents[&target], pointer) {
+            Some(value) => Some((value.clone(), target)),
+            None => {
+                self.warnings
+                    .push(format!("Reference {} was not found.", reference));
+                None
+            }
+        }
+    }
+}
+
+/// The file a reference points to, relative to the document containing it.
+fn reference_file(file: &str, doc: &Path) -> PathBuf {
+    match file {
+        "" => doc.to_path_buf(),
+        file => doc.parent().unwrap_or(Path::new("")).join(file).normalize(),
+    }
+}
+
+/// Identifies the target of a `$ref` whichever document it is written in.
+fn reference_key(schema: &Value, doc: &Path) -> Option<String> {
+    let reference = schema.get("$ref")?.as_str()?;
+    let (file, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+
+    Some(format!(
+        "{}#{}",
+        reference_file(file, doc).display(),
+        pointer
+    ))
+}
+
+/// Follows a JSON pointer eg. `/components/schemas/Pet`.
+fn pointer_get<'a>(value: &'a Value, pointer: &str) -> Option<&'a Value> {
+    let pointer = percent_decode(pointer);
+    let mut value = value;
+
+    for token in pointer.split('/').skip(1) {
+        let token = token.replace("~1", "/").replace("~0", "~");
+
+        value = match value {
+            Value::Mapping(mapping) => mapping.get(token.as_str())?,
+            Value::Sequence(sequence) => sequence.get(token.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+
+    Some(value)
+}
+
+/// `%7Bid%7D` to `{id}`, references to paths are usually encoded.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// `https://api.example.com/v1` to `api.example.com`.
+fn host(url: &str) -> String {
+    let host = url.split_once("://").map_or(url, |(_, rest)| rest);
+
+    host.split('/').next().unwrap_or(host).to_string()
+}
+
+/// Encodes what would change the meaning of a query string.
+fn encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b',' | b':' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+fn string_example(schema: &Value) -> String {
+    let example = match schema.get("format").and_then(Value::as_str) {
+        Some("date-time") => "2024-01-01T00:00:00Z",
+        Some("date") => "2024-01-01",
+        Some("time") => "00:00:00",
+        Some("email") => "user@example.com",
+        Some("uuid") => "3fa85f64-5717-4562-b3fc-2c963f66afa6",
+        Some("uri") | Some("url") => "https://example.com",
+        Some("hostname") => "example.com",
+        Some("ipv4") => "127.0.0.1",
+        Some("ipv6") => "::1",
+        Some("binary") | Some("byte") | Some("base64") => "",
+        _ => "string",
+    };
+
+    example.to_string()
+}
+
+/// Fields of a form from an object example.
+fn fields(example: &Value) -> Vec<(String, String)> {
+    match example {
+        Value::Mapping(mapping) => mapping
+            .iter()
+            .map(|(key, value)| (text(key), text(value)))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// A scalar as text, lists joined with commas and objects as JSON.
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        Value::Bool(value) => value.to_string(),
+        Value::Number(value) => value.to_string(),
+        Value::Sequence(values) => values.iter().map(text).collect::<Vec<_>>().join(","),
+        value => serde_json::to_string(value).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, content: &str) {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn requests(items: Vec<ImportedItem>) -> Vec<ImportedRequest> {
+        items
+            .into_iter()
+            .flat_map(|item| match item {
+                ImportedItem::Folder { items, .. } => requests(items),
+                ImportedItem::Request(request) => vec![request],
+            })
+            .collect()
+    }
+
+    fn json_body(request: &ImportedRequest) -> serde_json::Value {
+        match &request.body {
+            Some(ImportedBody::Json(json)) => serde_json::from_str(json).unwrap(),
+            body => panic!("Expected a JSON body, got {:?}", body),
+        }
+    }
+
+    #[test]
+    fn follows_references_to_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+
+        write(
+            dir.path(),
+            "api.yaml",
+            r#"
+openapi: 3.0.3
+info: { title: Users, version: "1" }
+servers: [{ url: https://api.example.com }]
+paths:
+  /users/{id}:
+    parameters:
+      - $ref: "./common/parameters.yaml#/Id"
+    put:
+      requestBody:
+        content:
+          application/json:
+            schema: { $ref: "schemas/user.yaml#/User" }
+"#,
+        );
+        write(
+            dir.path(),
+            "common/parameters.yaml",
+            "Id: { name: id, in: path, required: true, schema: { type: integer, example: 7 } }\n",
+        );
+        write(
+            dir.path(),
+            "schemas/user.yaml",
+            r#"
+User:
+  type: object
+  properties:
+    name: { type: string, example: Ann }
+    address: { $ref: "address.yaml#/Address" }
+"#,
+        );
+        write(
+            dir.path(),
+            "schemas/address.yaml",
+            "Address: { type: object, properties: { city: { type: string, example: Paris } } }\n",
+        );
+
+        let imported = convert(read_spec(&dir.path().join("api.yaml")).unwrap());
+        let requests = requests(imported.items);
+
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        assert_eq!(requests[0].url, "_.base_url/users/_.id");
+        assert_eq!(requests[0].params, [("id".to_string(), "7".to_string())]);
+        assert_eq!(
+            json_body(&requests[0]),
+            serde_json::json!({ "name": "Ann", "address": { "city": "Paris" } })
+        );
+    }
+
+    #[test]
+    fn stops_recursive_schemas() {
+        let dir = tempfile::tempdir().unwrap();
+
+        write(
+            dir.path(),
+            "api.yaml",
+            r##"
+openapi: 3.1.0
+info: { title: Tree, version: "1" }
+paths:
+  /nodes:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema: { $ref: "#/components/schemas/Node" }
+  /pairs:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema: { $ref: "#/components/schemas/Left" }
+components:
+  schemas:
+    Node:
+      type: object
+      properties:
+        value: { type: integer }
+        parent: { $ref: "#/components/schemas/Node" }
+        children: { type: array, items: { $ref: "#/components/schemas/Node" } }
+    Left:
+      type: object
+      properties:
+        right: { $ref: "#/components/schemas/Right" }
+    Right:
+      type: object
+      properties:
+        left: { $ref: "#/components/schemas/Left" }
+        name: { type: string, example: r }
+"##,
+        );
+
+        let imported = convert(read_spec(&dir.path().join("api.yaml")).unwrap());
+        let requests = requests(imported.items);
+
+        assert_eq!(
+            json_body(&requests[0]),
+            serde_json::json!({ "value": 1, "children": [] })
+        );
+        assert_eq!(
+            json_body(&requests[1]),
+            serde_json::json!({ "right": { "name": "r" } })
+        );
+    }
+}
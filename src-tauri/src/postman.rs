@@ -0,0 +1,1183 @@
+use crate::error::{ErrorDetails, PandaError, SourceLocation};
+use crate::headers::Header;
+use crate::http_runner::PandaTomlRequest;
+use crate::import::{
+    basic_auth, method_table, FormValue, Imported, ImportedBody, ImportedItem, ImportedRequest,
+    Templates,
+};
+use crate::runner::{item_order, FOLDER_CONFIG};
+use crate::utils::{PandaCollection, VARIABLE_REGEX};
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+pub const SCHEMA_V21: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+/// A Postman collection, only the parts Panda can use.
+///
+/// See https://schema.postman.com/collection/json/v2.1.0/draft-07/collection.json
+#[derive(Deserialize, Debug)]
+pub struct Collection {
+    pub info: Info,
+    #[serde(default)]
+    pub item: Vec<Item>,
+    #[serde(default, alias = "variables")]
+    pub variable: Vec<Variable>,
+    pub auth: Option<Auth>,
+    #[serde(default)]
+    pub event: Vec<Event>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Info {
+    pub name: String,
+    pub schema: Option<String>,
+}
+
+/// A folder when it has `item`, a request otherwise.
+#[derive(Deserialize, Debug)]
+pub struct Item {
+    #[serde(default)]
+    pub name: String,
+    pub item: Option<Vec<Item>>,
+    pub request: Option<RequestOrUrl>,
+    pub auth: Option<Auth>,
+    #[serde(default)]
+    pub event: Vec<Event>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum RequestOrUrl {
+    Url(String),
+    Request(Box<Request>),
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Request {
+    pub method: Option<String>,
+    #[serde(default)]
+    pub header: Headers,
+    pub url: Option<Url>,
+    pub body: Option<Body>,
+    pub auth: Option<Auth>,
+}
+
+/// Headers are usually a list but can also be given as raw text.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Headers {
+    List(Vec<KeyValue>),
+    Raw(String),
+}
+
+impl Default for Headers {
+    fn default() -> Self {
+        Headers::List(vec![])
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Url {
+    Raw(String),
+    Parts(UrlParts),
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UrlParts {
+    #[serde(default)]
+    pub raw: String,
+    #[serde(default)]
+    pub query: Vec<KeyValue>,
+    /// Values of the `:name` path segments.
+    #[serde(default)]
+    pub variable: Vec<Variable>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct KeyValue {
+    pub key: Option<String>,
+    pub value: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Variable {
+    pub key: Option<String>,
+    pub value: Option<Value>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Body {
+    pub mode: Option<String>,
+    pub raw: Option<String>,
+    #[serde(default)]
+    pub urlencoded: Vec<KeyValue>,
+    #[serde(default)]
+    pub formdata: Vec<FormParam>,
+    pub graphql: Option<GraphQl>,
+    pub options: Option<BodyOptions>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FormParam {
+    pub key: Option<String>,
+    pub value: Option<String>,
+    /// Path of the file for `file` params, a list when several files were picked.
+    pub src: Option<Value>,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GraphQl {
+    pub query: Option<String>,
+    pub variables: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BodyOptions {
+    pub raw: Option<RawOptions>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RawOptions {
+    pub language: Option<String>,
+}
+
+/// `{ "type": "bearer", "bearer": [{ "key": "token", "value": "..." }] }`
+#[derive(Deserialize, Debug)]
+pub struct Auth {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(flatten)]
+    pub params: BTreeMap<String, Value>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Event {
+    pub listen: String,
+    pub script: Option<Script>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Script {
+    #[serde(default)]
+    pub exec: Exec,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Exec {
+    Lines(Vec<String>),
+    Text(String),
+}
+
+impl Default for Exec {
+    fn default() -> Self {
+        Exec::Lines(vec![])
+    }
+}
+
+impl Auth {
+    /// The value of a parameter, v2.1 uses a list of key/value pairs and v2.0 an object.
+    fn param(&self, name: &str) -> Option<String> {
+        let value = match self.params.get(&self.kind)? {
+            Value::Array(params) => params
+                .iter()
+                .find(|param| param.get("key").and_then(Value::as_str) == Some(name))?
+                .get("value")?,
+            Value::Object(params) => params.get(name)?,
+            _ => return None,
+        };
+
+        match value {
+            Value::String(value) => Some(value.clone()),
+            Value::Null => None,
+            value => Some(value.to_string()),
+        }
+    }
+}
+
+pub fn read_collection(path: &Path) -> Result<Collection, PandaError> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| PandaError::Io(format!("Failed to read {:?}: {}", path, err).into()))?;
+
+    let collection: Collection = serde_json::from_str(&content).map_err(|err| {
+        PandaError::Parse(ErrorDetails::at(
+            format!("Not a Postman collection: {}", err),
+            SourceLocation::from_json(&err, Some(path.to_string_lossy().to_string())),
+        ))
+    })?;
+
+    match &collection.info.schema {
+        Some(schema) if schema.contains("v1.") => Err(PandaError::Parse(
+            "Postman v1 collections are not supported, export the collection as v2.1.".into(),
+        )),
+        _ => Ok(collection),
+    }
+}
+
+/// Converts a collection, `{{var}}` templates become `_.var` variables.
+pub fn convert(collection: Collection) -> Imported {
+    let mut converter = Converter::default();
+
+    converter.scripts(&collection.info.name, &collection.event);
+
+    let items = converter.items(&collection.item, collection.auth.as_ref(), "");
+
+    let mut variables = BTreeMap::new();
+
+    for variable in collection.variable.iter().filter(|v| !v.disabled) {
+        let key = match &variable.key {
+            Some(key) => key,
+            None => continue,
+        };
+
+        let value = match &variable.value {
+            Some(Value::String(value)) => value.clone(),
+            Some(Value::Null) | None => String::new(),
+            Some(value) => value.to_string(),
+        };
+
+        let name = converter.templates.name(key);
+        let value = converter.templates.convert(&value);
+        variables.insert(name, value);
+    }
+
+    let mut warnings = converter.warnings;
+    warnings.extend(converter.templates.warnings);
+
+    // The same template is usually used by many requests.
+    let mut seen = std::collections::HashSet::new();
+    warnings.retain(|warning| seen.insert(warning.clone()));
+
+    Imported {
+        name: collection.info.name,
+        items,
+        variables,
+        environments: BTreeMap::new(),
+        warnings,
+    }
+}
+
+#[derive(Default)]
+struct Converter {
+    templates: Templates,
+    warnings: Vec<String>,
+}
+
+impl Converter {
+    fn items(&mut self, items: &[Item], auth: Option<&Auth>, parent: &str) -> Vec<ImportedItem> {
+        let mut imported = vec![];
+
+        for item in items {
+            let path = match parent {
+                "" => item.name.clone(),
+                parent => format!("{}/{}", parent, item.name),
+            };
+
+            self.scripts(&path, &item.event);
+
+            // Requests and folders without auth inherit the one of their parent.
+            let auth = item.auth.as_ref().or(auth);
+
+            if let Some(children) = &item.item {
+                imported.push(ImportedItem::Folder {
+                    name: item.name.clone(),
+                    items: self.items(children, auth, &path),
+                });
+                continue;
+            }
+
+            let url_only;
+            let request = match &item.request {
+                Some(RequestOrUrl::Request(request)) => request.as_ref(),
+                Some(RequestOrUrl::Url(url)) => {
+                    url_only = Request {
+                        method: None,
+                        header: Headers::default(),
+                        url: Some(Url::Raw(url.clone())),
+                        body: None,
+                        auth: None,
+                    };
+                    &url_only
+                }
+                None => {
+                    self.warn(&path, "has no request and was skipped.");
+                    continue;
+                }
+            };
+
+            if let Some(request) = self.request(&item.name, request, auth, &path) {
+                imported.push(ImportedItem::Request(request));
+            }
+        }
+
+        imported
+    }
+
+    fn request(
+        &mut self,
+        name: &str,
+        request: &Request,
+        auth: Option<&Auth>,
+        path: &str,
+    ) -> Option<ImportedRequest> {
+        let method = request.method.as_deref().unwrap_or("GET");
+
+        let method = match method_table(method) {
+            Some(method) => method.to_string(),
+            None => {
+                self.warn(path, &format!("uses method {} and was skipped.", method));
+                return None;
+            }
+        };
+
+        let (mut url, params) = match &request.url {
+            Some(url) => self.url(url, path),
+            None => (String::new(), vec![]),
+        };
+
+        let mut headers = match &request.header {
+            Headers::List(headers) => headers
+                .iter()
+                .filter(|h| !h.disabled)
+                .filter_map(|h| Some((h.key.clone()?, h.value.clone().unwrap_or_default())))
+                .collect(),
+            Headers::Raw(raw) => raw
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                .collect(),
+        };
+
+        let headers: Vec<Header> = {
+            let auth = request.auth.as_ref().or(auth);
+
+            if let Some(auth) = auth {
+                self.auth(auth, &mut headers, &mut url, path);
+            }
+
+            headers
+                .into_iter()
+                .map(|(name, value)| Header {
+                    name: self.templates.convert(&name),
+                    value: self.templates.convert(&value),
+                })
+                .collect()
+        };
+
+        let body = match &request.body {
+            Some(body) if !body.disabled => self.body(body, &headers, path),
+            _ => None,
+        };
+
+        Some(ImportedRequest {
+            name: name.to_string(),
+            method,
+            url: self.templates.convert(&url),
+            params,
+            headers,
+            body,
+        })
+    }
+
+    /// The raw url with the disabled query parameters removed, `:name` path variables
+    /// become `_.name` and their values the path params of the request.
+    fn url(&mut self, url: &Url, path: &str) -> (String, Vec<(String, String)>) {
+        let parts = match url {
+            Url::Raw(raw) => return (raw.clone(), vec![]),
+            Url::Parts(parts) => parts,
+        };
+
+        let mut url = parts.raw.clone();
+
+        if parts.query.iter().any(|q| q.disabled) {
+            url.truncate(url.find('?').unwrap_or(url.len()));
+
+            let query: Vec<String> = parts
+                .query
+                .iter()
+                .filter(|q| !q.disabled)
+                .filter_map(|q| {
+                    let key = q.key.as_deref()?;
+
+                    Some(match &q.value {
+                        Some(value) => format!("{}={}", key, value),
+                        None => key.to_string(),
+                    })
+                })
+                .collect();
+
+            if !query.is_empty() {
+                url = format!("{}?{}", url, query.join("&"));
+            }
+        }
+
+        let mut params = vec![];
+
+        for variable in &parts.variable {
+            let key = match &variable.key {
+                Some(key) => key,
+                None => continue,
+            };
+
+            let value = match &variable.value {
+                Some(Value::String(value)) if !value.is_empty() => value.clone(),
+                Some(Value::Number(value)) => value.to_string(),
+                _ => {
+                    self.warn(
+                        path,
+                        &format!("has no value for path variable :{}, it was kept.", key),
+                    );
+                    continue;
+                }
+            };
+
+            url = replace_segment(&url, &format!(":{}", key), &format!("{{{{{}}}}}", key));
+            params.push((self.templates.name(key), self.templates.convert(&value)));
+        }
+
+        (url, params)
+    }
+
+    /// Adds the header or query parameter the auth of the request sends.
+    fn auth(
+        &mut self,
+        auth: &Auth,
+        headers: &mut Vec<(String, String)>,
+        url: &mut String,
+        path: &str,
+    ) {
+        let mut header = |name: &str, value: String| {
+            if !headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name)) {
+                headers.push((name.to_string(), value));
+            }
+        };
+
+        match auth.kind.as_str() {
+            "noauth" => {}
+            "bearer" => {
+                let token = auth.param("token").unwrap_or_default();
+                header("Authorization", format!("Bearer {}", token));
+            }
+            "basic" => {
+                let username = auth.param("username").unwrap_or_default();
+                let password = auth.param("password").unwrap_or_default();
+
+                match basic_auth(&username, &password) {
+                    Some(value) => header("Authorization", value),
+                    None => self.warn(
+                        path,
+                        "uses basic auth with variables, set the Authorization header manually.",
+                    ),
+                }
+            }
+            "apikey" => {
+                let key = auth.param("key").unwrap_or_default();
+                let value = auth.param("value").unwrap_or_default();
+
+                match auth.param("in").as_deref() {
+                    Some("query") => {
+                        let separator = if url.contains('?') { '&' } else { '?' };
+                        *url = format!("{}{}{}={}", url, separator, key, value);
+                    }
+                    _ => header(&key, value),
+                }
+            }
+            kind => self.warn(path, &format!("uses {} auth which is not supported.", kind)),
+        }
+    }
+
+    fn body(&mut self, body: &Body, headers: &[Header], path: &str) -> Option<ImportedBody> {
+        match body.mode.as_deref() {
+            Some("raw") => {
+                let raw = self.templates.convert(body.raw.as_deref()?);
+
+                if raw.is_empty() {
+                    return None;
+                }
+
+                let language = body
+                    .options
+                    .as_ref()
+                    .and_then(|options| options.raw.as_ref())
+                    .and_then(|raw| raw.language.clone())
+                    .or_else(|| {
+                        let content_type = headers
+                            .iter()
+                            .find(|h| h.name.eq_ignore_ascii_case("content-type"))?
+                            .value
+                            .to_lowercase();
+
+                        ["json", "xml"]
+                            .into_iter()
+                            .find(|language| content_type.contains(language))
+                            .map(str::to_string)
+                    });
+
+                match language.as_deref() {
+                    Some("json") => Some(ImportedBody::Json(raw)),
+                    Some("xml") => Some(ImportedBody::Xml(raw)),
+                    _ => Some(ImportedBody::Text(raw)),
+                }
+            }
+            Some("urlencoded") => Some(ImportedBody::FormUrlEncoded(
+                body.urlencoded
+                    .iter()
+                    .filter(|field| !field.disabled)
+                    .filter_map(|field| {
+                        Some((
+                            self.templates.convert(field.key.as_deref()?),
+                            self.templates
+                                .convert(field.value.as_deref().unwrap_or_default()),
+                        ))
+                    })
+                    .collect(),
+            )),
+            Some("formdata") => {
+                let mut fields = vec![];
+
+                for field in body.formdata.iter().filter(|field| !field.disabled) {
+                    let key = match &field.key {
+                        Some(key) => self.templates.convert(key),
+                        None => continue,
+                    };
+
+                    let value = match field.kind.as_deref() {
+                        Some("file") => {
+                            let src = match &field.src {
+                                Some(Value::String(src)) => src.clone(),
+                                Some(Value::Array(src)) => src
+                                    .first()
+                                    .and_then(Value::as_str)
+                                    .unwrap_or_default()
+                                    .to_string(),
+                                _ => String::new(),
+                            };
+
+                            self.warn(
+                                path,
+                                &format!(
+                                    "sends file {:?} as field {}, copy it to the project.",
+                                    src, key
+                                ),
+                            );
+
+                            FormValue::File(src)
+                        }
+                        _ => FormValue::Text(
+                            self.templates
+                                .convert(field.value.as_deref().unwrap_or_default()),
+                        ),
+                    };
+
+                    fields.push((key, value));
+                }
+
+                Some(ImportedBody::FormMultipart(fields))
+            }
+            Some("graphql") => {
+                let graphql = body.graphql.as_ref()?;
+                let variables = graphql
+                    .variables
+                    .as_deref()
+                    .filter(|v| !v.trim().is_empty())
+                    .and_then(|v| serde_json::from_str::<Value>(v).ok())
+                    .unwrap_or(Value::Null);
+
+                let json = serde_json::json!({
+                    "query": graphql.query.clone().unwrap_or_default(),
+                    "variables": variables,
+                });
+
+                let json = serde_json::to_string_pretty(&json).unwrap_or_default();
+
+                Some(ImportedBody::Json(self.templates.convert(&json)))
+            }
+            Some(mode) => {
+                self.warn(
+                    path,
+                    &format!("has a {} body which is not supported.", mode),
+                );
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn scripts(&mut self, path: &str, events: &[Event]) {
+        for event in events {
+            let has_code = match event.script.as_ref().map(|script| &script.exec) {
+                Some(Exec::Lines(lines)) => lines.iter().any(|line| !line.trim().is_empty()),
+                Some(Exec::Text(text)) => !text.trim().is_empty(),
+                None => false,
+            };
+
+            if !has_code {
+                continue;
+            }
+
+            let script = match event.listen.as_str() {
+                "prerequest" => "pre-request script",
+                "test" => "test script",
+                _ => "script",
+            };
+
+            self.warn(path, &format!("has a {} which was not imported.", script));
+        }
+    }
+
+    fn warn(&mut self, path: &str, message: &str) {
+        self.warnings.push(format!("{} {}", path, message));
+    }
+}
+
+/// Replaces a whole path segment eg. `:id` in `/users/:id/posts` but not in `/:ids`.
+fn replace_segment(url: &str, segment: &str, value: &str) -> String {
+    let (path, query) = match url.find('?') {
+        Some(i) => url.split_at(i),
+        None => (url, ""),
+    };
+
+    let path: Vec<&str> = path
+        .split('/')
+        .map(|part| if part == segment { value } else { part })
+        .collect();
+
+    path.join("/") + query
+}
+
+/// Converts a collection tree read by `get_collection_from_path` to a Postman v2.1
+/// collection, `_.var` variables become `{{var}}`.
+///
+/// Returns the collection and the files that could not be exported.
+pub fn export(
+    name: &str,
+    items: &[PandaCollection],
+    variables: &BTreeMap<String, String>,
+) -> (Value, Vec<String>) {
+    let mut warnings = vec![];
+    let item = export_items(items, &mut warnings);
+
+    let variable: Vec<Value> = variables
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": templates(value) }))
+        .collect();
+
+    let collection = json!({
+        "info": {
+            "_postman_id": Uuid::new_v4().to_string(),
+            "name": name,
+            "schema": SCHEMA_V21,
+        },
+        "item": item,
+        "variable": variable,
+    });
+
+    (collection, warnings)
+}
+
+/// Folders and requests in the order the runner uses, other files are left out.
+fn export_items(items: &[PandaCollection], warnings: &mut Vec<String>) -> Vec<Value> {
+    let mut items: Vec<&PandaCollection> = items
+        .iter()
+        .filter(|item| !item.name.starts_with('.') && item.name != FOLDER_CONFIG)
+        .filter(|item| item.children.is_some() || item.name.ends_with(".toml"))
+        .collect();
+
+    items.sort_by(|a, b| {
+        let a_order = item_order(a).unwrap_or(i64::MAX);
+        let b_order = item_order(b).unwrap_or(i64::MAX);
+
+        a_order.cmp(&b_order).then_with(|| a.name.cmp(&b.name))
+    });
+
+    let mut exported = vec![];
+
+    for item in items {
+        if let Some(children) = &item.children {
+            exported.push(json!({
+                "name": item.name,
+                "item": export_items(children, warnings),
+            }));
+            continue;
+        }
+
+        let content = item.content.as_deref().unwrap_or_default();
+
+        let request = toml::from_str::<PandaTomlRequest>(content)
+            .map_err(|err| err.message().to_string())
+            .and_then(|request| {
+                ImportedRequest::from_toml(&request, &item.name)
+                    .ok_or_else(|| "no request is defined".to_string())
+            });
+
+        match request {
+            Ok(request) => exported.push(export_request(&request)),
+            Err(err) => warnings.push(format!("{} was skipped: {}", item.path, err)),
+        }
+    }
+
+    exported
+}
+
+fn export_request(request: &ImportedRequest) -> Value {
+    let mut headers: Vec<&Header> = request.headers.iter().collect();
+
+    // A single Authorization header is exported as the auth of the request.
+    let authorization: Vec<&Header> = headers
+        .iter()
+        .copied()
+        .filter(|h| h.name.eq_ignore_ascii_case("authorization"))
+        .collect();
+
+    let auth = match authorization.as_slice() {
+        [header] => export_auth(&header.value),
+        _ => None,
+    };
+
+    if auth.is_some() {
+        headers.retain(|h| !h.name.eq_ignore_ascii_case("authorization"));
+    }
+
+    let header: Vec<Value> = headers
+        .iter()
+        .map(|h| json!({ "key": templates(&h.name), "value": templates(&h.value) }))
+        .collect();
+
+    let url = match request.params.is_empty() {
+        true => json!(templates(&request.url)),
+        false => {
+            let variable: Vec<Value> = request
+                .params
+                .iter()
+                .map(|(key, value)| json!({ "key": key, "value": templates(value) }))
+                .collect();
+
+            json!({ "raw": path_variables(&request.url, &request.params), "variable": variable })
+        }
+    };
+
+    let mut exported = json!({
+        "method": request.method.to_uppercase(),
+        "header": header,
+        "url": url,
+    });
+
+    if let Some(body) = &request.body {
+        exported["body"] = export_body(body);
+    }
+
+    if let Some(auth) = auth {
+        exported["auth"] = auth;
+    }
+
+    json!({ "name": request.name, "request": exported })
+}
+
+/// Bearer and basic auth, `None` for other schemes which are kept as a header.
+fn export_auth(value: &str) -> Option<Value> {
+    let (scheme, credentials) = value.trim().split_once(' ')?;
+    let credentials = credentials.trim();
+
+    if scheme.eq_ignore_ascii_case("bearer") {
+        return Some(json!({
+            "type": "bearer",
+            "bearer": [{ "key": "token", "value": templates(credentials), "type": "string" }],
+        }));
+    }
+
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(credentials)
+        .ok()?;
+    let (username, password) = String::from_utf8(decoded)
+        .ok()?
+        .split_once(':')
+        .map(|(username, password)| (username.to_string(), password.to_string()))?;
+
+    Some(json!({
+        "type": "basic",
+        "basic": [
+            { "key": "username", "value": username, "type": "string" },
+            { "key": "password", "value": password, "type": "string" },
+        ],
+    }))
+}
+
+fn export_body(body: &ImportedBody) -> Value {
+    let raw = |content: &str, language: &str| {
+        json!({
+            "mode": "raw",
+            "raw": templates(content),
+            "options": { "raw": { "language": language } },
+        })
+    };
+
+    let fields = |fields: &[(String, String)]| -> Vec<Value> {
+        fields
+            .iter()
+            .map(|(key, value)| {
+                json!({ "key": templates(key), "value": templates(value), "type": "text" })
+            })
+            .collect()
+    };
+
+    match body {
+        ImportedBody::Json(content) => raw(content, "json"),
+        ImportedBody::Xml(content) => raw(content, "xml"),
+        ImportedBody::Text(content) => raw(content, "text"),
+        ImportedBody::FormUrlEncoded(form) => json!({
+            "mode": "urlencoded",
+            "urlencoded": fields(form),
+        }),
+        ImportedBody::FormMultipart(form) => json!({
+            "mode": "formdata",
+            "formdata": form
+                .iter()
+                .map(|(key, value)| match value {
+                    FormValue::Text(value) => {
+                        json!({ "key": templates(key), "value": templates(value), "type": "text" })
+                    }
+                    FormValue::File(file) => {
+                        json!({ "key": templates(key), "src": file, "type": "file" })
+                    }
+                })
+                .collect::<Vec<_>>(),
+        }),
+    }
+}
+
+/// `_.base_url/users` to `{{base_url}}/users`.
+fn templates(text: &str) -> String {
+    path_variables(text, &[])
+}
+
+/// Same as `templates`, path params become path variables eg. `/users/:user_id`.
+fn path_variables(url: &str, params: &[(String, String)]) -> String {
+    VARIABLE_REGEX
+        .replace_all(url, |caps: &fancy_regex::Captures<'_>| {
+            let name = &caps[1];
+
+            match params.iter().any(|(key, _)| key == name) {
+                true => format!(":{}", name),
+                false => format!("{{{{{}}}}}", name),
+            }
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::write_import;
+    use crate::utils::get_collection_from_path;
+
+    fn collection(value: Value) -> Collection {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn requests(items: &[ImportedItem]) -> Vec<&ImportedRequest> {
+        items
+            .iter()
+            .flat_map(|item| match item {
+                ImportedItem::Request(request) => vec![request],
+                ImportedItem::Folder { items, .. } => requests(items),
+            })
+            .collect()
+    }
+
+    fn header<'a>(request: &'a ImportedRequest, name: &str) -> Option<&'a str> {
+        request
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+            .map(|h| h.value.as_str())
+    }
+
+    #[test]
+    fn converts_folders_and_inherited_auth() {
+        let imported = convert(collection(json!({
+            "info": { "name": "API", "schema": SCHEMA_V21 },
+            "auth": { "type": "bearer", "bearer": [{ "key": "token", "value": "{{token}}" }] },
+            "variable": [{ "key": "base-url", "value": "https://example.com" }],
+            "item": [
+                {
+                    "name": "users",
+                    "item": [
+                        {
+                            "name": "Get user",
+                            "request": {
+                                "method": "GET",
+                                "url": {
+                                    "raw": "{{base-url}}/users/:id?page=1&draft=true",
+                                    "query": [
+                                        { "key": "page", "value": "1" },
+                                        { "key": "draft", "value": "true", "disabled": true },
+                                    ],
+                                    "variable": [{ "key": "id", "value": "42" }],
+                                },
+                            },
+                        },
+                        {
+                            "name": "admin",
+                            "auth": {
+                                "type": "basic",
+                                "basic": { "username": "admin", "password": "secret" },
+                            },
+                            "item": [{ "name": "Stats", "request": "{{base-url}}/stats" }],
+                        },
+                    ],
+                },
+                {
+                    "name": "Search",
+                    "request": {
+                        "method": "GET",
+                        "url": "{{base-url}}/search",
+                        "auth": {
+                            "type": "apikey",
+                            "apikey": [
+                                { "key": "key", "value": "api_key" },
+                                { "key": "value", "value": "{{key}}" },
+                                { "key": "in", "value": "query" },
+                            ],
+                        },
+                    },
+                },
+            ],
+        })));
+
+        assert_eq!(imported.variables["base_url"], "https://example.com");
+
+        let users = match &imported.items[0] {
+            ImportedItem::Folder { name, items } => {
+                assert_eq!(name, "users");
+                items
+            }
+            item => panic!("{:?}", item),
+        };
+        assert!(matches!(&users[1], ImportedItem::Folder { name, .. } if name == "admin"));
+
+        let requests = requests(&imported.items);
+        let (get, stats, search) = (requests[0], requests[1], requests[2]);
+
+        assert_eq!(get.url, "_.base_url/users/_.id?page=1");
+        assert_eq!(get.params, [("id".to_string(), "42".to_string())]);
+        assert_eq!(header(get, "authorization"), Some("Bearer _.token"));
+
+        assert_eq!(stats.method, "get");
+        assert_eq!(
+            header(stats, "authorization"),
+            Some("Basic YWRtaW46c2VjcmV0")
+        );
+
+        assert_eq!(search.url, "_.base_url/search?api_key=_.key");
+        assert_eq!(header(search, "authorization"), None);
+    }
+
+    #[test]
+    fn converts_bodies() {
+        let request = |body: Value| json!({ "name": "r", "request": { "method": "POST", "url": "https://example.com", "body": body } });
+
+        let imported = convert(collection(json!({
+            "info": { "name": "Bodies" },
+            "item": [
+                request(json!({ "mode": "raw", "raw": "{\"a\": \"{{a}}\"}", "options": { "raw": { "language": "json" } } })),
+                request(json!({ "mode": "raw", "raw": "<a/>", "options": { "raw": { "language": "xml" } } })),
+                request(json!({ "mode": "raw", "raw": "plain" })),
+                request(json!({ "mode": "urlencoded", "urlencoded": [
+                    { "key": "a", "value": "1" },
+                    { "key": "b", "value": "2", "disabled": true },
+                ] })),
+                request(json!({ "mode": "formdata", "formdata": [
+                    { "key": "name", "value": "{{name}}", "type": "text" },
+                    { "key": "avatar", "src": ["/tmp/avatar.png"], "type": "file" },
+                ] })),
+                request(json!({ "mode": "graphql", "graphql": { "query": "{ me { id } }", "variables": "{\"a\": 1}" } })),
+            ],
+        })));
+
+        let bodies: Vec<Option<ImportedBody>> = requests(&imported.items)
+            .into_iter()
+            .map(|request| request.body.clone())
+            .collect();
+
+        assert_eq!(
+            bodies[0],
+            Some(ImportedBody::Json("{\"a\": \"_.a\"}".into()))
+        );
+        assert_eq!(bodies[1], Some(ImportedBody::Xml("<a/>".into())));
+        assert_eq!(bodies[2], Some(ImportedBody::Text("plain".into())));
+        assert_eq!(
+            bodies[3],
+            Some(ImportedBody::FormUrlEncoded(vec![("a".into(), "1".into())]))
+        );
+        assert_eq!(
+            bodies[4],
+            Some(ImportedBody::FormMultipart(vec![
+                ("name".into(), FormValue::Text("_.name".into())),
+                ("avatar".into(), FormValue::File("/tmp/avatar.png".into())),
+            ]))
+        );
+
+        let graphql: Value = match &bodies[5] {
+            Some(ImportedBody::Json(json)) => serde_json::from_str(json).unwrap(),
+            body => panic!("{:?}", body),
+        };
+        assert_eq!(
+            graphql,
+            json!({ "query": "{ me { id } }", "variables": { "a": 1 } })
+        );
+
+        assert!(imported
+            .warnings
+            .iter()
+            .any(|w| w.contains("sends file \"/tmp/avatar.png\" as field avatar")));
+    }
+
+    #[test]
+    fn warns_about_scripts_and_unsupported_auth() {
+        let imported = convert(collection(json!({
+            "info": { "name": "API" },
+            "event": [{ "listen": "prerequest", "script": { "exec": ["pm.environment.set('a', 1);"] } }],
+            "item": [
+                {
+                    "name": "Login",
+                    "event": [
+                        { "listen": "test", "script": { "exec": "pm.test('ok');" } },
+                        { "listen": "prerequest", "script": { "exec": [""] } },
+                    ],
+                    "request": {
+                        "method": "POST",
+                        "url": "https://example.com/login",
+                        "auth": { "type": "oauth2", "oauth2": [] },
+                    },
+                },
+                {
+                    "name": "Signed",
+                    "request": {
+                        "method": "GET",
+                        "url": "https://example.com",
+                        "auth": {
+                            "type": "basic",
+                            "basic": [
+                                { "key": "username", "value": "{{user}}" },
+                                { "key": "password", "value": "secret" },
+                            ],
+                        },
+                    },
+                },
+            ],
+        })));
+
+        assert_eq!(
+            imported.warnings,
+            [
+                "API has a pre-request script which was not imported.",
+                "Login has a test script which was not imported.",
+                "Login uses oauth2 auth which is not supported.",
+                "Signed uses basic auth with variables, set the Authorization header manually.",
+            ]
+        );
+
+        let requests = requests(&imported.items);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(header(requests[0], "authorization"), None);
+        assert_eq!(header(requests[1], "authorization"), None);
+    }
+
+    /// One line per folder and request, headers sorted as auth headers are added last.
+    fn outline(items: &[ImportedItem], parent: &str) -> Vec<String> {
+        let mut lines = vec![];
+
+        for item in items {
+            match item {
+                ImportedItem::Folder { name, items } => {
+                    let path = format!("{}/{}", parent, name);
+                    lines.push(path.clone());
+                    lines.extend(outline(items, &path));
+                }
+                ImportedItem::Request(request) => {
+                    let mut request = request.clone();
+                    request.headers.sort_by(|a, b| a.name.cmp(&b.name));
+                    lines.push(format!("{}/{:?}", parent, request));
+                }
+            }
+        }
+
+        lines
+    }
+
+    #[test]
+    fn exports_what_was_imported() {
+        let sample = Path::new(env!("CARGO_MANIFEST_DIR")).join("../postman_sample.json");
+        let imported = convert(read_collection(&sample).unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("panda.config.json"),
+            r#"{"name": "test", "version": "1", "collection": "./collection"}"#,
+        )
+        .unwrap();
+
+        let summary = write_import(dir.path(), imported.clone(), None).unwrap();
+        assert_eq!((summary.folders, summary.requests), (2, 9));
+
+        let items = get_collection_from_path(summary.path, vec![]).unwrap();
+        let (exported, warnings) = export(&imported.name, &items, &imported.variables);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let reimported = convert(serde_json::from_value(exported).unwrap());
+
+        assert_eq!(reimported.variables, imported.variables);
+        assert_eq!(outline(&reimported.items, ""), outline(&imported.items, ""));
+
+        // Every body kind and auth went through the round trip.
+        let requests = requests(&imported.items);
+        let bodies: Vec<&str> = requests
+            .iter()
+            .filter_map(|request| match request.body.as_ref()? {
+                ImportedBody::Json(_) => Some("json"),
+                ImportedBody::Xml(_) => Some("xml"),
+                ImportedBody::Text(_) => Some("text"),
+                ImportedBody::FormUrlEncoded(_) => Some("urlencoded"),
+                ImportedBody::FormMultipart(_) => Some("multipart"),
+            })
+            .collect();
+        assert_eq!(
+            bodies,
+            ["json", "urlencoded", "multipart", "xml", "text", "json"]
+        );
+
+        let get_user = requests[2];
+        assert_eq!(get_user.url, "_.baseUrl/users/_.id?fields=name,email");
+        assert_eq!(get_user.params, [("id".to_string(), "42".to_string())]);
+        assert_eq!(header(get_user, "x-api-key"), Some("_.apiKey"));
+        assert_eq!(requests[3].url, "_.baseUrl/users?q=john&api_key=_.apiKey");
+        assert_eq!(
+            header(requests[4], "authorization"),
+            Some("Basic YWRtaW46c2VjcmV0")
+        );
+        assert_eq!(
+            header(requests[6], "authorization"),
+            Some("Bearer _.authToken")
+        );
+    }
+}
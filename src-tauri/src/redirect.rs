@@ -0,0 +1,403 @@
+use crate::error::PandaError;
+use crate::headers::{read_headers, Header};
+use crate::timings::{ConnectionProbe, Timings};
+use reqwest::header::{
+    HeaderMap, HeaderName, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION,
+    PROXY_AUTHORIZATION, TRANSFER_ENCODING,
+};
+use reqwest::{Client, Method, Request, Response, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Same limit reqwest applies when it follows redirects itself.
+const MAX_REDIRECTS: usize = 10;
+
+/// Request headers that are never forwarded to another origin.
+const SENSITIVE_HEADERS: [HeaderName; 3] = [AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE];
+
+/// A redirect response that was followed.
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct RedirectHop {
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    /// Absolute url the `Location` header resolved to.
+    pub location: String,
+    pub headers: Vec<Header>,
+    pub timings: Timings,
+    /// Request headers dropped before following because `location` is on another origin.
+    pub stripped_headers: Vec<String>,
+    /// Why `location` wasn't followed, the response is then the final one.
+    pub refused: Option<String>,
+}
+
+/// Final response of a request along with the redirects followed to reach it.
+pub struct Redirected {
+    pub response: Response,
+    /// When the final request was sent and when its response headers arrived.
+    pub sent_at: Instant,
+    pub headers_at: Instant,
+    pub hops: Vec<RedirectHop>,
+}
+
+/// Sends `request`, following redirects unless `follow` is `false` in which case the
+/// first redirect response is returned as is.
+///
+/// The client must be built with `redirect::Policy::none()`. Its TLS config presents the
+/// client certificate chosen for `identity_host`, redirects to other hosts are refused so
+/// neither the certificate nor its CA apply to them.
+pub async fn send(
+    client: &Client,
+    request: Request,
+    follow: bool,
+    identity_host: Option<&str>,
+    probe: &ConnectionProbe,
+) -> Result<Redirected, PandaError> {
+    let mut request = request;
+    let mut hops = vec![];
+
+    loop {
+        let method = request.method().clone();
+        let url = request.url().clone();
+        let request_headers = request.headers().clone();
+
+        // Streaming bodies can't be sent twice, a 307 or 308 for them is returned as is.
+        let replay = request.try_clone();
+
+        let sent_at = Instant::now();
+
+        let response = client.execute(request).await?;
+
+        let headers_at = Instant::now();
+
+        let next = match get_location(&response) {
+            Some(location) if follow => redirect_request(
+                replay,
+                &request_headers,
+                &method,
+                &url,
+                response.status(),
+                location,
+            ),
+            _ => None,
+        };
+
+        let (next, stripped_headers) = match next {
+            Some(n) => n,
+            None => {
+                return Ok(Redirected {
+                    response,
+                    sent_at,
+                    headers_at,
+                    hops,
+                })
+            }
+        };
+
+        let next_host = next.url().host_str().unwrap_or_default();
+
+        if let Some(host) = identity_host.filter(|host| !host.eq_ignore_ascii_case(next_host)) {
+            hops.push(RedirectHop {
+                method: method.to_string(),
+                url: url.to_string(),
+                status: response.status().as_u16(),
+                location: next.url().to_string(),
+                headers: read_headers(response.headers()),
+                timings: probe.get_timings(sent_at, headers_at, Instant::now()),
+                stripped_headers: vec![],
+                refused: Some(format!(
+                    "Not following to {} since the client certificate for {} would be presented to it.",
+                    next_host, host
+                )),
+            });
+
+            return Ok(Redirected {
+                response,
+                sent_at,
+                headers_at,
+                hops,
+            });
+        }
+
+        if hops.len() >= MAX_REDIRECTS {
+            return Err(PandaError::Network(
+                format!(
+                    "Too many redirects, stopped after {} hops at {}.",
+                    MAX_REDIRECTS, url
+                )
+                .into(),
+            ));
+        }
+
+        let status = response.status().as_u16();
+        let headers = read_headers(response.headers());
+
+        // Reading the body lets the connection be reused for the next hop.
+        let _ = response.bytes().await;
+
+        hops.push(RedirectHop {
+            method: method.to_string(),
+            url: url.to_string(),
+            status,
+            location: next.url().to_string(),
+            headers,
+            timings: probe.get_timings(sent_at, headers_at, Instant::now()),
+            stripped_headers,
+            refused: None,
+        });
+
+        request = next;
+    }
+}
+
+/// Resolves the `Location` header against the response url.
+fn get_location(response: &Response) -> Option<Url> {
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    let url = response.url().join(location).ok()?;
+
+    match url.scheme() {
+        "http" | "https" => Some(url),
+        _ => None,
+    }
+}
+
+/// Builds the request following a redirect the way browsers do, 301 and 302 turn a
+/// POST into a GET, 303 turns anything but HEAD into a GET and 307/308 replay the request.
+fn redirect_request(
+    replay: Option<Request>,
+    headers: &HeaderMap,
+    method: &Method,
+    url: &Url,
+    status: StatusCode,
+    location: Url,
+) -> Option<(Request, Vec<String>)> {
+    let switch_to_get = match status {
+        StatusCode::SEE_OTHER => *method != Method::HEAD,
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => *method == Method::POST,
+        StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => false,
+        _ => return None,
+    };
+
+    let mut next = if switch_to_get {
+        let mut next = Request::new(Method::GET, location);
+        *next.headers_mut() = headers.clone();
+
+        for name in [CONTENT_TYPE, CONTENT_LENGTH, TRANSFER_ENCODING] {
+            next.headers_mut().remove(name);
+        }
+
+        next
+    } else {
+        let mut next = replay?;
+        *next.url_mut() = location;
+        next
+    };
+
+    let mut stripped_headers = vec![];
+
+    if next.url().origin() != url.origin() {
+        for name in SENSITIVE_HEADERS {
+            if next.headers_mut().remove(&name).is_some() {
+                stripped_headers.push(name.to_string());
+            }
+        }
+    }
+
+    Some((next, stripped_headers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn request(method: Method, url: &str) -> (Request, HeaderMap) {
+        let mut request = Request::new(method, Url::parse(url).unwrap());
+        let headers = request.headers_mut();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        headers.insert(
+            PROXY_AUTHORIZATION,
+            HeaderValue::from_static("Basic cHJveHk="),
+        );
+        headers.insert(COOKIE, HeaderValue::from_static("session=1"));
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        *request.body_mut() = Some("{}".into());
+
+        let headers = request.headers().clone();
+        (request, headers)
+    }
+
+    fn follow(method: Method, status: u16, location: &str) -> (Request, Vec<String>) {
+        let (request, headers) = request(method.clone(), "https://example.com/a");
+        let url = request.url().clone();
+
+        redirect_request(
+            request.try_clone(),
+            &headers,
+            &method,
+            &url,
+            StatusCode::from_u16(status).unwrap(),
+            Url::parse(location).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn switches_to_get_like_browsers() {
+        for status in [301, 302, 303] {
+            let (next, _) = follow(Method::POST, status, "https://example.com/b");
+
+            assert_eq!(next.method(), Method::GET, "{}", status);
+            assert!(next.body().is_none());
+            assert!(!next.headers().contains_key(CONTENT_TYPE));
+        }
+
+        assert_eq!(
+            follow(Method::PUT, 303, "https://example.com/b").0.method(),
+            Method::GET
+        );
+        assert_eq!(
+            follow(Method::PUT, 302, "https://example.com/b").0.method(),
+            Method::PUT
+        );
+        assert_eq!(
+            follow(Method::HEAD, 303, "https://example.com/b")
+                .0
+                .method(),
+            Method::HEAD
+        );
+    }
+
+    #[test]
+    fn replays_the_request_for_307_and_308() {
+        for status in [307, 308] {
+            let (next, _) = follow(Method::POST, status, "https://example.com/b");
+
+            assert_eq!(next.method(), Method::POST, "{}", status);
+            assert_eq!(next.url().as_str(), "https://example.com/b");
+            assert_eq!(next.body().and_then(|b| b.as_bytes()), Some(&b"{}"[..]));
+            assert_eq!(next.headers()[CONTENT_TYPE], "application/json");
+        }
+    }
+
+    #[test]
+    fn strips_credentials_for_other_origins() {
+        let (next, stripped) = follow(Method::GET, 302, "https://example.com/b");
+        assert!(stripped.is_empty());
+        assert!(next.headers().contains_key(AUTHORIZATION));
+
+        for location in [
+            "https://other.com/b",
+            "http://example.com/b",
+            "https://example.com:8443/b",
+        ] {
+            let (next, stripped) = follow(Method::GET, 302, location);
+
+            assert_eq!(stripped, ["authorization", "proxy-authorization", "cookie"]);
+            for name in SENSITIVE_HEADERS {
+                assert!(!next.headers().contains_key(name), "{}", location);
+            }
+            assert!(next.headers().contains_key(CONTENT_TYPE));
+        }
+    }
+
+    /// Answers `/hop/N` with a redirect to `/hop/N+1` up to `/hop/100`, `/away` with a redirect to
+    /// `localhost` and anything else with a 200.
+    async fn start_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let read = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                let path = request.split(' ').nth(1).unwrap_or("/").to_string();
+
+                let hop = path
+                    .strip_prefix("/hop/")
+                    .and_then(|hop| hop.parse::<usize>().ok());
+
+                let location = if let Some(hop) = hop.filter(|hop| *hop < 100) {
+                    Some(format!("/hop/{}", hop + 1))
+                } else if path == "/away" {
+                    Some(format!("http://localhost:{}/", port))
+                } else {
+                    None
+                };
+
+                let response = match location {
+                    Some(location) => format!(
+                        "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        location
+                    ),
+                    None => "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_string(),
+                };
+
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        port
+    }
+
+    async fn get(url: &str, identity_host: Option<&str>) -> Result<Redirected, PandaError> {
+        let client = Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .no_proxy()
+            .build()
+            .unwrap();
+        let request = Request::new(Method::GET, Url::parse(url).unwrap());
+
+        send(
+            &client,
+            request,
+            true,
+            identity_host,
+            &ConnectionProbe::default(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn stops_after_too_many_redirects() {
+        let port = start_server().await;
+
+        let redirected = get(
+            &format!("http://127.0.0.1:{}/hop/{}", port, 100 - MAX_REDIRECTS),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(redirected.response.status(), StatusCode::OK);
+        assert_eq!(redirected.hops.len(), MAX_REDIRECTS);
+
+        let err = get(&format!("http://127.0.0.1:{}/hop/0", port), None)
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Too many redirects"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn refuses_other_hosts_when_presenting_a_client_certificate() {
+        let port = start_server().await;
+        let url = format!("http://127.0.0.1:{}/away", port);
+
+        let redirected = get(&url, Some("127.0.0.1")).await.unwrap();
+        assert_eq!(redirected.response.status(), StatusCode::FOUND);
+        assert_eq!(redirected.hops.len(), 1);
+        assert!(redirected.hops[0]
+            .refused
+            .as_ref()
+            .unwrap()
+            .contains("client certificate for 127.0.0.1"));
+
+        let redirected = get(&url, None).await.unwrap();
+        assert_eq!(redirected.response.status(), StatusCode::OK);
+        assert_eq!(redirected.hops[0].refused, None);
+    }
+}
@@ -0,0 +1,492 @@
+use crate::error::PandaError;
+use crate::har;
+use crate::html_report;
+use crate::runner::{Iteration, RequestResult, RunSummary};
+use crate::snapshot::SnapshotOutcome;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReporterKind {
+    /// JUnit XML, one test case per request and per snapshot comparison.
+    Junit,
+    /// Every result with its request and response.
+    Json,
+    /// Test Anything Protocol version 13.
+    Tap,
+    /// A single page that can be opened offline, with the details of each request.
+    Html,
+    /// HTTP Archive 1.2, opened by browser devtools and other HAR viewers.
+    Har,
+}
+
+/// A reporter and where it writes, `None` for the standard output.
+#[derive(Clone, Debug)]
+pub struct Reporter {
+    pub kind: ReporterKind,
+    pub path: Option<PathBuf>,
+}
+
+/// Everything a run produced, the input of the reporters.
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct RunReport {
+    /// The request file or folder that was run, relative to the project root.
+    pub target: String,
+    pub environment: Option<String>,
+    /// RFC 3339 time the run started at.
+    pub started_at: String,
+    pub summary: RunSummary,
+    pub results: Vec<RequestResult>,
+}
+
+impl Reporter {
+    pub fn render(&self, report: &RunReport) -> Result<String, PandaError> {
+        match self.kind {
+            ReporterKind::Junit => Ok(junit(report)),
+            ReporterKind::Json => serde_json::to_string_pretty(report)
+                .map(|json| json + "\n")
+                .map_err(|err| {
+                    PandaError::Io(format!("Failed to serialize report: {}", err).into())
+                }),
+            ReporterKind::Tap => Ok(tap(report)),
+            ReporterKind::Html => Ok(html_report::render(report)),
+            ReporterKind::Har => serde_json::to_string_pretty(&har::from_report(report))
+                .map(|json| json + "\n")
+                .map_err(|err| PandaError::Io(format!("Failed to serialize HAR: {}", err).into())),
+        }
+    }
+
+    /// Writes the report to its file, creating the missing directories.
+    pub fn write(&self, report: &RunReport) -> Result<(), PandaError> {
+        let content = self.render(report)?;
+
+        let path = match &self.path {
+            Some(path) => path,
+            None => {
+                print!("{}", content);
+                return Ok(());
+            }
+        };
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|err| {
+                PandaError::Io(format!("Failed to create {:?}: {}", dir, err).into())
+            })?;
+        }
+
+        fs::write(path, content)
+            .map_err(|err| PandaError::Io(format!("Failed to write {:?}: {}", path, err).into()))
+    }
+}
+
+/// Parses `junit`, `tap=results.tap` or `json=reports/run.json`.
+pub fn parse_reporter(value: &str) -> Result<Reporter, String> {
+    let (name, path) = match value.split_once('=') {
+        Some((name, path)) if !path.is_empty() => (name, Some(PathBuf::from(path))),
+        Some(_) => return Err(format!("Missing the path of reporter {:?}.", value)),
+        None => (value, None),
+    };
+
+    let kind = ReporterKind::from_str(name, true).map_err(|_| {
+        format!(
+            "Unknown reporter {:?}, use junit, json, tap, html or har.",
+            name
+        )
+    })?;
+
+    Ok(Reporter { kind, path })
+}
+
+fn test_name(result: &RequestResult) -> String {
+    iteration_name(&result.file, result.iteration.as_ref())
+}
+
+/// `users/list.toml [2/5 users.csv]` for a request run for a data row.
+fn iteration_name(file: &str, iteration: Option<&Iteration>) -> String {
+    match iteration {
+        Some(i) => format!("{} [{}/{} {}]", file, i.index, i.total, i.data_file),
+        None => file.to_string(),
+    }
+}
+
+/// The JUnit class of a request, its folder with dots or the target at the root.
+fn classname(file: &str, target: &str) -> String {
+    Path::new(file)
+        .parent()
+        .map(|dir| dir.to_string_lossy().replace('/', "."))
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| target.to_string())
+}
+
+/// Why a request did not pass, `None` when it did.
+fn failure_message(result: &RequestResult) -> Option<String> {
+    if let Some(err) = &result.error {
+        return Some(err.to_string());
+    }
+
+    match &result.snapshot {
+        Some(SnapshotOutcome::Changed(changes)) => Some(format!(
+            "Snapshot changed:\n{}",
+            changes
+                .iter()
+                .map(|change| change.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        )),
+        _ => None,
+    }
+}
+
+fn junit(report: &RunReport) -> String {
+    let mut testcases = String::new();
+    let mut tests = 0;
+    let mut failures = 0;
+    let mut errors = 0;
+
+    for result in &report.results {
+        let name = test_name(result);
+        let classname = classname(&result.file, &report.target);
+        let time = result.elapsed_time.unwrap_or_default() / 1000.0;
+
+        let mut output = String::new();
+
+        if let (Some(method), Some(url)) = (&result.method, &result.url) {
+            let _ = write!(output, "{} {}", method, url);
+
+            if let Some(status) = result.status {
+                let _ = write!(output, " -> {}", status);
+            }
+        }
+
+        tests += 1;
+
+        let _ = write!(
+            testcases,
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\">",
+            escape_xml(&name),
+            escape_xml(&classname),
+            time
+        );
+
+        match &result.error {
+            // Failed captures are assertions, anything else stopped the request.
+            Some(err @ PandaError::Assertion(_)) => {
+                failures += 1;
+                let _ = write!(
+                    testcases,
+                    "\n      <failure message=\"{}\" type=\"{}\"/>",
+                    escape_xml(&err.to_string()),
+                    err.kind()
+                );
+            }
+            Some(err) => {
+                errors += 1;
+                let _ = write!(
+                    testcases,
+                    "\n      <error message=\"{}\" type=\"{}\"/>",
+                    escape_xml(&err.to_string()),
+                    err.kind()
+                );
+            }
+            None => {}
+        }
+
+        if !output.is_empty() {
+            let _ = write!(
+                testcases,
+                "\n      <system-out>{}</system-out>",
+                escape_xml(&output)
+            );
+        }
+
+        testcases.push_str("\n    </testcase>\n");
+
+        let snapshot = match &result.snapshot {
+            Some(outcome) => outcome,
+            None => continue,
+        };
+
+        tests += 1;
+
+        let _ = write!(
+            testcases,
+            "    <testcase name=\"{} snapshot\" classname=\"{}\" time=\"0\">",
+            escape_xml(&name),
+            escape_xml(&classname)
+        );
+
+        if let SnapshotOutcome::Changed(_) = snapshot {
+            failures += 1;
+            let _ = write!(
+                testcases,
+                "\n      <failure message=\"Snapshot changed\" type=\"snapshot\">{}</failure>",
+                escape_xml(&failure_message(result).unwrap_or_default())
+            );
+        }
+
+        testcases.push_str("\n    </testcase>\n");
+    }
+
+    for skipped in &report.summary.skipped_requests {
+        tests += 1;
+
+        let _ = writeln!(
+            testcases,
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"0\">\n      <skipped/>\n    </testcase>",
+            escape_xml(&iteration_name(&skipped.file, skipped.iteration.as_ref())),
+            escape_xml(&classname(&skipped.file, &report.target))
+        );
+    }
+
+    let time = report.summary.elapsed_time / 1000.0;
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"panda\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+        tests, failures, errors, report.summary.skipped, time
+    );
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\" timestamp=\"{}\">",
+        escape_xml(&report.target),
+        tests,
+        failures,
+        errors,
+        report.summary.skipped,
+        time,
+        escape_xml(&report.started_at)
+    );
+    xml.push_str(&testcases);
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+
+    xml
+}
+
+fn tap(report: &RunReport) -> String {
+    let mut tap = String::from("TAP version 13\n");
+    let _ = writeln!(tap, "1..{}", report.results.len());
+
+    for (i, result) in report.results.iter().enumerate() {
+        let mut description = test_name(result);
+
+        if let Some(method) = &result.method {
+            description = format!("{} {}", method, description);
+        }
+
+        let message = match failure_message(result) {
+            None => {
+                let _ = writeln!(
+                    tap,
+                    "ok {} - {} ({}, {:.1}ms)",
+                    i + 1,
+                    description,
+                    result.status.unwrap_or_default(),
+                    result.elapsed_time.unwrap_or_default()
+                );
+                continue;
+            }
+            Some(message) => message,
+        };
+
+        let _ = writeln!(tap, "not ok {} - {}", i + 1, description);
+
+        // YAML block, JSON strings are valid YAML scalars.
+        let _ = writeln!(tap, "  ---");
+        let _ = writeln!(tap, "  message: {}", Json(&message));
+
+        if let Some(err) = &result.error {
+            let _ = writeln!(tap, "  kind: {}", err.kind());
+        }
+
+        if let Some(url) = &result.url {
+            let _ = writeln!(tap, "  url: {}", Json(url));
+        }
+
+        if let Some(status) = result.status {
+            let _ = writeln!(tap, "  status: {}", status);
+        }
+
+        let _ = writeln!(tap, "  ...");
+    }
+
+    let summary = &report.summary;
+
+    if summary.cancelled {
+        let _ = writeln!(tap, "Bail out! The run was cancelled.");
+    } else if summary.skipped > 0 {
+        let _ = writeln!(
+            tap,
+            "Bail out! Stopped after a failure, {} requests were skipped.",
+            summary.skipped
+        );
+    }
+
+    let _ = writeln!(
+        tap,
+        "# {} passed, {} failed, {} skipped in {:.1}ms",
+        summary.passed, summary.failed, summary.skipped, summary.elapsed_time
+    );
+
+    tap
+}
+
+/// Formats a string as a JSON literal.
+struct Json<'a>(&'a str);
+
+impl std::fmt::Display for Json<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match serde_json::to_string(self.0) {
+            Ok(json) => f.write_str(&json),
+            Err(_) => Err(std::fmt::Error),
+        }
+    }
+}
+
+/// Escapes markup characters and drops the control characters XML 1.0 does not allow.
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' | '\r' | '\t' => escaped.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::SkippedRequest;
+    use crate::snapshot::SnapshotChange;
+    use serde_json::json;
+
+    fn result(file: &str) -> RequestResult {
+        RequestResult {
+            file: file.to_string(),
+            method: Some("GET".to_string()),
+            url: Some("https://example.com/users".to_string()),
+            status: Some(200),
+            elapsed_time: Some(12.0),
+            ..Default::default()
+        }
+    }
+
+    fn report(results: Vec<RequestResult>, skipped: &[&str]) -> RunReport {
+        RunReport {
+            target: "collection".to_string(),
+            environment: None,
+            started_at: "2024-01-01T00:00:00Z".to_string(),
+            summary: RunSummary {
+                total: results.len() + skipped.len(),
+                passed: results.iter().filter(|r| r.passed()).count(),
+                failed: results.iter().filter(|r| !r.passed()).count(),
+                skipped: skipped.len(),
+                skipped_requests: skipped
+                    .iter()
+                    .map(|file| SkippedRequest {
+                        file: file.to_string(),
+                        iteration: None,
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+            results,
+        }
+    }
+
+    fn changed() -> Option<SnapshotOutcome> {
+        Some(SnapshotOutcome::Changed(vec![SnapshotChange {
+            path: "$.body.id".to_string(),
+            expected: Some(json!(1)),
+            actual: Some(json!(2)),
+        }]))
+    }
+
+    #[test]
+    fn junit_reports_changed_snapshots_as_failures_and_send_errors_as_errors() {
+        let mut snapshot = result("collection/users/list.toml");
+        snapshot.snapshot = changed();
+
+        let mut error = result("collection/users/get.toml");
+        error.status = None;
+        error.error = Some(PandaError::Timeout("Request timed out.".into()));
+
+        let mut capture = result("collection/login.toml");
+        capture.error = Some(PandaError::Assertion(
+            "Nothing matched $.body.token.".into(),
+        ));
+
+        let xml = junit(&report(vec![snapshot, error, capture], &[]));
+
+        assert!(xml.contains(
+            "<testsuites name=\"panda\" tests=\"4\" failures=\"2\" errors=\"1\" skipped=\"0\""
+        ));
+        assert!(xml.contains("<testcase name=\"collection/users/list.toml snapshot\" classname=\"collection.users\" time=\"0\">\n      <failure message=\"Snapshot changed\" type=\"snapshot\">"));
+        assert!(xml.contains("<error message=\"Request timed out.\" type=\"timeout\"/>"));
+        assert!(
+            xml.contains("<failure message=\"Nothing matched $.body.token.\" type=\"assertion\"/>")
+        );
+        assert!(xml.contains("<system-out>GET https://example.com/users -&gt; 200</system-out>"));
+    }
+
+    #[test]
+    fn junit_lists_skipped_requests() {
+        let mut failed = result("collection/1-login.toml");
+        failed.error = Some(PandaError::Assertion("Nothing matched.".into()));
+
+        let xml = junit(&report(vec![failed], &["collection/users/2-me.toml"]));
+
+        assert!(xml.contains("tests=\"2\" failures=\"1\" errors=\"0\" skipped=\"1\""));
+        assert!(xml.contains(
+            "    <testcase name=\"collection/users/2-me.toml\" classname=\"collection.users\" time=\"0\">\n      <skipped/>\n    </testcase>\n"
+        ));
+    }
+
+    #[test]
+    fn tap_reports_failures_with_a_yaml_block() {
+        let mut snapshot = result("collection/list.toml");
+        snapshot.snapshot = changed();
+
+        let mut error = result("collection/get.toml");
+        error.status = None;
+        error.error = Some(PandaError::Connect("Connection refused.".into()));
+
+        let tap = tap(&report(
+            vec![result("collection/ok.toml"), snapshot, error],
+            &["collection/next.toml"],
+        ));
+
+        assert!(tap.starts_with("TAP version 13\n1..3\n"));
+        assert!(tap.contains("ok 1 - GET collection/ok.toml (200, 12.0ms)\n"));
+        assert!(tap.contains(
+            "not ok 2 - GET collection/list.toml\n  ---\n  message: \"Snapshot changed:\\n"
+        ));
+        assert!(tap.contains(
+            "not ok 3 - GET collection/get.toml\n  ---\n  message: \"Connection refused.\"\n  kind: connect\n  url: \"https://example.com/users\"\n  ...\n"
+        ));
+        assert!(tap.contains("Bail out! Stopped after a failure, 1 requests were skipped.\n"));
+    }
+
+    #[test]
+    fn escape_xml_drops_control_characters() {
+        assert_eq!(
+            escape_xml("a\u{0}b\u{1b}[31m\u{8}<c>&\"d'\te\r\n"),
+            "ab[31m&lt;c&gt;&amp;&quot;d&apos;\te\r\n"
+        );
+    }
+}
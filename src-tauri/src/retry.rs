@@ -0,0 +1,279 @@
+use crate::error::PandaError;
+use crate::headers::Header;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use time::format_description::well_known::Rfc2822;
+use time::OffsetDateTime;
+
+/// When and how often a request is sent again.
+///
+/// Delays grow from `delay` by `factor` after each attempt up to `max_delay`, a random
+/// part of up to half of each delay is removed so clients don't retry in lockstep.
+/// A `Retry-After` header replaces the computed delay, still capped by `max_delay`.
+/// `timeout` errors need the request's `timeout` to be set.
+///
+/// ```toml
+/// [get]
+/// url = "https://example.com"
+/// timeout = 5000
+///
+/// [get.retry]
+/// attempts = 4
+/// on_status = [429, 503]
+/// on_error = ["connect", "timeout"]
+/// delay = 200
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Total number of attempts, the first one included.
+    pub attempts: u32,
+    /// Response statuses that are retried.
+    pub on_status: Vec<u16>,
+    /// Error kinds that are retried.
+    pub on_error: Vec<RetryError>,
+    /// Delay before the second attempt in milliseconds.
+    pub delay: u64,
+    pub factor: f64,
+    /// Longest delay between two attempts in milliseconds.
+    pub max_delay: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            attempts: 3,
+            on_status: vec![429, 502, 503, 504],
+            on_error: vec![RetryError::Connect, RetryError::Timeout],
+            delay: 500,
+            factor: 2.0,
+            max_delay: 30_000,
+        }
+    }
+}
+
+/// Failures worth another attempt, named after the kinds of `PandaError`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryError {
+    Connect,
+    Timeout,
+    Dns,
+    Tls,
+    Network,
+    Body,
+}
+
+/// An attempt that was not kept, either its status or its error was retried.
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct RetryAttempt {
+    /// Starts at 1.
+    pub attempt: u32,
+    pub status: Option<u16>,
+    pub error: Option<PandaError>,
+    pub elapsed_time: f64,
+    /// Milliseconds waited before the next attempt.
+    pub delay: f64,
+    /// `true` when the delay came from the `Retry-After` header.
+    pub retry_after: bool,
+}
+
+impl RetryConfig {
+    pub fn should_retry_status(&self, status: u16) -> bool {
+        self.on_status.contains(&status)
+    }
+
+    pub fn should_retry_error(&self, err: &PandaError) -> bool {
+        let kind = match err {
+            PandaError::Connect(_) => RetryError::Connect,
+            PandaError::Timeout(_) => RetryError::Timeout,
+            PandaError::Dns(_) => RetryError::Dns,
+            PandaError::Tls(_) => RetryError::Tls,
+            PandaError::Network(_) => RetryError::Network,
+            PandaError::Body(_) => RetryError::Body,
+            _ => return false,
+        };
+
+        self.on_error.contains(&kind)
+    }
+
+    /// Time to wait after the failed `attempt`, the header's when `Retry-After` is set.
+    pub fn delay_after(&self, attempt: u32, headers: &[Header]) -> (Duration, bool) {
+        let max_delay = Duration::from_millis(self.max_delay);
+
+        if let Some(delay) = retry_after(headers) {
+            return (delay.min(max_delay), true);
+        }
+
+        let backoff = self.backoff(attempt);
+
+        // Equal jitter: between half and all of the backoff.
+        let delay = backoff / 2.0 + backoff / 2.0 * rand::random::<f64>();
+
+        (
+            Duration::from_secs_f64(delay / 1000.0).min(max_delay),
+            false,
+        )
+    }
+
+    /// Delay in milliseconds after the failed `attempt` before jitter.
+    fn backoff(&self, attempt: u32) -> f64 {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+
+        (self.delay as f64 * self.factor.max(1.0).powi(exponent)).min(self.max_delay as f64)
+    }
+}
+
+/// Reads `Retry-After` given either in seconds or as an HTTP date.
+fn retry_after(headers: &[Header]) -> Option<Duration> {
+    let value = headers
+        .iter()
+        .find(|header| header.name.eq_ignore_ascii_case("retry-after"))?
+        .value
+        .trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = OffsetDateTime::parse(value, &Rfc2822).ok()?;
+    let remaining = date - OffsetDateTime::now_utc();
+
+    Some(remaining.try_into().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_runner::{self, RunOptions};
+
+    fn retry_after_header(value: &str) -> Vec<Header> {
+        vec![Header {
+            name: "Retry-After".into(),
+            value: value.into(),
+        }]
+    }
+
+    #[test]
+    fn grows_delays_up_to_the_cap() {
+        let config = RetryConfig {
+            delay: 100,
+            factor: 3.0,
+            max_delay: 2000,
+            ..Default::default()
+        };
+
+        let backoffs: Vec<f64> = (1..=5).map(|attempt| config.backoff(attempt)).collect();
+        assert_eq!(backoffs, [100.0, 300.0, 900.0, 2000.0, 2000.0]);
+        assert_eq!(config.backoff(u32::MAX), 2000.0);
+
+        for attempt in 1..=5 {
+            let (delay, retry_after) = config.delay_after(attempt, &[]);
+            let backoff = config.backoff(attempt);
+            let delay = delay.as_secs_f64() * 1000.0;
+
+            assert!(!retry_after);
+            assert!(
+                delay >= backoff / 2.0 && delay <= backoff,
+                "{} {}",
+                attempt,
+                delay
+            );
+        }
+
+        // Factors below 1 would shrink the delays.
+        let config = RetryConfig {
+            factor: 0.5,
+            ..config
+        };
+        assert_eq!(config.backoff(3), 100.0);
+    }
+
+    #[test]
+    fn follows_retry_after() {
+        let config = RetryConfig {
+            max_delay: 10_000,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            config.delay_after(1, &retry_after_header("3")),
+            (Duration::from_secs(3), true)
+        );
+        assert_eq!(
+            config.delay_after(1, &retry_after_header("120")),
+            (Duration::from_secs(10), true)
+        );
+
+        let in_five_seconds = (OffsetDateTime::now_utc() + time::Duration::seconds(5))
+            .format(&Rfc2822)
+            .unwrap();
+        let (delay, retry_after) = config.delay_after(1, &retry_after_header(&in_five_seconds));
+        assert!(retry_after);
+        assert!(
+            delay > Duration::from_secs(3) && delay <= Duration::from_secs(5),
+            "{:?}",
+            delay
+        );
+
+        assert_eq!(
+            config.delay_after(1, &retry_after_header("Sun, 06 Nov 1994 08:49:37 +0000")),
+            (Duration::ZERO, true)
+        );
+
+        // Values that can't be read fall back to the backoff.
+        assert!(!config.delay_after(1, &retry_after_header("soon")).1);
+    }
+
+    #[test]
+    fn matches_statuses_and_error_kinds() {
+        let config = RetryConfig::default();
+
+        assert!(config.should_retry_status(503));
+        assert!(!config.should_retry_status(500));
+
+        assert!(config.should_retry_error(&PandaError::Connect("refused".into())));
+        assert!(config.should_retry_error(&PandaError::Timeout("slow".into())));
+        assert!(!config.should_retry_error(&PandaError::Dns("unknown".into())));
+        assert!(!config.should_retry_error(&PandaError::Request("invalid".into())));
+
+        let config: RetryConfig = toml::from_str("on_error = [\"dns\", \"body\"]").unwrap();
+        assert_eq!(config.attempts, 3);
+        assert!(config.should_retry_error(&PandaError::Dns("unknown".into())));
+        assert!(!config.should_retry_error(&PandaError::Connect("refused".into())));
+    }
+
+    #[tokio::test]
+    async fn retries_requests_that_time_out() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // Accepts connections and never answers.
+        tokio::spawn(async move {
+            let mut streams = vec![];
+            while let Ok((stream, _)) = listener.accept().await {
+                streams.push(stream);
+            }
+        });
+
+        let source = format!(
+            "[get]\nurl = \"http://127.0.0.1:{}/\"\ntimeout = 100\n\n[get.retry]\nattempts = 2\ndelay = 1\n",
+            port
+        );
+        let options = RunOptions {
+            proxy: Some(crate::config::ProxyConfig {
+                use_env: Some(false),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let (_cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+        let err = http_runner::run_toml_request(&source, &options, cancel_rx)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, PandaError::Timeout(_)), "{:?}", err);
+        assert!(err.to_string().contains("attempt"), "{}", err);
+    }
+}
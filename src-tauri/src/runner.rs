@@ -0,0 +1,1003 @@
+use crate::config::{read_config, SnapshotConfig};
+use crate::error::PandaError;
+use crate::history::{HistoryResponse, Redactor};
+use crate::http_runner::{self, PandaHttpResponse, PandaTomlRequest, ResolvedRequest, RunOptions};
+use crate::iterations::{read_data_file, DataRow};
+use crate::snapshot::{body_value, check_snapshot, snapshot_path, SnapshotOutcome};
+use crate::utils::{normalise_path, parse_toml, PandaCollection};
+use normalize_path::NormalizePath;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use serde_json_path::JsonPath;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::sync::watch::Receiver;
+
+/// Settings of the folder containing it and of its sub folders.
+pub const FOLDER_CONFIG: &str = "_folder.toml";
+
+/// `_folder.toml`
+///
+/// ```toml
+/// order = 2
+/// stop_on_failure = true
+/// iterations = "./data/users.csv"
+/// ```
+#[derive(Deserialize, Clone, Debug, Default, Serialize)]
+pub struct FolderConfig {
+    /// Position among sibling requests and folders, takes precedence over a numeric name
+    /// prefix eg. `01-login`.
+    pub order: Option<i64>,
+    /// Ends the run when a request fails, sub folders inherit it. Defaults to `false`.
+    pub stop_on_failure: Option<bool>,
+    /// CSV or JSON data file relative to the folder, the folder runs once per row.
+    pub iterations: Option<String>,
+}
+
+/// Top level keys of a request file used by the runner, same meaning as in `_folder.toml`.
+#[derive(Deserialize, Default)]
+struct RequestRunConfig {
+    order: Option<i64>,
+    iterations: Option<String>,
+}
+
+/// A request file of a run with the settings of the folders containing it.
+#[derive(Clone, Debug)]
+pub struct RunItem {
+    pub path: PathBuf,
+    pub stop_on_failure: bool,
+    pub iteration: Option<Iteration>,
+}
+
+/// A row of a data file, its columns are available as variables.
+#[derive(Deserialize, Clone, Debug, PartialEq, Serialize)]
+pub struct Iteration {
+    /// Starts at 1.
+    pub index: usize,
+    pub total: usize,
+    pub data_file: String,
+    pub variables: DataRow,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotMode {
+    Check,
+    /// Overwrites the snapshots that changed.
+    Update,
+}
+
+#[derive(Deserialize, Clone, Debug, Default, Serialize)]
+pub struct RunSettings {
+    /// Overrides `stop_on_failure` of every folder.
+    pub stop_on_failure: Option<bool>,
+    /// Compares responses with their snapshots, `None` skips snapshots.
+    pub snapshot: Option<SnapshotMode>,
+    /// CSV or JSON data file, the whole run is repeated once per row.
+    pub data: Option<PathBuf>,
+}
+
+/// Progress of a run, emitted as `collection_run` events to the UI.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent {
+    Started {
+        total: usize,
+    },
+    RequestStarted {
+        index: usize,
+        file: String,
+        iteration: Option<Iteration>,
+    },
+    RequestFinished {
+        index: usize,
+        result: Box<RequestResult>,
+    },
+    Finished {
+        summary: RunSummary,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug, Default, Serialize)]
+pub struct RequestResult {
+    /// Request file relative to the project root.
+    pub file: String,
+    pub method: Option<String>,
+    pub url: Option<String>,
+    pub status: Option<u16>,
+    pub elapsed_time: Option<f64>,
+    /// What was sent and received with secrets redacted, as stored in the history.
+    pub request: Option<ResolvedRequest>,
+    pub response: Option<HistoryResponse>,
+    /// Variables captured from the response, the following requests can use them.
+    pub captured: HashMap<String, String>,
+    pub snapshot: Option<SnapshotOutcome>,
+    /// Problems that didn't fail the request eg. a snapshot ignore expression matching nothing.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    pub iteration: Option<Iteration>,
+    pub error: Option<PandaError>,
+}
+
+impl RequestResult {
+    /// Status codes are not checked, a request fails when it can't be sent, a capture
+    /// finds nothing or its snapshot changed.
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && !matches!(self.snapshot, Some(SnapshotOutcome::Changed(_)))
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default, Serialize)]
+pub struct RunSummary {
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    /// Requests not run because the run stopped or was cancelled.
+    pub skipped: usize,
+    /// The requests counted in `skipped`, in run order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_requests: Vec<SkippedRequest>,
+    /// Total time in milliseconds.
+    pub elapsed_time: f64,
+    pub cancelled: bool,
+    /// Results of each data file row, empty without iterations.
+    pub iterations: Vec<IterationSummary>,
+}
+
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct SkippedRequest {
+    pub file: String,
+    pub iteration: Option<Iteration>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default, Serialize)]
+pub struct IterationSummary {
+    pub data_file: String,
+    pub index: usize,
+    pub passed: usize,
+    pub failed: usize,
+}
+
+/// Runs the requests of `target`, a request file or a folder, one after the other in the
+/// order given by `find_requests`.
+///
+/// Variables captured by a request are added to `options.variables` for the next ones.
+pub async fn run_collection(
+    project_root: &Path,
+    target: &Path,
+    options: RunOptions,
+    settings: &RunSettings,
+    cancelled_rx: Receiver<bool>,
+    mut on_event: impl FnMut(RunEvent),
+) -> Result<RunSummary, PandaError> {
+    let config = read_config(&project_root.to_string_lossy())?;
+    let snapshot_defaults = config.snapshot.unwrap_or_default();
+
+    let mut items = find_requests(target)?;
+
+    if let Some(data) = &settings.data {
+        items = repeat_for_rows(items, data)?;
+    }
+
+    let mut options = options;
+    let started_at = Instant::now();
+
+    let mut summary = RunSummary {
+        total: items.len(),
+        ..Default::default()
+    };
+
+    on_event(RunEvent::Started { total: items.len() });
+
+    for (index, item) in items.iter().enumerate() {
+        if *cancelled_rx.borrow() {
+            summary.cancelled = true;
+            break;
+        }
+
+        let file = relative_file(project_root, &item.path);
+
+        on_event(RunEvent::RequestStarted {
+            index,
+            file: file.clone(),
+            iteration: item.iteration.clone(),
+        });
+
+        let mut result = RequestResult {
+            file,
+            iteration: item.iteration.clone(),
+            ..Default::default()
+        };
+
+        // Row values take precedence over captured and user variables.
+        let iteration_options;
+
+        let request_options = match &item.iteration {
+            Some(iteration) => {
+                let mut o = options.clone();
+                o.variables.extend(iteration.variables.clone());
+
+                iteration_options = o;
+                &iteration_options
+            }
+            None => &options,
+        };
+
+        if let Err(err) = run_request(
+            &item.path,
+            request_options,
+            &snapshot_defaults,
+            settings.snapshot,
+            cancelled_rx.clone(),
+            &mut result,
+        )
+        .await
+        {
+            result.error = Some(err);
+        }
+
+        // The interrupted request is counted as skipped like the ones after it.
+        if *cancelled_rx.borrow() {
+            summary.cancelled = true;
+            break;
+        }
+
+        let passed = result.passed();
+
+        match passed {
+            true => summary.passed += 1,
+            false => summary.failed += 1,
+        };
+
+        if let Some(iteration) = &item.iteration {
+            summary.add_iteration_result(iteration, passed);
+        }
+
+        options.variables.extend(result.captured.clone());
+
+        on_event(RunEvent::RequestFinished {
+            index,
+            result: Box::new(result),
+        });
+
+        if !passed && settings.stop_on_failure.unwrap_or(item.stop_on_failure) {
+            break;
+        }
+    }
+
+    summary.cancelled |= *cancelled_rx.borrow();
+    summary.skipped = summary.total - summary.passed - summary.failed;
+
+    // Requests run in order, the ones left are those after the last finished one.
+    summary.skipped_requests = items[summary.passed + summary.failed..]
+        .iter()
+        .map(|item| SkippedRequest {
+            file: relative_file(project_root, &item.path),
+            iteration: item.iteration.clone(),
+        })
+        .collect();
+
+    summary.elapsed_time = started_at.elapsed().as_secs_f64() * 1000.0;
+
+    on_event(RunEvent::Finished {
+        summary: summary.clone(),
+    });
+
+    Ok(summary)
+}
+
+fn relative_file(project_root: &Path, path: &Path) -> String {
+    normalise_path(
+        path.strip_prefix(project_root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string(),
+    )
+}
+
+impl RunSummary {
+    fn add_iteration_result(&mut self, iteration: &Iteration, passed: bool) {
+        let position = self
+            .iterations
+            .iter()
+            .position(|i| i.index == iteration.index && i.data_file == iteration.data_file);
+
+        let entry = match position {
+            Some(p) => &mut self.iterations[p],
+            None => {
+                self.iterations.push(IterationSummary {
+                    data_file: iteration.data_file.clone(),
+                    index: iteration.index,
+                    ..Default::default()
+                });
+
+                self.iterations.last_mut().unwrap()
+            }
+        };
+
+        match passed {
+            true => entry.passed += 1,
+            false => entry.failed += 1,
+        };
+    }
+}
+
+/// Runs a request file, filling `result` as it goes so a failure keeps what was known.
+async fn run_request(
+    path: &Path,
+    options: &RunOptions,
+    snapshot_defaults: &SnapshotConfig,
+    snapshot: Option<SnapshotMode>,
+    cancelled_rx: Receiver<bool>,
+    result: &mut RequestResult,
+) -> Result<(), PandaError> {
+    let source = fs::read_to_string(path)
+        .map_err(|err| PandaError::Io(format!("Failed to read {:?}: {}", path, err).into()))?;
+
+    let request: PandaTomlRequest =
+        parse_toml(&source, &options.variables, Some(result.file.clone()))?;
+
+    let redactor = Redactor::new(&options.variables);
+
+    if let Some((method, url)) = request.method_and_url() {
+        result.method = Some(method.to_string());
+        result.url = Some(redactor.url(url));
+    }
+
+    let captures = request.captures().cloned().unwrap_or_default();
+
+    let snapshot_config = request
+        .snapshot_config()
+        .cloned()
+        .unwrap_or_default()
+        .merge(snapshot_defaults.clone());
+
+    let cancelled = cancelled_rx.clone();
+
+    let response = http_runner::run_single_request(request, options, cancelled_rx).await?;
+
+    // A cancelled request gets a placeholder response, nothing is captured from it nor
+    // compared with its snapshot.
+    if *cancelled.borrow() {
+        return Err(PandaError::Network("Request was cancelled.".into()));
+    }
+
+    let captured = capture(&response, &captures);
+
+    // Captured values eg. a token are secrets in this response as well.
+    let redactor = match &captured {
+        Ok(captured) => {
+            let mut variables = options.variables.clone();
+            variables.extend(captured.clone());
+
+            Redactor::new(&variables)
+        }
+        Err(_) => redactor,
+    };
+
+    result.url = Some(redactor.url(&response.request.url));
+    result.status = Some(response.status);
+    result.elapsed_time = Some(response.elapsed_time);
+    result.request = Some(redactor.request(&response.request));
+    result.response = Some(redactor.response(&response));
+    result.captured = captured?;
+
+    if let Some(mode) = snapshot {
+        // Each row gets its own snapshot since responses usually differ.
+        let (outcome, warnings) = check_snapshot(
+            &snapshot_path(path, result.iteration.as_ref().map(|i| i.index)),
+            &response,
+            &snapshot_config,
+            mode == SnapshotMode::Update,
+        )?;
+
+        result.snapshot = Some(outcome);
+        result.warnings.extend(warnings);
+    }
+
+    Ok(())
+}
+
+/// Evaluates each capture against `{ "status": 200, "headers": { ... }, "body": ... }`,
+/// header names are lowercase and only the first value of a repeated header is kept.
+fn capture(
+    response: &PandaHttpResponse,
+    captures: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, PandaError> {
+    let mut captured = HashMap::new();
+
+    if captures.is_empty() {
+        return Ok(captured);
+    }
+
+    let mut headers = Map::new();
+
+    for header in response.headers.iter().flatten() {
+        headers
+            .entry(header.name.to_lowercase())
+            .or_insert_with(|| Value::String(header.value.clone()));
+    }
+
+    let document = json!({
+        "status": response.status,
+        "headers": headers,
+        "body": body_value(response),
+    });
+
+    for (name, expression) in captures {
+        let path = JsonPath::parse(expression).map_err(|err| {
+            PandaError::Config(
+                format!("Invalid capture {:?} = {:?}: {}", name, expression, err).into(),
+            )
+        })?;
+
+        let value = match path.query(&document).first() {
+            Some(Value::String(s)) => s.clone(),
+            Some(value) => value.to_string(),
+            None => {
+                return Err(PandaError::Assertion(
+                    format!("Capture {:?} found nothing at {:?}.", name, expression).into(),
+                ))
+            }
+        };
+
+        captured.insert(name.clone(), value);
+    }
+
+    Ok(captured)
+}
+
+/// Lists the request files of `target` depth first.
+///
+/// Siblings are sorted by their `order` key, then by the number their name starts with
+/// eg. `02-create.toml`, then by name. Hidden entries eg. `.panda` are skipped. Folders
+/// and requests with `iterations` are repeated once per row of their data file.
+pub fn find_requests(target: &Path) -> Result<Vec<RunItem>, PandaError> {
+    if target.is_file() {
+        let stop_on_failure = match target.parent() {
+            Some(parent) => read_folder_config(parent)?.stop_on_failure,
+            None => None,
+        };
+
+        let item = RunItem {
+            path: target.to_path_buf(),
+            stop_on_failure: stop_on_failure.unwrap_or(false),
+            iteration: None,
+        };
+
+        return match read_request_config(target).iterations {
+            Some(data) => repeat_for_rows(vec![item], &relative_to(target, &data)),
+            None => Ok(vec![item]),
+        };
+    }
+
+    if !target.is_dir() {
+        return Err(PandaError::Io(
+            format!("{:?} is neither a request file nor a folder.", target).into(),
+        ));
+    }
+
+    let config = read_folder_config(target)?;
+
+    let mut items = vec![];
+    collect_requests(target, config.stop_on_failure.unwrap_or(false), &mut items)?;
+
+    match config.iterations {
+        Some(data) => repeat_for_rows(items, &target.join(data)),
+        None => Ok(items),
+    }
+}
+
+/// A request or folder found while walking the collection.
+struct Child {
+    order: Option<i64>,
+    name: String,
+    path: PathBuf,
+    /// `None` for requests.
+    folder: Option<FolderConfig>,
+    iterations: Option<String>,
+}
+
+fn collect_requests(
+    dir: &Path,
+    stop_on_failure: bool,
+    items: &mut Vec<RunItem>,
+) -> Result<(), PandaError> {
+    let entries = fs::read_dir(dir).map_err(|err| {
+        PandaError::Io(format!("Failed to read collection {:?}: {}", dir, err).into())
+    })?;
+
+    let mut children: Vec<Child> = vec![];
+
+    for entry in entries {
+        let entry = entry.map_err(|err| {
+            PandaError::Io(format!("Failed to read collection {:?}: {}", dir, err).into())
+        })?;
+
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if name.starts_with('.') || name == FOLDER_CONFIG {
+            continue;
+        }
+
+        if path.is_dir() {
+            let config = read_folder_config(&path)?;
+
+            children.push(Child {
+                order: config.order.or_else(|| name_prefix(&name)),
+                iterations: config.iterations.clone(),
+                folder: Some(config),
+                name,
+                path,
+            });
+        } else if path.extension().is_some_and(|e| e == "toml") {
+            let config = read_request_config(&path);
+
+            children.push(Child {
+                order: config.order.or_else(|| name_prefix(&name)),
+                iterations: config.iterations,
+                folder: None,
+                name,
+                path,
+            });
+        }
+    }
+
+    children.sort_by(|a, b| {
+        let a_order = a.order.unwrap_or(i64::MAX);
+        let b_order = b.order.unwrap_or(i64::MAX);
+
+        a_order.cmp(&b_order).then_with(|| a.name.cmp(&b.name))
+    });
+
+    for child in children {
+        let mut child_items = vec![];
+
+        let data_file = match child.folder {
+            Some(config) => {
+                let stop_on_failure = config.stop_on_failure.unwrap_or(stop_on_failure);
+
+                collect_requests(&child.path, stop_on_failure, &mut child_items)?;
+
+                child.iterations.map(|data| child.path.join(data))
+            }
+            None => {
+                let data_file = child.iterations.map(|data| relative_to(&child.path, &data));
+
+                child_items.push(RunItem {
+                    path: child.path,
+                    stop_on_failure,
+                    iteration: None,
+                });
+
+                data_file
+            }
+        };
+
+        match data_file {
+            Some(data_file) => items.extend(repeat_for_rows(child_items, &data_file)?),
+            None => items.extend(child_items),
+        }
+    }
+
+    Ok(())
+}
+
+/// Repeats `items` once per row of `data_file`, rows of nested data files keep their
+/// index and take precedence.
+fn repeat_for_rows(items: Vec<RunItem>, data_file: &Path) -> Result<Vec<RunItem>, PandaError> {
+    let rows = read_data_file(data_file)?;
+    let total = rows.len();
+    let data_file_name = normalise_path(data_file.normalize().to_string_lossy().to_string());
+
+    let mut repeated = Vec::with_capacity(items.len() * total);
+
+    for (i, row) in rows.into_iter().enumerate() {
+        for item in &items {
+            let iteration = match &item.iteration {
+                Some(inner) => Iteration {
+                    variables: row
+                        .clone()
+                        .into_iter()
+                        .chain(inner.variables.clone())
+                        .collect(),
+                    ..inner.clone()
+                },
+                None => Iteration {
+                    index: i + 1,
+                    total,
+                    data_file: data_file_name.clone(),
+                    variables: row.clone(),
+                },
+            };
+
+            repeated.push(RunItem {
+                iteration: Some(iteration),
+                ..item.clone()
+            });
+        }
+    }
+
+    Ok(repeated)
+}
+
+/// Resolves `path` against the folder containing `file`.
+fn relative_to(file: &Path, path: &str) -> PathBuf {
+    match file.parent() {
+        Some(parent) => parent.join(path),
+        None => PathBuf::from(path),
+    }
+}
+
+pub fn read_folder_config(dir: &Path) -> Result<FolderConfig, PandaError> {
+    let path = dir.join(FOLDER_CONFIG);
+
+    if !path.exists() {
+        return Ok(FolderConfig::default());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|err| PandaError::Io(format!("Failed to read {:?}: {}", path, err).into()))?;
+
+    toml::from_str(&content).map_err(|err| {
+        PandaError::from_toml(err, &content, Some(path.to_string_lossy().to_string()))
+    })
+}
+
+/// Reads the run settings without substituting variables, they are ignored when the file
+/// can't be parsed as is.
+fn read_request_config(path: &Path) -> RequestRunConfig {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| toml::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Position of a request file or folder among its siblings, as sorted by the runner.
+pub fn item_order(item: &PandaCollection) -> Option<i64> {
+    let content = match &item.children {
+        Some(children) => children
+            .iter()
+            .find(|child| child.name == FOLDER_CONFIG)
+            .and_then(|child| child.content.as_deref()),
+        None => item.content.as_deref(),
+    };
+
+    content
+        .and_then(|content| toml::from_str::<RequestRunConfig>(content).ok())
+        .and_then(|config| config.order)
+        .or_else(|| name_prefix(&item.name))
+}
+
+/// `12` for `12-delete-user.toml`.
+fn name_prefix(name: &str) -> Option<i64> {
+    let digits: String = name.chars().take_while(|c| c.is_ascii_digit()).collect();
+
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProxyConfig;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn write(root: &Path, file: &str, content: &str) {
+        let path = root.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn files(root: &Path, items: &[RunItem]) -> Vec<String> {
+        items
+            .iter()
+            .map(|item| {
+                normalise_path(
+                    item.path
+                        .strip_prefix(root)
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn orders_by_order_key_then_name_prefix_then_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let request = "[get]\nurl = \"http://localhost\"\n";
+
+        write(root, "b.toml", request);
+        write(root, "a.toml", request);
+        write(root, "10-tenth.toml", request);
+        write(root, "2-two.toml", request);
+        write(root, "02-second.toml", request);
+        write(root, "z.toml", &format!("order = 1\n{}", request));
+        write(root, "01-folder/x.toml", request);
+        write(root, "sub/_folder.toml", "order = 0");
+        write(root, "sub/inner.toml", request);
+        write(root, ".hidden/h.toml", request);
+        write(root, "notes.txt", "");
+
+        let items = find_requests(root).unwrap();
+
+        assert_eq!(
+            files(root, &items),
+            [
+                "sub/inner.toml",
+                "01-folder/x.toml",
+                "z.toml",
+                "02-second.toml",
+                "2-two.toml",
+                "10-tenth.toml",
+                "a.toml",
+                "b.toml",
+            ]
+        );
+    }
+
+    #[test]
+    fn applies_folder_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let request = "[get]\nurl = \"http://localhost\"\n";
+
+        write(root, "_folder.toml", "stop_on_failure = true");
+        write(root, "a.toml", request);
+        write(
+            root,
+            "lenient/_folder.toml",
+            "stop_on_failure = false\niterations = \"rows.csv\"",
+        );
+        write(root, "lenient/rows.csv", "USER\nann\nbob\n");
+        write(root, "lenient/b.toml", request);
+        write(root, "lenient/nested/c.toml", request);
+
+        let items = find_requests(root).unwrap();
+
+        let found: Vec<(String, bool, Option<usize>)> = files(root, &items)
+            .into_iter()
+            .zip(&items)
+            .map(|(file, item)| {
+                (
+                    file,
+                    item.stop_on_failure,
+                    item.iteration.as_ref().map(|i| i.index),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            found,
+            [
+                ("a.toml".to_string(), true, None),
+                ("lenient/b.toml".to_string(), false, Some(1)),
+                ("lenient/nested/c.toml".to_string(), false, Some(1)),
+                ("lenient/b.toml".to_string(), false, Some(2)),
+                ("lenient/nested/c.toml".to_string(), false, Some(2)),
+            ]
+        );
+        assert_eq!(
+            items[3].iteration.as_ref().unwrap().variables["USER"],
+            "bob"
+        );
+
+        // A single file still gets the settings of its folder.
+        let items = find_requests(&root.join("a.toml")).unwrap();
+        assert!(items[0].stop_on_failure);
+
+        write(root, "broken/_folder.toml", "order = \"first\"");
+        assert!(matches!(find_requests(root), Err(PandaError::Parse(_))));
+    }
+
+    /// `/login` returns a token whatever the query, `/me` echoes the `Authorization` header and `/hang` never
+    /// answers.
+    async fn start_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0; 4096];
+                    let read = stream.read(&mut request).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&request[..read]).to_string();
+                    let path = request.split(' ').nth(1).unwrap_or_default();
+                    let path = path.split('?').next().unwrap_or_default();
+
+                    let body = if path == "/login" {
+                        r#"{"token": "t0ken"}"#.to_string()
+                    } else if path == "/me" {
+                        let auth = request
+                            .lines()
+                            .find_map(|line| line.strip_prefix("authorization: "))
+                            .unwrap_or_default();
+                        json!({ "auth": auth }).to_string()
+                    } else {
+                        std::future::pending::<()>().await;
+                        unreachable!()
+                    };
+
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        port
+    }
+
+    fn project(port: u16, requests: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "panda.config.json",
+            r#"{"name": "test", "version": "1", "collection": "./collection"}"#,
+        );
+
+        for (file, content) in requests {
+            let content = content.replace("PORT", &port.to_string());
+            write(dir.path(), &format!("collection/{}", file), &content);
+        }
+
+        dir
+    }
+
+    fn options(root: &Path) -> RunOptions {
+        RunOptions {
+            project_root: root.to_path_buf(),
+            proxy: Some(ProxyConfig {
+                use_env: Some(false),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    async fn run(
+        root: &Path,
+        settings: RunSettings,
+        cancelled_rx: Receiver<bool>,
+    ) -> (RunSummary, Vec<RequestResult>) {
+        let mut results = vec![];
+
+        let summary = run_collection(
+            root,
+            &root.join("collection"),
+            options(root),
+            &settings,
+            cancelled_rx,
+            |event| {
+                if let RunEvent::RequestFinished { result, .. } = event {
+                    results.push(*result);
+                }
+            },
+        )
+        .await
+        .unwrap();
+
+        (summary, results)
+    }
+
+    #[tokio::test]
+    async fn passes_captured_values_to_the_next_requests() {
+        let port = start_server().await;
+        let dir = project(
+            port,
+            &[
+                (
+                    "1-login.toml",
+                    "[get]\nurl = \"http://127.0.0.1:PORT/login\"\n\n[get.capture]\nTOKEN = \"$.body.token\"\nSTATUS = \"$.status\"\n",
+                ),
+                (
+                    "2-me.toml",
+                    "[get]\nurl = \"http://127.0.0.1:PORT/me\"\n\n[get.headers]\nAuthorization = \"Bearer _.TOKEN\"\n",
+                ),
+            ],
+        );
+
+        let (_cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+        let (summary, results) = run(dir.path(), RunSettings::default(), cancel_rx).await;
+
+        assert_eq!((summary.passed, summary.failed), (2, 0));
+        assert_eq!(results[0].captured["TOKEN"], "t0ken");
+        assert_eq!(results[0].captured["STATUS"], "200");
+        // The captured token is redacted like the variables.
+        assert_eq!(
+            results[1].response.as_ref().unwrap().body,
+            r#"{"auth":"Bearer _.TOKEN"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_when_a_capture_finds_nothing() {
+        let port = start_server().await;
+        let dir = project(
+            port,
+            &[
+                ("_folder.toml", "stop_on_failure = true"),
+                (
+                    "1-login.toml",
+                    "[get]\nurl = \"http://127.0.0.1:PORT/login\"\n\n[get.capture]\nTOKEN = \"$.body.missing\"\n",
+                ),
+                ("2-me.toml", "[get]\nurl = \"http://127.0.0.1:PORT/me\"\n"),
+            ],
+        );
+
+        let (_cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+        let (summary, results) = run(dir.path(), RunSettings::default(), cancel_rx).await;
+
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (0, 1, 1));
+        assert_eq!(summary.skipped_requests[0].file, "collection/2-me.toml");
+        assert_eq!(results[0].status, Some(200));
+        assert!(results[0].captured.is_empty());
+        assert!(matches!(results[0].error, Some(PandaError::Assertion(_))));
+    }
+
+    #[tokio::test]
+    async fn cancelled_requests_are_skipped_without_snapshots() {
+        let port = start_server().await;
+        let dir = project(
+            port,
+            &[
+                (
+                    "1-hang.toml",
+                    "[get]\nurl = \"http://127.0.0.1:PORT/hang\"\n",
+                ),
+                (
+                    "2-login.toml",
+                    "[get]\nurl = \"http://127.0.0.1:PORT/login\"\n",
+                ),
+            ],
+        );
+
+        let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            let _ = cancel_tx.send(true);
+        });
+
+        let settings = RunSettings {
+            snapshot: Some(SnapshotMode::Check),
+            ..Default::default()
+        };
+        let (summary, results) = run(dir.path(), settings, cancel_rx).await;
+
+        assert!(summary.cancelled);
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (0, 0, 2));
+        assert_eq!(summary.skipped_requests.len(), 2);
+        assert!(results.is_empty());
+        assert!(!dir.path().join("collection/1-hang.snap.json").exists());
+    }
+
+    #[tokio::test]
+    async fn sends_query_strings_without_quotes() {
+        let port = start_server().await;
+        let dir = project(
+            port,
+            &[(
+                "search.toml",
+                "[get]\nurl = \"http://127.0.0.1:PORT/login?sort=name\"\n\n[get.query]\nq = \"ann lee\"\npage = 2\n",
+            )],
+        );
+
+        let (_cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+        let (summary, results) = run(dir.path(), RunSettings::default(), cancel_rx).await;
+
+        assert_eq!(summary.passed, 1);
+        assert_eq!(
+            results[0].url.as_deref(),
+            Some(format!("http://127.0.0.1:{}/login?sort=name&page=2&q=ann+lee", port).as_str())
+        );
+    }
+}
@@ -0,0 +1,482 @@
+use crate::body::BodyKind;
+use crate::config::SnapshotConfig;
+use crate::error::{ErrorDetails, PandaError, SourceLocation};
+use crate::http_runner::PandaHttpResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use serde_json_path::JsonPath;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Replaces `.toml` for the snapshot stored next to a request eg. `users.snap.json`.
+pub const SNAPSHOT_EXTENSION: &str = "snap.json";
+
+/// Stored in place of values matched by `ignore` expressions.
+const IGNORED: &str = "[ignored]";
+
+#[derive(Deserialize, Clone, Debug, Serialize)]
+#[serde(tag = "outcome", content = "changes", rename_all = "snake_case")]
+pub enum SnapshotOutcome {
+    /// There was no snapshot yet, it has been written.
+    Created,
+    Matched,
+    /// The response changed and the snapshot was overwritten.
+    Updated(Vec<SnapshotChange>),
+    Changed(Vec<SnapshotChange>),
+}
+
+/// A value that differs from the snapshot, `None` when it is missing on one side.
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct SnapshotChange {
+    /// JSONPath of the value in the snapshot eg. `$.body.items[0].name`.
+    pub path: String,
+    pub expected: Option<Value>,
+    pub actual: Option<Value>,
+}
+
+impl fmt::Display for SnapshotChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.expected, &self.actual) {
+            (Some(expected), Some(actual)) => {
+                write!(f, "~ {}: {} -> {}", self.path, expected, actual)
+            }
+            (Some(expected), None) => write!(f, "- {}: {}", self.path, expected),
+            (None, Some(actual)) => write!(f, "+ {}: {}", self.path, actual),
+            (None, None) => write!(f, "  {}", self.path),
+        }
+    }
+}
+
+/// `users.snap.json` for `users.toml`, `users.3.snap.json` for its third data row.
+pub fn snapshot_path(request_path: &Path, iteration: Option<usize>) -> PathBuf {
+    match iteration {
+        Some(index) => request_path.with_extension(format!("{}.{}", index, SNAPSHOT_EXTENSION)),
+        None => request_path.with_extension(SNAPSHOT_EXTENSION),
+    }
+}
+
+/// Compares `response` with the snapshot stored at `path`, the snapshot is written when
+/// it is missing or when `update` is set.
+///
+/// Also returns a warning for each `ignore` expression that matched nothing, usually a
+/// path missing its `$.body` prefix.
+pub fn check_snapshot(
+    path: &Path,
+    response: &PandaHttpResponse,
+    config: &SnapshotConfig,
+    update: bool,
+) -> Result<(SnapshotOutcome, Vec<String>), PandaError> {
+    let (actual, unmatched) = normalise(response, config)?;
+
+    let warnings = unmatched
+        .iter()
+        .map(|expression| {
+            format!(
+                "Snapshot ignore expression {:?} matched nothing, paths start at the snapshot root eg. $.body.id.",
+                expression
+            )
+        })
+        .collect();
+
+    Ok((compare_snapshot(path, &actual, update)?, warnings))
+}
+
+fn compare_snapshot(
+    path: &Path,
+    actual: &Value,
+    update: bool,
+) -> Result<SnapshotOutcome, PandaError> {
+    if !path.exists() {
+        write_snapshot(path, actual)?;
+
+        return Ok(SnapshotOutcome::Created);
+    }
+
+    let content = fs::read_to_string(path).map_err(|err| {
+        PandaError::Io(format!("Failed to read snapshot {:?}: {}", path, err).into())
+    })?;
+
+    let expected: Value = serde_json::from_str(&content).map_err(|err| {
+        PandaError::Parse(ErrorDetails::at(
+            format!("Invalid snapshot: {}", err),
+            SourceLocation::from_json(&err, Some(path.to_string_lossy().to_string())),
+        ))
+    })?;
+
+    let changes = diff(&expected, actual);
+
+    if changes.is_empty() {
+        return Ok(SnapshotOutcome::Matched);
+    }
+
+    if !update {
+        return Ok(SnapshotOutcome::Changed(changes));
+    }
+
+    write_snapshot(path, actual)?;
+
+    Ok(SnapshotOutcome::Updated(changes))
+}
+
+/// The recorded form of a response: its status, the selected headers and the body, along
+/// with the `ignore` expressions that matched nothing.
+///
+/// Object keys are sorted, binary bodies are only compared by size.
+pub fn normalise(
+    response: &PandaHttpResponse,
+    config: &SnapshotConfig,
+) -> Result<(Value, Vec<String>), PandaError> {
+    let default_headers = vec!["content-type".to_string()];
+    let mut headers = Map::new();
+
+    for name in config.headers.as_ref().unwrap_or(&default_headers) {
+        let name = name.to_lowercase();
+
+        let mut values: Vec<Value> = response
+            .headers
+            .iter()
+            .flatten()
+            .filter(|header| header.name.eq_ignore_ascii_case(&name))
+            .map(|header| Value::String(header.value.clone()))
+            .collect();
+
+        match values.len() {
+            0 => {}
+            1 => {
+                headers.insert(name, values.remove(0));
+            }
+            _ => {
+                headers.insert(name, Value::Array(values));
+            }
+        }
+    }
+
+    let mut snapshot = json!({
+        "status": response.status,
+        "headers": headers,
+        "body": body_value(response),
+    });
+
+    let mut unmatched = vec![];
+
+    for expression in &config.ignore {
+        if ignore(&mut snapshot, expression)? == 0 {
+            unmatched.push(expression.clone());
+        }
+    }
+
+    Ok((snapshot, unmatched))
+}
+
+/// JSON bodies as JSON, other text as a string and binary bodies as a description with
+/// their size.
+pub fn body_value(response: &PandaHttpResponse) -> Value {
+    match (&response.text_response, response.body_kind) {
+        (Some(text), BodyKind::Text) => {
+            serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.clone()))
+        }
+        _ if response.size == 0 => Value::Null,
+        _ => Value::String(format!(
+            "<{} bytes of {}>",
+            response.size, response.mime_type
+        )),
+    }
+}
+
+/// Replaces every value of `snapshot` matched by the JSONPath `expression`, returning how
+/// many were.
+fn ignore(snapshot: &mut Value, expression: &str) -> Result<usize, PandaError> {
+    let path = JsonPath::parse(expression).map_err(|err| {
+        PandaError::Config(
+            format!(
+                "Invalid snapshot ignore expression {:?}: {}",
+                expression, err
+            )
+            .into(),
+        )
+    })?;
+
+    let pointers: Vec<String> = path
+        .query_located(snapshot)
+        .locations()
+        .map(|location| location.to_json_pointer())
+        .collect();
+
+    for pointer in &pointers {
+        if let Some(value) = snapshot.pointer_mut(pointer) {
+            *value = Value::String(IGNORED.to_string());
+        }
+    }
+
+    Ok(pointers.len())
+}
+
+fn write_snapshot(path: &Path, snapshot: &Value) -> Result<(), PandaError> {
+    let mut content = serde_json::to_string_pretty(snapshot)
+        .map_err(|err| PandaError::Io(format!("Failed to serialize snapshot: {}", err).into()))?;
+    content.push('\n');
+
+    fs::write(path, content).map_err(|err| {
+        PandaError::Io(format!("Failed to write snapshot {:?}: {}", path, err).into())
+    })
+}
+
+/// Lists the values that differ between two snapshots, objects and arrays are compared
+/// member by member.
+pub fn diff(expected: &Value, actual: &Value) -> Vec<SnapshotChange> {
+    let mut changes = vec![];
+
+    diff_at("$", expected, actual, &mut changes);
+
+    changes
+}
+
+fn diff_at(path: &str, expected: &Value, actual: &Value, changes: &mut Vec<SnapshotChange>) {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, expected_value) in expected {
+                let child = member_path(path, key);
+
+                match actual.get(key) {
+                    Some(actual_value) => diff_at(&child, expected_value, actual_value, changes),
+                    None => changes.push(SnapshotChange {
+                        path: child,
+                        expected: Some(expected_value.clone()),
+                        actual: None,
+                    }),
+                }
+            }
+
+            for (key, actual_value) in actual {
+                if !expected.contains_key(key) {
+                    changes.push(SnapshotChange {
+                        path: member_path(path, key),
+                        expected: None,
+                        actual: Some(actual_value.clone()),
+                    });
+                }
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            for i in 0..expected.len().max(actual.len()) {
+                let child = format!("{}[{}]", path, i);
+
+                match (expected.get(i), actual.get(i)) {
+                    (Some(e), Some(a)) => diff_at(&child, e, a, changes),
+                    (e, a) => changes.push(SnapshotChange {
+                        path: child,
+                        expected: e.cloned(),
+                        actual: a.cloned(),
+                    }),
+                }
+            }
+        }
+        _ if expected != actual => changes.push(SnapshotChange {
+            path: path.to_string(),
+            expected: Some(expected.clone()),
+            actual: Some(actual.clone()),
+        }),
+        _ => {}
+    }
+}
+
+/// `$.name` for plain keys, `$['content-type']` for the others.
+fn member_path(path: &str, key: &str) -> String {
+    let is_plain = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    match is_plain {
+        true => format!("{}.{}", path, key),
+        false => format!(
+            "{}['{}']",
+            path,
+            key.replace('\\', "\\\\").replace('\'', "\\'")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headers::Header;
+
+    fn response(body: &str) -> PandaHttpResponse {
+        PandaHttpResponse {
+            status: 200,
+            headers: Some(vec![
+                Header {
+                    name: "content-type".into(),
+                    value: "application/json".into(),
+                },
+                Header {
+                    name: "set-cookie".into(),
+                    value: "a=1".into(),
+                },
+                Header {
+                    name: "set-cookie".into(),
+                    value: "b=2".into(),
+                },
+                Header {
+                    name: "date".into(),
+                    value: "Mon, 19 Oct 2026 10:00:00 GMT".into(),
+                },
+            ]),
+            text_response: Some(body.to_string()),
+            body_kind: BodyKind::Text,
+            size: body.len(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn normalises_status_selected_headers_and_body() {
+        let config = SnapshotConfig {
+            headers: Some(vec![
+                "Content-Type".into(),
+                "Set-Cookie".into(),
+                "X-Missing".into(),
+            ]),
+            ignore: vec![],
+        };
+
+        let (snapshot, unmatched) =
+            normalise(&response(r#"{"b": 1, "a": [true]}"#), &config).unwrap();
+
+        assert_eq!(
+            snapshot,
+            json!({
+                "status": 200,
+                "headers": {"content-type": "application/json", "set-cookie": ["a=1", "b=2"]},
+                "body": {"a": [true], "b": 1},
+            })
+        );
+        assert!(unmatched.is_empty());
+
+        let (snapshot, _) = normalise(&response("plain"), &SnapshotConfig::default()).unwrap();
+        assert_eq!(snapshot["body"], "plain");
+        assert_eq!(
+            snapshot["headers"],
+            json!({"content-type": "application/json"})
+        );
+
+        let binary = PandaHttpResponse {
+            body_kind: BodyKind::Binary,
+            size: 3,
+            mime_type: "image/png".into(),
+            ..response("")
+        };
+        let (snapshot, _) = normalise(&binary, &SnapshotConfig::default()).unwrap();
+        assert_eq!(snapshot["body"], "<3 bytes of image/png>");
+    }
+
+    #[test]
+    fn ignores_paths_from_the_snapshot_root() {
+        let config = SnapshotConfig {
+            headers: Some(vec!["date".into()]),
+            ignore: vec![
+                "$.body.id".into(),
+                "$.body.items[*].at".into(),
+                "$.headers.date".into(),
+                "$.id".into(),
+            ],
+        };
+        let body = r#"{"id": 7, "items": [{"at": 1, "n": "a"}, {"at": 2, "n": "b"}]}"#;
+
+        let (snapshot, unmatched) = normalise(&response(body), &config).unwrap();
+
+        assert_eq!(
+            snapshot,
+            json!({
+                "status": 200,
+                "headers": {"date": IGNORED},
+                "body": {"id": IGNORED, "items": [{"at": IGNORED, "n": "a"}, {"at": IGNORED, "n": "b"}]},
+            })
+        );
+        // Paths relative to the body match nothing.
+        assert_eq!(unmatched, ["$.id"]);
+
+        let config = SnapshotConfig {
+            headers: None,
+            ignore: vec!["$[".into()],
+        };
+        assert!(matches!(
+            normalise(&response(body), &config),
+            Err(PandaError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn diffs_with_snapshot_paths() {
+        let expected = json!({
+            "status": 200,
+            "headers": {"content-type": "application/json"},
+            "body": {"id": "[ignored]", "name": "a", "tags": ["x", "y"], "old": 1},
+        });
+        let actual = json!({
+            "status": 201,
+            "headers": {"content-type": "application/json"},
+            "body": {"id": "[ignored]", "name": "b", "tags": ["x"], "new-key": true},
+        });
+
+        let changes: Vec<String> = diff(&expected, &actual)
+            .iter()
+            .map(|change| change.to_string())
+            .collect();
+
+        assert_eq!(
+            changes,
+            [
+                "~ $.body.name: \"a\" -> \"b\"",
+                "- $.body.old: 1",
+                "- $.body.tags[1]: \"y\"",
+                "+ $.body['new-key']: true",
+                "~ $.status: 200 -> 201",
+            ]
+        );
+        assert!(diff(&expected, &expected).is_empty());
+    }
+
+    #[test]
+    fn writes_then_compares_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = snapshot_path(&dir.path().join("users.toml"), Some(2));
+        assert!(path.ends_with("users.2.snap.json"));
+
+        let config = SnapshotConfig {
+            headers: None,
+            ignore: vec!["$.body.at".into()],
+        };
+        let check = |body: &str, update: bool| {
+            check_snapshot(&path, &response(body), &config, update).unwrap()
+        };
+
+        assert!(matches!(
+            check(r#"{"at": 1, "n": 1}"#, false).0,
+            SnapshotOutcome::Created
+        ));
+        assert!(matches!(
+            check(r#"{"at": 2, "n": 1}"#, false).0,
+            SnapshotOutcome::Matched
+        ));
+        assert!(matches!(
+            check(r#"{"at": 2, "n": 2}"#, false).0,
+            SnapshotOutcome::Changed(_)
+        ));
+        assert!(matches!(
+            check(r#"{"at": 2, "n": 2}"#, true).0,
+            SnapshotOutcome::Updated(_)
+        ));
+        assert!(matches!(
+            check(r#"{"at": 3, "n": 2}"#, false).0,
+            SnapshotOutcome::Matched
+        ));
+
+        let (_, warnings) = check(r#"{"n": 2}"#, false);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("$.body.at"));
+    }
+}
@@ -0,0 +1,315 @@
+use crate::tls::TlsDetails;
+use hyper_util::client::legacy::connect::Connection;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Phase breakdown of a request in milliseconds with microsecond precision.
+///
+/// Connection phases are `None` when they did not happen eg. `tls` for plain http
+/// or all of them when an existing connection was reused.
+#[derive(Deserialize, Clone, Debug, Default, Serialize)]
+pub struct Timings {
+    pub dns: Option<f64>,
+    pub tcp: Option<f64>,
+    pub tls: Option<f64>,
+    /// From the connection being ready until the response headers arrived.
+    pub ttfb: f64,
+    /// Reading the response body.
+    pub download: f64,
+    pub total: f64,
+}
+
+/// Instants recorded while the client opens a connection.
+#[derive(Clone, Debug, Default)]
+struct ConnectionEvents {
+    connect_start: Option<Instant>,
+    dns_start: Option<Instant>,
+    dns_end: Option<Instant>,
+    tls_start: Option<Instant>,
+    connect_end: Option<Instant>,
+    /// Handshake details of the connection, `None` for plain http.
+    tls: Option<TlsDetails>,
+}
+
+/// Shared between the resolver, the connector layer and the TLS hooks of a single client.
+#[derive(Debug, Default)]
+pub struct ConnectionProbe {
+    events: Mutex<ConnectionEvents>,
+}
+
+impl ConnectionProbe {
+    fn record(&self, update: impl FnOnce(&mut ConnectionEvents)) {
+        update(&mut self.events.lock().unwrap());
+    }
+
+    /// Called when the TLS client hello is about to be sent.
+    pub fn tls_started(&self) {
+        self.record(|e| e.tls_start = Some(Instant::now()));
+    }
+
+    /// Updates the TLS details of the connection being established.
+    pub fn record_tls(&self, update: impl FnOnce(&mut TlsDetails)) {
+        self.record(|e| update(e.tls.get_or_insert_with(TlsDetails::default)));
+    }
+
+    pub fn get_tls_details(&self) -> Option<TlsDetails> {
+        self.events.lock().unwrap().tls.clone()
+    }
+
+    /// Whether a request sent at `sent` went over a connection opened for an earlier one.
+    pub fn is_reused(&self, sent: Instant) -> bool {
+        self.events
+            .lock()
+            .unwrap()
+            .connect_end
+            .is_some_and(|end| end < sent)
+    }
+
+    /// Builds the timings of a request sent at `sent`, whose headers arrived at `headers`
+    /// and whose body was read by `done`.
+    pub fn get_timings(&self, sent: Instant, headers: Instant, done: Instant) -> Timings {
+        let events = self.events.lock().unwrap().clone();
+
+        // Events from an earlier request mean the connection was reused.
+        let connected = events.connect_end.filter(|end| *end >= sent);
+        let ready = connected.unwrap_or(sent);
+
+        let mut timings = Timings {
+            ttfb: ms(ready, headers),
+            download: ms(headers, done),
+            total: ms(sent, done),
+            ..Default::default()
+        };
+
+        if let Some(connect_end) = connected {
+            let tcp_start = events.dns_end.or(events.connect_start).unwrap_or(sent);
+
+            timings.dns = match (events.dns_start, events.dns_end) {
+                (Some(start), Some(end)) => Some(ms(start, end)),
+                _ => None,
+            };
+            timings.tcp = Some(ms(tcp_start, events.tls_start.unwrap_or(connect_end)));
+            timings.tls = events.tls_start.map(|start| ms(start, connect_end));
+        }
+
+        timings
+    }
+}
+
+fn ms(start: Instant, end: Instant) -> f64 {
+    end.saturating_duration_since(start).as_micros() as f64 / 1000.0
+}
+
+/// Lookup failure of `TimedResolver`, tells DNS errors apart from the other connect errors.
+#[derive(Debug)]
+pub struct DnsError {
+    name: String,
+    source: std::io::Error,
+}
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to resolve {}", self.name)
+    }
+}
+
+impl Error for DnsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Resolves hostnames through the system resolver while timing the lookup.
+pub struct TimedResolver {
+    probe: Arc<ConnectionProbe>,
+}
+
+impl TimedResolver {
+    pub fn new(probe: Arc<ConnectionProbe>) -> Self {
+        TimedResolver { probe }
+    }
+}
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let probe = Arc::clone(&self.probe);
+
+        Box::pin(async move {
+            probe.record(|e| e.dns_start = Some(Instant::now()));
+
+            let addrs = tokio::net::lookup_host((name.as_str(), 0)).await;
+
+            probe.record(|e| e.dns_end = Some(Instant::now()));
+
+            let addrs = addrs.map_err(|source| DnsError {
+                name: name.as_str().to_string(),
+                source,
+            })?;
+
+            let addrs: Addrs = Box::new(addrs.collect::<Vec<SocketAddr>>().into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Connector layer timing connection establishment, DNS and TLS included.
+#[derive(Clone)]
+pub struct ProbeLayer {
+    probe: Arc<ConnectionProbe>,
+}
+
+impl ProbeLayer {
+    pub fn new(probe: Arc<ConnectionProbe>) -> Self {
+        ProbeLayer { probe }
+    }
+}
+
+impl<S> Layer<S> for ProbeLayer {
+    type Service = ProbeService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ProbeService {
+            inner,
+            probe: Arc::clone(&self.probe),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ProbeService<S> {
+    inner: S,
+    probe: Arc<ConnectionProbe>,
+}
+
+impl<S, Req> Service<Req> for ProbeService<S>
+where
+    S: Service<Req>,
+    S::Response: Connection,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let probe = Arc::clone(&self.probe);
+
+        probe.record(|e| {
+            *e = ConnectionEvents {
+                connect_start: Some(Instant::now()),
+                ..Default::default()
+            }
+        });
+
+        let connecting = self.inner.call(req);
+
+        Box::pin(async move {
+            let conn = connecting.await;
+
+            // hyper only learns the selected protocol when it is `h2`.
+            let h2 = conn
+                .as_ref()
+                .is_ok_and(|conn| conn.connected().is_negotiated_h2());
+
+            probe.record(|e| {
+                e.connect_end = Some(Instant::now());
+
+                if let Some(tls) = &mut e.tls {
+                    tls.alpn = h2.then(|| "h2".to_string());
+                }
+            });
+
+            conn
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn splits_a_new_tls_connection_into_phases() {
+        let sent = Instant::now();
+        let probe = ConnectionProbe::default();
+        probe.record(|e| {
+            e.connect_start = Some(sent);
+            e.dns_start = Some(at(sent, 1));
+            e.dns_end = Some(at(sent, 5));
+            e.tls_start = Some(at(sent, 15));
+            e.connect_end = Some(at(sent, 45));
+        });
+
+        let timings = probe.get_timings(sent, at(sent, 95), at(sent, 100));
+
+        assert_eq!(timings.dns, Some(4.0));
+        assert_eq!(timings.tcp, Some(10.0));
+        assert_eq!(timings.tls, Some(30.0));
+        assert_eq!(timings.ttfb, 50.0);
+        assert_eq!(timings.download, 5.0);
+        assert_eq!(timings.total, 100.0);
+        assert!(!probe.is_reused(sent));
+    }
+
+    #[test]
+    fn plain_http_has_no_tls_phase() {
+        let sent = Instant::now();
+        let probe = ConnectionProbe::default();
+        probe.record(|e| {
+            e.connect_start = Some(sent);
+            e.dns_start = Some(sent);
+            e.dns_end = Some(at(sent, 2));
+            e.connect_end = Some(at(sent, 10));
+        });
+
+        let timings = probe.get_timings(sent, at(sent, 30), at(sent, 30));
+
+        assert_eq!(timings.dns, Some(2.0));
+        assert_eq!(timings.tcp, Some(8.0));
+        assert_eq!(timings.tls, None);
+        assert_eq!(timings.ttfb, 20.0);
+        assert!(probe.get_tls_details().is_none());
+    }
+
+    #[test]
+    fn reused_connection_has_no_connection_phases() {
+        let opened = Instant::now();
+        let probe = ConnectionProbe::default();
+        probe.record(|e| {
+            e.connect_start = Some(opened);
+            e.dns_start = Some(opened);
+            e.dns_end = Some(at(opened, 2));
+            e.tls_start = Some(at(opened, 4));
+            e.connect_end = Some(at(opened, 10));
+        });
+
+        let sent = at(opened, 50);
+        let timings = probe.get_timings(sent, at(sent, 20), at(sent, 25));
+
+        assert!(probe.is_reused(sent));
+        assert_eq!(timings.dns, None);
+        assert_eq!(timings.tcp, None);
+        assert_eq!(timings.tls, None);
+        assert_eq!(timings.ttfb, 20.0);
+        assert_eq!(timings.total, 25.0);
+    }
+}
@@ -0,0 +1,392 @@
+use crate::config::ClientCertificate;
+use crate::timings::ConnectionProbe;
+use crate::utils::replace_variables;
+use glob::Pattern;
+use p12_keystore::KeyStore;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::{
+    ClientSessionMemoryCache, ClientSessionStore, Resumption, WebPkiServerVerifier,
+};
+use rustls::crypto::ring;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::{
+    ClientConfig, DigitallySignedStruct, DistinguishedName, KeyLog, NamedGroup, RootCertStore,
+    SignatureScheme,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use x509_parser::extensions::GeneralName;
+
+/// Number of TLS sessions remembered per client.
+const SESSION_CACHE_SIZE: usize = 32;
+
+/// Roots of the OS trust store, read once as clients are built per request.
+static NATIVE_ROOTS: OnceLock<Vec<CertificateDer<'static>>> = OnceLock::new();
+
+/// Negotiated parameters of a TLS connection.
+#[derive(Deserialize, Clone, Debug, Default, Serialize)]
+pub struct TlsDetails {
+    /// `TLSv1.2` or `TLSv1.3`.
+    pub version: String,
+    /// IANA name eg. `TLS13_AES_128_GCM_SHA256`, read from the session ticket of TLS 1.3
+    /// connections. `None` for TLS 1.2 and servers that don't send tickets since rustls
+    /// doesn't expose the suite otherwise.
+    pub cipher_suite: Option<String>,
+    /// `h2` when the server selected HTTP/2, the connection doesn't tell whether `None`
+    /// means the server selected `http/1.1` or ignored ALPN.
+    pub alpn: Option<String>,
+    /// Chain sent by the server, leaf certificate first. Empty for resumed sessions since
+    /// the server doesn't send it again.
+    pub peer_certificates: Vec<PeerCertificate>,
+}
+
+#[derive(Deserialize, Clone, Debug, Serialize)]
+pub struct PeerCertificate {
+    pub subject: String,
+    pub issuer: String,
+    pub serial_number: String,
+    /// Subject alternative names eg. `DNS:example.com` or `IP:127.0.0.1`.
+    pub subject_alt_names: Vec<String>,
+    /// Unix timestamps in seconds.
+    pub not_before: i64,
+    pub not_after: i64,
+}
+
+type ClientIdentity = (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>);
+
+/// Builds the TLS config for requests to `host`, along with the client certificate it
+/// presents.
+///
+/// Servers are verified against the bundled Mozilla roots and the OS trust store. The first
+/// client certificate whose host glob matches `host` is presented and its `ca` bundle
+/// trusted, handshake progress and the negotiated parameters are reported to `probe`.
+pub fn build_tls_config<'a>(
+    certificates: &'a [ClientCertificate],
+    host: &str,
+    project_root: &Path,
+    variables: &HashMap<String, String>,
+    probe: Arc<ConnectionProbe>,
+) -> Result<(ClientConfig, Option<&'a ClientCertificate>), String> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+    // The OS store holds private CAs eg. of a corporate proxy intercepting TLS, certificates
+    // it fails to parse are skipped like browsers do.
+    let native = NATIVE_ROOTS.get_or_init(|| rustls_native_certs::load_native_certs().certs);
+    roots.add_parsable_certificates(native.iter().cloned());
+
+    let certificate = find_client_certificate(certificates, host)?;
+    let mut identity = None;
+
+    if let Some(certificate) = certificate {
+        identity = Some(load_identity(certificate, project_root, variables)?);
+
+        if let Some(ca) = &certificate.ca {
+            for root in read_pem_certs(project_root, ca)? {
+                roots
+                    .add(root)
+                    .map_err(|err| format!("Invalid CA bundle {:?}: {}", ca, err))?;
+            }
+        }
+    }
+
+    let provider = Arc::new(ring::default_provider());
+
+    let verifier = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .build()
+        .map_err(|err| err.to_string())?;
+
+    let builder = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|err| err.to_string())?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(ProbeVerifier {
+            inner: verifier,
+            probe: Arc::clone(&probe),
+        }));
+
+    let mut config = match identity {
+        Some((chain, key)) => builder
+            .with_client_auth_cert(chain, key)
+            .map_err(|err| format!("Invalid client certificate for {:?}: {}", host, err))?,
+        None => builder.with_no_client_auth(),
+    };
+
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    config.resumption = Resumption::store(Arc::new(ProbeSessionStore {
+        inner: ClientSessionMemoryCache::new(SESSION_CACHE_SIZE),
+        probe: Arc::clone(&probe),
+    }));
+    config.key_log = Arc::new(ProbeKeyLog { probe });
+
+    Ok((config, certificate))
+}
+
+/// The first client certificate whose host glob matches `host`.
+fn find_client_certificate<'a>(
+    certificates: &'a [ClientCertificate],
+    host: &str,
+) -> Result<Option<&'a ClientCertificate>, String> {
+    let host = host.to_lowercase();
+
+    for certificate in certificates {
+        let pattern = Pattern::new(&certificate.host.to_lowercase()).map_err(|err| {
+            format!(
+                "Invalid client certificate host {:?}: {}",
+                certificate.host, err
+            )
+        })?;
+
+        if pattern.matches(&host) {
+            return Ok(Some(certificate));
+        }
+    }
+
+    Ok(None)
+}
+
+fn load_identity(
+    certificate: &ClientCertificate,
+    project_root: &Path,
+    variables: &HashMap<String, String>,
+) -> Result<ClientIdentity, String> {
+    match certificate {
+        ClientCertificate {
+            pkcs12: Some(pkcs12),
+            ..
+        } => {
+            let passphrase = certificate
+                .passphrase
+                .as_deref()
+                .map(|p| replace_variables(p, variables))
+                .unwrap_or_default();
+
+            read_pkcs12(&read_file(project_root, pkcs12)?, &passphrase)
+        }
+        ClientCertificate {
+            cert: Some(cert), ..
+        } => {
+            let chain = read_pem_certs(project_root, cert)?;
+
+            // The key is optional since the certificate file may already bundle it.
+            let key_file = certificate.key.as_deref().unwrap_or(cert);
+            let key_pem = read_file(project_root, key_file)?;
+
+            let key = rustls_pemfile::private_key(&mut key_pem.as_slice())
+                .map_err(|err| format!("Invalid private key {:?}: {}", key_file, err))?
+                .ok_or_else(|| format!("No private key found in {:?}.", key_file))?;
+
+            Ok((chain, key))
+        }
+        _ => Err(format!(
+            "Client certificate for {:?} requires either `cert` or `pkcs12`.",
+            certificate.host
+        )),
+    }
+}
+
+/// Reads the first private key chain of a PKCS#12 archive.
+fn read_pkcs12(data: &[u8], passphrase: &str) -> Result<ClientIdentity, String> {
+    let keystore = KeyStore::from_pkcs12(data, passphrase)
+        .map_err(|err| format!("Unable to read PKCS#12 archive: {}", err))?;
+
+    let (_, chain) = keystore
+        .private_key_chain()
+        .ok_or("PKCS#12 archive does not contain a private key.")?;
+
+    let certs = chain
+        .chain()
+        .iter()
+        .map(|cert| CertificateDer::from(cert.as_der().to_vec()))
+        .collect();
+
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(chain.key().to_vec()));
+
+    Ok((certs, key))
+}
+
+fn read_pem_certs(project_root: &Path, path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    rustls_pemfile::certs(&mut read_file(project_root, path)?.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("Invalid certificate {:?}: {}", path, err))
+}
+
+fn read_peer_certificate(der: &CertificateDer<'_>) -> Option<PeerCertificate> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der.as_ref()).ok()?;
+
+    let subject_alt_names = match cert.subject_alternative_name() {
+        Ok(Some(extension)) => extension
+            .value
+            .general_names
+            .iter()
+            .map(|name| match name {
+                GeneralName::DNSName(dns) => format!("DNS:{}", dns),
+                GeneralName::RFC822Name(email) => format!("email:{}", email),
+                GeneralName::URI(uri) => format!("URI:{}", uri),
+                GeneralName::IPAddress(ip) => match ip.len() {
+                    4 => format!("IP:{}", IpAddr::from(<[u8; 4]>::try_from(*ip).unwrap())),
+                    16 => format!("IP:{}", IpAddr::from(<[u8; 16]>::try_from(*ip).unwrap())),
+                    _ => name.to_string(),
+                },
+                _ => name.to_string(),
+            })
+            .collect(),
+        _ => vec![],
+    };
+
+    Some(PeerCertificate {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        serial_number: cert.raw_serial_as_string(),
+        subject_alt_names,
+        not_before: cert.validity().not_before.timestamp(),
+        not_after: cert.validity().not_after.timestamp(),
+    })
+}
+
+fn read_file(project_root: &Path, path: &str) -> Result<Vec<u8>, String> {
+    let path = project_root.join(path);
+
+    fs::read(&path).map_err(|err| format!("Failed to read {:?}: {}", path, err))
+}
+
+/// Session cache reporting when a handshake starts, rustls asks it for a key share
+/// hint right before sending the client hello, and the cipher suite of the tickets
+/// TLS 1.3 servers send once connected.
+#[derive(Debug)]
+struct ProbeSessionStore {
+    inner: ClientSessionMemoryCache,
+    probe: Arc<ConnectionProbe>,
+}
+
+impl ClientSessionStore for ProbeSessionStore {
+    fn set_kx_hint(&self, server_name: ServerName<'static>, group: NamedGroup) {
+        self.inner.set_kx_hint(server_name, group)
+    }
+
+    fn kx_hint(&self, server_name: &ServerName<'_>) -> Option<NamedGroup> {
+        self.probe.tls_started();
+        self.inner.kx_hint(server_name)
+    }
+
+    fn set_tls12_session(
+        &self,
+        server_name: ServerName<'static>,
+        value: rustls::client::Tls12ClientSessionValue,
+    ) {
+        self.inner.set_tls12_session(server_name, value)
+    }
+
+    fn tls12_session(
+        &self,
+        server_name: &ServerName<'_>,
+    ) -> Option<rustls::client::Tls12ClientSessionValue> {
+        self.inner.tls12_session(server_name)
+    }
+
+    fn remove_tls12_session(&self, server_name: &ServerName<'static>) {
+        self.inner.remove_tls12_session(server_name)
+    }
+
+    fn insert_tls13_ticket(
+        &self,
+        server_name: ServerName<'static>,
+        value: rustls::client::Tls13ClientSessionValue,
+    ) {
+        let suite = value.suite().common.suite;
+        self.probe
+            .record_tls(|tls| tls.cipher_suite = Some(format!("{:?}", suite)));
+
+        self.inner.insert_tls13_ticket(server_name, value)
+    }
+
+    fn take_tls13_ticket(
+        &self,
+        server_name: &ServerName<'static>,
+    ) -> Option<rustls::client::Tls13ClientSessionValue> {
+        self.inner.take_tls13_ticket(server_name)
+    }
+}
+
+/// Reports the protocol version, rustls derives differently labelled secrets for TLS 1.2
+/// and 1.3 handshakes, resumed ones included. The secrets themselves are dropped.
+#[derive(Debug)]
+struct ProbeKeyLog {
+    probe: Arc<ConnectionProbe>,
+}
+
+impl KeyLog for ProbeKeyLog {
+    fn log(&self, label: &str, _client_random: &[u8], _secret: &[u8]) {
+        let version = match label {
+            "CLIENT_RANDOM" => "TLSv1.2",
+            "CLIENT_HANDSHAKE_TRAFFIC_SECRET" => "TLSv1.3",
+            _ => return,
+        };
+
+        self.probe.record_tls(|tls| tls.version = version.into());
+    }
+
+    fn will_log(&self, label: &str) -> bool {
+        matches!(label, "CLIENT_RANDOM" | "CLIENT_HANDSHAKE_TRAFFIC_SECRET")
+    }
+}
+
+/// Default verifier reporting the peer chain to the probe.
+#[derive(Debug)]
+struct ProbeVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    probe: Arc<ConnectionProbe>,
+}
+
+impl ServerCertVerifier for ProbeVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        // Recorded before verifying so rejected chains can be inspected too.
+        let certificates = std::iter::once(end_entity)
+            .chain(intermediates)
+            .filter_map(read_peer_certificate)
+            .collect();
+
+        self.probe
+            .record_tls(|tls| tls.peer_certificates = certificates);
+
+        self.inner
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+
+    fn root_hint_subjects(&self) -> Option<&[DistinguishedName]> {
+        self.inner.root_hint_subjects()
+    }
+}
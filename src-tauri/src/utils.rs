@@ -1,11 +1,13 @@
-use anyhow::Context;
+use crate::error::{ErrorDetails, PandaError, SourceLocation};
 use fancy_regex::Regex;
 use normalize_path::NormalizePath;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 use uuid::Uuid;
 use walkdir::WalkDir;
 
@@ -27,30 +29,27 @@ pub struct PandaCollection {
 pub fn get_collection_from_path(
     path: String,
     mut collections: Vec<PandaCollection>,
-) -> Result<Vec<PandaCollection>, String> {
+) -> Result<Vec<PandaCollection>, PandaError> {
     for entry in WalkDir::new(path.clone()).min_depth(1).max_depth(1) {
-        let entry =
-            match entry.with_context(|| format!("Failed to read collection: {:?}", path.clone())) {
-                Ok(dir) => dir,
-                Err(err) => return Err(err.to_string()),
-            };
+        let entry = match entry {
+            Ok(dir) => dir,
+            Err(err) => {
+                return Err(PandaError::Io(
+                    format!("Failed to read collection {:?}: {}", path, err).into(),
+                ))
+            }
+        };
 
         if entry.file_type().is_dir() {
             let path = Path::new(entry.path());
 
-            let file_path = path
-                .normalize()
-                .to_str()
-                .expect("should be a valid utf8 string")
-                .to_string();
+            let file_path = path.normalize().to_string_lossy().to_string();
 
             let file_name = path
                 .normalize()
                 .file_name()
-                .expect("should be a valid utf8 string")
-                .to_str()
-                .unwrap()
-                .to_string();
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
 
             let clean_path = normalise_path(file_path);
 
@@ -73,24 +72,24 @@ pub fn get_collection_from_path(
         if entry.file_type().is_file() {
             let path = Path::new(entry.path());
 
-            let file_path = path
-                .normalize()
-                .to_str()
-                .expect("should be a valid utf8 string")
-                .to_string();
+            let file_path = path.normalize().to_string_lossy().to_string();
 
             let file_name = path
                 .normalize()
                 .file_name()
-                .expect("should be a valid utf8 string")
-                .to_str()
-                .unwrap()
-                .to_string();
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
 
             let clean_path = normalise_path(file_path);
 
-            let contents = fs::read_to_string(clean_path.clone())
-                .expect("Should have been able to read the file");
+            let contents = match fs::read_to_string(&clean_path) {
+                Ok(c) => c,
+                Err(err) => {
+                    return Err(PandaError::Io(
+                        format!("Failed to read {:?}: {}", clean_path, err).into(),
+                    ))
+                }
+            };
 
             collections.push(PandaCollection {
                 id: Uuid::new_v4().to_string(),
@@ -120,6 +119,24 @@ pub fn get_collection_from_path(
 //     }
 // }
 
+/// Directory holding local project state eg. cookies, it is never committed.
+pub const PANDA_DIR: &str = ".panda";
+
+/// Returns the project's `.panda` directory, creating it with its own `.gitignore` if needed.
+pub fn get_panda_dir(project_root: &Path) -> Result<PathBuf, String> {
+    let dir = project_root.join(PANDA_DIR);
+
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|err| format!("Failed to create {:?}: {}", dir, err))?;
+
+        // Cookies may hold secrets so keep them out of git.
+        fs::write(dir.join(".gitignore"), "*\n")
+            .map_err(|err| format!("Failed to create {:?}: {}", dir, err))?;
+    }
+
+    Ok(dir)
+}
+
 pub fn normalise_path(dir: String) -> String {
     let mut path = String::from(dir);
 
@@ -132,6 +149,27 @@ pub fn normalise_path(dir: String) -> String {
     path
 }
 
+/// Percent encodes everything but the unreserved characters, `_.VAR_NAME` variables
+/// are kept as is.
+pub fn encode_component(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+/// Matches a `_.VAR_NAME` variable, the name is the first capture group.
+pub static VARIABLE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"_\.([A-Za-z]+(?:_[A-Za-z]+)?)").unwrap());
+
 /// Extracts unique variable names from a given string.
 ///
 /// Variables are expected to be in the format `_.VAR_NAME`, where `VAR_NAME`
@@ -142,30 +180,25 @@ pub fn normalise_path(dir: String) -> String {
 ///
 /// # Returns
 /// A vector of unique variable names (without the `_.` prefix).
-fn get_variables(code: &str) -> Vec<String> {
-    let re = Regex::new(r"(?<=_\.)[A-Za-z]+(?:_[A-Za-z]+)?").unwrap();
+pub fn get_variables(code: &str) -> Vec<String> {
     let mut unique_vars = HashSet::new();
 
-    for caps in re.find_iter(code).flatten() {
-        unique_vars.insert(caps.as_str().to_string());
+    for caps in VARIABLE_REGEX.captures_iter(code).flatten() {
+        unique_vars.insert(caps[1].to_string());
     }
 
     unique_vars.into_iter().collect()
 }
 
-/// Formats a vector of variable names into a single string in the format:
-/// `_.VAR_1|_.VAR_2|_.VAR_3`
-///
-/// # Arguments
-/// * `vars` - A vector of variable names (without `_.` prefix).
-///
-/// # Returns
-/// A formatted string joining variables with `|`.
-fn format_vec_to_regex(vars: Vec<String>) -> String {
-    vars.iter()
-        .map(|s| format!("_.{}", s))
-        .collect::<Vec<String>>()
-        .join("|")
+/// Whether `name` can be written as `_.name`, letters with at most one underscore
+/// between them.
+pub fn is_variable_name(name: &str) -> bool {
+    let variable = format!("_.{}", name);
+
+    matches!(
+        VARIABLE_REGEX.find(&variable),
+        Ok(Some(m)) if m.start() == 0 && m.end() == variable.len()
+    )
 }
 
 /// Demonstrates the replacement of variables in a code string.
@@ -175,47 +208,139 @@ fn format_vec_to_regex(vars: Vec<String>) -> String {
 /// - `API_KEY` -> "api_1230202"
 ///
 /// Example input:
-/// ```
+/// ```text
 /// "https://_.BASE_URL/api/_.API_KEY?redirect=_.BASE_URL"
 /// ```
 /// Expected output:
-/// ```
+/// ```text
 /// "https://example.com/api/api_1230202?redirect=example.com"
 /// ```
 pub fn replace_variables(code: &str, vars: &HashMap<String, String>) -> String {
-    let extracted_vars = get_variables(code);
-    let regex_pattern = format_vec_to_regex(extracted_vars);
-
-    let re = Regex::new(&regex_pattern).unwrap();
-
-    re.replace_all(code, |caps: &fancy_regex::Captures<'_>| {
-        let full_match = caps.get(0).expect("Unable to capture group").as_str();
-
-        if full_match.len() > 0 {
-            let var_name = &full_match[2..]; // Extracts "VAR_NAME" from "_.VAR_NAME"
-            let new_var = vars
-                .get(var_name)
+    VARIABLE_REGEX
+        .replace_all(code, |caps: &fancy_regex::Captures<'_>| {
+            vars.get(&caps[1])
                 .cloned()
-                .unwrap_or_else(|| full_match.to_string());
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .to_string()
+}
 
-            new_var
-        } else {
-            "".to_string()
-        }
-    })
-    .to_string()
+/// Parses a TOML request and substitutes `vars` in its strings and keys.
+///
+/// Substituting after parsing keeps values from changing the structure of the request eg.
+/// a quote ending a string early, errors point at `source` as written.
+pub fn parse_toml<T: DeserializeOwned>(
+    source: &str,
+    vars: &HashMap<String, String>,
+    file: Option<String>,
+) -> Result<T, PandaError> {
+    let located = |err| PandaError::from_toml(err, source, file.clone());
+
+    toml::from_str::<T>(source).map_err(located)?;
+    let table: toml::Table = toml::from_str(source).map_err(located)?;
+
+    replace_toml_variables(toml::Value::Table(table), vars)
+        .try_into()
+        .map_err(|err: toml::de::Error| PandaError::Parse(err.message().to_string().into()))
+}
+
+fn replace_toml_variables(value: toml::Value, vars: &HashMap<String, String>) -> toml::Value {
+    match value {
+        toml::Value::String(text) => toml::Value::String(replace_variables(&text, vars)),
+        toml::Value::Array(values) => toml::Value::Array(
+            values
+                .into_iter()
+                .map(|value| replace_toml_variables(value, vars))
+                .collect(),
+        ),
+        toml::Value::Table(table) => toml::Value::Table(
+            table
+                .into_iter()
+                .map(|(key, value)| {
+                    (
+                        replace_variables(&key, vars),
+                        replace_toml_variables(value, vars),
+                    )
+                })
+                .collect(),
+        ),
+        value => value,
+    }
 }
 
-pub fn variables_to_hashmap(code: &str) -> HashMap<String, String> {
+pub fn variables_to_hashmap(code: &str) -> Result<HashMap<String, String>, PandaError> {
     let mut vars = HashMap::new();
-    let valid_json_str: Value = serde_json::from_str(code).expect("Failed to parse JSON");
 
-    let obj = valid_json_str.as_object().unwrap();
+    let valid_json_str: Value = serde_json::from_str(code).map_err(|err| {
+        PandaError::Variable(ErrorDetails::at(
+            format!("Invalid variables: {}", err),
+            SourceLocation::from_json(&err, None),
+        ))
+    })?;
+
+    let obj = match valid_json_str.as_object() {
+        Some(o) => o,
+        None => {
+            return Err(PandaError::Variable(
+                "Variables should be a JSON object.".into(),
+            ))
+        }
+    };
 
     for (key, value) in obj {
         let trimmed_value = value.to_string().trim_matches('"').to_string();
         vars.insert(key.clone(), trimmed_value);
     }
 
-    vars
+    Ok(vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Request {
+        url: String,
+        body: String,
+        headers: HashMap<String, String>,
+    }
+
+    #[test]
+    fn substitutes_variables_after_parsing() {
+        let source = r#"
+url = "https://example.com/_.ID"
+body = '''{"name": "_.NAME"}'''
+headers = { "_.HEADER" = "_.VALUE" }
+"#;
+        let vars = HashMap::from([
+            (
+                "ID".to_string(),
+                "1\"\nurl = \"https://evil.com".to_string(),
+            ),
+            ("NAME".to_string(), "''' quotes".to_string()),
+            ("HEADER".to_string(), "X-Id".to_string()),
+            ("VALUE".to_string(), "a\\b".to_string()),
+        ]);
+
+        let request: Request = parse_toml(source, &vars, None).unwrap();
+
+        assert_eq!(
+            request.url,
+            "https://example.com/1\"\nurl = \"https://evil.com"
+        );
+        assert_eq!(request.body, r#"{"name": "''' quotes"}"#);
+        assert_eq!(request.headers["X-Id"], "a\\b");
+    }
+
+    #[test]
+    fn checks_variable_names() {
+        for name in ["EMAIL", "USER_NAME", "id"] {
+            assert!(is_variable_name(name), "{}", name);
+        }
+
+        for name in ["", "user_id2", "first_last_name", "_NAME", "NAME_"] {
+            assert!(!is_variable_name(name), "{}", name);
+        }
+    }
 }
@@ -0,0 +1,314 @@
+//! Requests against a local TLS server that requires a client certificate signed by its CA.
+
+use p12_keystore::{Certificate, KeyStore, KeyStoreEntry, PrivateKeyChain};
+use panda_lib::config::ClientCertificate;
+use panda_lib::error::PandaError;
+use panda_lib::http_runner::{run_single_request, PandaHttpResponse, PandaTomlRequest, RunOptions};
+use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig, SupportedProtocolVersion};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+
+const P12_PASSWORD: &str = "s3cret";
+
+/// A CA, the server certificate it signed for `localhost` and the client identity files
+/// written to the project directory.
+struct Pki {
+    ca: CertificateDer<'static>,
+    server_cert: CertificateDer<'static>,
+    server_key: PrivateKeyDer<'static>,
+    project: TempDir,
+}
+
+fn create_pki() -> Pki {
+    let ca_key = KeyPair::generate().unwrap();
+    let mut ca_params = CertificateParams::new(vec![]).unwrap();
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca = ca_params.self_signed(&ca_key).unwrap();
+
+    let server_key = KeyPair::generate().unwrap();
+    let server_cert = CertificateParams::new(vec!["localhost".into()])
+        .unwrap()
+        .signed_by(&server_key, &ca, &ca_key)
+        .unwrap();
+
+    let client_key = KeyPair::generate().unwrap();
+    let client_cert = CertificateParams::new(vec!["client".into()])
+        .unwrap()
+        .signed_by(&client_key, &ca, &ca_key)
+        .unwrap();
+
+    let project = tempfile::tempdir().unwrap();
+    let write = |name: &str, content: &[u8]| fs::write(project.path().join(name), content).unwrap();
+
+    write("ca.pem", ca.pem().as_bytes());
+    write("client.pem", client_cert.pem().as_bytes());
+    write("client.key", client_key.serialize_pem().as_bytes());
+
+    let chain = PrivateKeyChain::new(
+        client_key.serialize_der(),
+        [1; 20],
+        vec![Certificate::from_der(client_cert.der()).unwrap()],
+    );
+    let mut keystore = KeyStore::new();
+    keystore.add_entry("client", KeyStoreEntry::PrivateKeyChain(chain));
+    write(
+        "client.p12",
+        &keystore.writer(P12_PASSWORD).write().unwrap(),
+    );
+
+    Pki {
+        ca: ca.der().clone(),
+        server_cert: server_cert.der().clone(),
+        server_key: PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(server_key.serialize_der())),
+        project,
+    }
+}
+
+/// Starts a server answering `hello` to clients presenting a certificate signed by the CA,
+/// or redirecting `/redirect` to `127.0.0.1`, returns its port.
+async fn start_server(pki: &Pki) -> u16 {
+    start_server_with_versions(pki, rustls::DEFAULT_VERSIONS).await
+}
+
+async fn start_server_with_versions(
+    pki: &Pki,
+    versions: &[&'static SupportedProtocolVersion],
+) -> u16 {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let mut roots = RootCertStore::empty();
+    roots.add(pki.ca.clone()).unwrap();
+
+    let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .build()
+        .unwrap();
+
+    let config = ServerConfig::builder_with_provider(provider)
+        .with_protocol_versions(versions)
+        .unwrap()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(vec![pki.server_cert.clone()], pki.server_key.clone_key())
+        .unwrap();
+
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+
+            tokio::spawn(async move {
+                let Ok(mut tls) = acceptor.accept(stream).await else {
+                    return;
+                };
+
+                let mut request = [0; 4096];
+                let _ = tls.read(&mut request).await;
+
+                let response = if request.starts_with(b"GET /redirect ") {
+                    format!(
+                        "HTTP/1.1 302 Found\r\nLocation: https://127.0.0.1:{}/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        port
+                    )
+                } else {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello".into()
+                };
+
+                let _ = tls.write_all(response.as_bytes()).await;
+                let _ = tls.shutdown().await;
+            });
+        }
+    });
+
+    port
+}
+
+async fn send(
+    port: u16,
+    path: &str,
+    project: &Path,
+    certificate: ClientCertificate,
+    variables: HashMap<String, String>,
+) -> Result<PandaHttpResponse, PandaError> {
+    let request: PandaTomlRequest = toml::from_str(&format!(
+        "[get]\nurl = \"https://localhost:{}{}\"",
+        port, path
+    ))
+    .unwrap();
+
+    let options = RunOptions {
+        project_root: project.to_path_buf(),
+        client_certificates: vec![certificate],
+        variables,
+        ..Default::default()
+    };
+
+    let (_cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+
+    run_single_request(request, &options, cancel_rx).await
+}
+
+fn pem_certificate(host: &str) -> ClientCertificate {
+    ClientCertificate {
+        host: host.into(),
+        cert: Some("./client.pem".into()),
+        key: Some("./client.key".into()),
+        pkcs12: None,
+        passphrase: None,
+        ca: Some("./ca.pem".into()),
+    }
+}
+
+#[tokio::test]
+async fn presents_pem_identity() {
+    let pki = create_pki();
+    let port = start_server(&pki).await;
+
+    let response = send(
+        port,
+        "/",
+        pki.project.path(),
+        pem_certificate("localhost"),
+        HashMap::new(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response.status, 200);
+}
+
+#[tokio::test]
+async fn presents_pkcs12_identity_with_passphrase_variable() {
+    let pki = create_pki();
+    let port = start_server(&pki).await;
+
+    let certificate = ClientCertificate {
+        host: "localhost".into(),
+        cert: None,
+        key: None,
+        pkcs12: Some("client.p12".into()),
+        passphrase: Some("_.CERT_PASSWORD".into()),
+        ca: Some("./ca.pem".into()),
+    };
+    let variables = HashMap::from([("CERT_PASSWORD".into(), P12_PASSWORD.into())]);
+
+    let response = send(port, "/", pki.project.path(), certificate, variables)
+        .await
+        .unwrap();
+
+    assert_eq!(response.status, 200);
+}
+
+#[tokio::test]
+async fn matches_hosts_with_case_insensitive_globs() {
+    let pki = create_pki();
+    let port = start_server(&pki).await;
+
+    for host in ["local*", "LOCALHOST", "?ocalhos[st]"] {
+        let response = send(
+            port,
+            "/",
+            pki.project.path(),
+            pem_certificate(host),
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status, 200, "{}", host);
+    }
+}
+
+#[tokio::test]
+async fn handshake_fails_when_no_glob_matches() {
+    let pki = create_pki();
+    let port = start_server(&pki).await;
+
+    // Neither the identity nor the `ca` apply, the server certificate isn't trusted either.
+    for host in ["*.localhost", "example.com"] {
+        let result = send(
+            port,
+            "/",
+            pki.project.path(),
+            pem_certificate(host),
+            HashMap::new(),
+        )
+        .await;
+
+        assert!(result.is_err(), "{}", host);
+    }
+}
+
+#[tokio::test]
+async fn reports_negotiated_tls_parameters() {
+    let pki = create_pki();
+
+    let cases = [
+        (&rustls::version::TLS13, "TLSv1.3", Some("TLS13_")),
+        // rustls keeps the TLS 1.2 suite to itself.
+        (&rustls::version::TLS12, "TLSv1.2", None),
+    ];
+
+    for (version, name, suite_prefix) in cases {
+        let port = start_server_with_versions(&pki, &[version]).await;
+
+        let response = send(
+            port,
+            "/",
+            pki.project.path(),
+            pem_certificate("localhost"),
+            HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        let tls = response.connection.tls.unwrap();
+
+        assert_eq!(tls.version, name);
+        match suite_prefix {
+            Some(prefix) => assert!(
+                tls.cipher_suite.as_ref().unwrap().starts_with(prefix),
+                "{:?}",
+                tls.cipher_suite
+            ),
+            None => assert_eq!(tls.cipher_suite, None),
+        }
+        // The test server doesn't offer any ALPN protocol.
+        assert_eq!(tls.alpn, None);
+        assert_eq!(tls.peer_certificates.len(), 1);
+    }
+}
+
+#[tokio::test]
+async fn refuses_redirects_to_other_hosts() {
+    let pki = create_pki();
+    let port = start_server(&pki).await;
+
+    let response = send(
+        port,
+        "/redirect",
+        pki.project.path(),
+        pem_certificate("localhost"),
+        HashMap::new(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(response.status, 302);
+    assert_eq!(response.redirects.len(), 1);
+    assert_eq!(
+        response.redirects[0].location,
+        format!("https://127.0.0.1:{}/", port)
+    );
+    assert!(response.redirects[0].refused.is_some());
+}
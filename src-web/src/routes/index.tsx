@@ -81,7 +81,7 @@ x-redis-ratelimit = "2000"
 
 type Response = {
   status: number;
-  headers: Record<string, unknown> | null;
+  headers: { name: string; value: string }[] | null;
   text_response: string | null;
   elapsed_time: number;
   content_type: string;
@@ -121,6 +121,7 @@ function Index() {
         const request = (await invoke("cmd_http_request", {
           toml_schema: toml,
           default_variables: JSON.stringify(variables),
+          file_path: activeFile?.path || null,
         })) as Response;
 
         console.log("request", request);
@@ -422,7 +423,7 @@ const RequestUI = ({
             </div>
             <div className="flex items-center">
               <Dot className="h-7" />
-              <span className="text-sm ">{data.elapsed_time}s</span>
+              <span className="text-sm ">{Math.round(data.elapsed_time)}ms</span>
             </div>
           </div>
         )}